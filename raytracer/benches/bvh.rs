@@ -0,0 +1,115 @@
+//! Compares closest-hit queries against a linear `HittableList` and a `Bvh`.
+//!
+//! Run with `cargo bench --bench bvh`.
+
+use std::time::{Duration, Instant};
+
+use raytracer::bvh::Bvh;
+use raytracer::hittable::{Hittable, HittableList, MaterialId};
+use raytracer::ray::Ray;
+use raytracer::rng::Pcg32;
+use raytracer::shapes::{Sphere, Triangle};
+use raytracer::vector::Vector3;
+
+const SPHERES: usize = 200_000;
+/// Vertices per side of the terrain grid; the mesh has `2 * (GRID - 1)^2` triangles.
+const GRID: usize = 256;
+const RAYS: usize = 2_000;
+
+fn random_point(rng: &mut Pcg32, scale: f32) -> Vector3 {
+    Vector3::new(
+        scale * (2.0 * rng.next_f32() - 1.0),
+        scale * (2.0 * rng.next_f32() - 1.0),
+        scale * (2.0 * rng.next_f32() - 1.0),
+    )
+}
+
+/// A bumpy heightfield spanning `[-50, 50]` in x and z.
+fn terrain() -> Vec<Triangle> {
+    let vertex = |i: usize, j: usize| {
+        let x = 100.0 * i as f32 / (GRID - 1) as f32 - 50.0;
+        let z = 100.0 * j as f32 / (GRID - 1) as f32 - 50.0;
+        Vector3::new(x, 3.0 * (0.3 * x).sin() * (0.2 * z).cos(), z)
+    };
+
+    let mut triangles = Vec::with_capacity(2 * (GRID - 1) * (GRID - 1));
+    for i in 0..GRID - 1 {
+        for j in 0..GRID - 1 {
+            let (a, b) = (vertex(i, j), vertex(i + 1, j));
+            let (c, d) = (vertex(i + 1, j + 1), vertex(i, j + 1));
+            triangles.push(Triangle::new(a, b, c, MaterialId(0)));
+            triangles.push(Triangle::new(a, c, d, MaterialId(0)));
+        }
+    }
+    triangles
+}
+
+/// Cast every ray at `objects`, returning the elapsed time and the number of hits.
+fn cast(objects: &dyn Hittable, rays: &[Ray]) -> (Duration, usize) {
+    let start = Instant::now();
+    let hits = rays
+        .iter()
+        .filter(|ray| objects.hit(ray, 1e-3, f32::INFINITY).is_some())
+        .count();
+    (start.elapsed(), hits)
+}
+
+fn compare<T: Hittable + Copy + 'static>(name: &str, primitives: &[T], rays: &[Ray]) {
+    let mut list = HittableList::new();
+    let mut objects: Vec<Box<dyn Hittable>> = Vec::with_capacity(primitives.len());
+    for primitive in primitives {
+        list.add(*primitive);
+        objects.push(Box::new(*primitive));
+    }
+
+    let start = Instant::now();
+    let bvh = Bvh::new(objects);
+    let build = start.elapsed();
+
+    let (linear_time, linear_hits) = cast(&list, rays);
+    let (bvh_time, bvh_hits) = cast(&bvh, rays);
+    assert_eq!(linear_hits, bvh_hits, "BVH and linear search disagree.");
+
+    let per_ray = |t: Duration| t / rays.len() as u32;
+    println!("{} {}, {} rays", primitives.len(), name, rays.len());
+    println!("  BVH build:     {:>10.2?} (depth {})", build, bvh.depth());
+    println!(
+        "  linear search: {:>10.2?} ({:.2?}/ray)",
+        linear_time,
+        per_ray(linear_time)
+    );
+    println!(
+        "  BVH search:    {:>10.2?} ({:.2?}/ray)",
+        bvh_time,
+        per_ray(bvh_time)
+    );
+    println!(
+        "  speedup:       {:>10.1}x",
+        linear_time.as_secs_f64() / bvh_time.as_secs_f64()
+    );
+}
+
+fn main() {
+    let mut rng = Pcg32::new(0, 0);
+
+    let spheres: Vec<Sphere> = (0..SPHERES)
+        .map(|_| Sphere::new(random_point(&mut rng, 50.0), 0.1, MaterialId(0)))
+        .collect();
+    let rays: Vec<Ray> = (0..RAYS)
+        .map(|_| {
+            let origin = random_point(&mut rng, 60.0);
+            let target = random_point(&mut rng, 10.0);
+            Ray::new(origin, target - origin)
+        })
+        .collect();
+    compare("spheres", &spheres, &rays);
+
+    let rays: Vec<Ray> = (0..RAYS)
+        .map(|_| {
+            let origin = Vector3::new(0.0, 40.0, 0.0) + random_point(&mut rng, 20.0);
+            let target = Vector3::new(80.0, 0.0, 80.0).cwise_mul(random_point(&mut rng, 0.5));
+            Ray::new(origin, target - origin)
+        })
+        .collect();
+    compare("triangles", &terrain(), &rays);
+}
@@ -0,0 +1,72 @@
+//! Times `Vector3` arithmetic in `f32` and `f64`.
+//!
+//! Run with `cargo bench --bench vector`, and again with `--features simd` to compare the SSE
+//! backend for `f32` against the scalar one. The SSE kernels load and store a lane per call, so
+//! chains of dependent operations (such as `normalized`) can come out slower than scalar code
+//! that the compiler is free to vectorize or keep in registers.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use raytracer::float::Float;
+use raytracer::rng::Pcg32;
+use raytracer::vector::Vector3;
+
+const VECTORS: usize = 4_096;
+const ROUNDS: usize = 2_000;
+
+fn random_vectors<T: Float>(rng: &mut Pcg32) -> Vec<Vector3<T>> {
+    (0..VECTORS)
+        .map(|_| {
+            Vector3::new(
+                T::from_f64(rng.next_f32() as f64 - 0.5),
+                T::from_f64(rng.next_f32() as f64 - 0.5),
+                T::from_f64(rng.next_f32() as f64 - 0.5),
+            )
+        })
+        .collect()
+}
+
+/// Time `ROUNDS` passes of `f` over every adjacent pair in `vectors`.
+fn time<T: Float>(vectors: &[Vector3<T>], f: impl Fn(Vector3<T>, Vector3<T>) -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        let mut acc = T::ZERO;
+        for pair in vectors.windows(2) {
+            acc = acc + f(black_box(pair[0]), black_box(pair[1]));
+        }
+        black_box(acc);
+    }
+    start.elapsed()
+}
+
+fn run<T: Float>(name: &str, rng: &mut Pcg32) {
+    let vectors = random_vectors::<T>(rng);
+    let ops = (ROUNDS * (VECTORS - 1)) as f64;
+    let report = |op: &str, t: Duration| {
+        let per_op = 1e9 * t.as_secs_f64() / ops;
+        println!("  {:<14} {:>10.2?} ({:.2}ns/op)", op, t, per_op)
+    };
+
+    println!("{}", name);
+    report("add + sub", time(&vectors, |a, b| ((a + b) - b).x()));
+    report("scale", time(&vectors, |a, b| (a * b.y()).z()));
+    report("cwise_mul", time(&vectors, |a, b| a.cwise_mul(b).x()));
+    report("dot", time(&vectors, |a, b| a.dot(b)));
+    report("cross", time(&vectors, |a, b| a.cross(b).y()));
+    report(
+        "normalized",
+        time(&vectors, |a, b| (a + b).normalized().z()),
+    );
+}
+
+fn main() {
+    let backend = if cfg!(all(feature = "simd", target_arch = "x86_64")) {
+        "SSE"
+    } else {
+        "scalar"
+    };
+    let mut rng = Pcg32::new(0, 0);
+    run::<f32>(&format!("f32 ({})", backend), &mut rng);
+    run::<f64>("f64 (scalar)", &mut rng);
+}
@@ -0,0 +1,131 @@
+use crate::ray::Ray;
+use crate::vector::Vector3;
+
+/// An axis-aligned bounding box spanning `min` to `max`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vector3,
+    pub max: Vector3,
+}
+
+impl Aabb {
+    /// Create a new `Aabb` spanning `min` to `max`.
+    pub fn new(min: Vector3, max: Vector3) -> Self {
+        Aabb { min, max }
+    }
+
+    /// Create an empty `Aabb` that contains nothing; the identity for `union`.
+    pub fn empty() -> Self {
+        Aabb::new(
+            Vector3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            Vector3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        )
+    }
+
+    /// Create the smallest `Aabb` containing every point in `points`.
+    pub fn from_points(points: impl IntoIterator<Item = Vector3>) -> Self {
+        points
+            .into_iter()
+            .fold(Aabb::empty(), |aabb, p| aabb.union(Aabb::new(p, p)))
+    }
+
+    /// Return the smallest `Aabb` containing both this box and `other`.
+    pub fn union(self, other: Aabb) -> Aabb {
+        Aabb::new(
+            self.min.cwise(other.min, f32::min),
+            self.max.cwise(other.max, f32::max),
+        )
+    }
+
+    /// Return whether this box contains no points.
+    pub fn is_empty(&self) -> bool {
+        self.min.x() > self.max.x() || self.min.y() > self.max.y() || self.min.z() > self.max.z()
+    }
+
+    /// Return the center of this box.
+    pub fn centroid(&self) -> Vector3 {
+        0.5 * (self.min + self.max)
+    }
+
+    /// Return the extent of this box along each axis.
+    pub fn extent(&self) -> Vector3 {
+        self.max - self.min
+    }
+
+    /// Return the total area of the faces of this box, or zero if it is empty.
+    pub fn surface_area(&self) -> f32 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let d = self.extent();
+        2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
+    /// Return the index of the axis along which this box is longest.
+    pub fn longest_axis(&self) -> usize {
+        let d = self.extent();
+        if d.x() >= d.y() && d.x() >= d.z() {
+            0
+        } else if d.y() >= d.z() {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Return whether `ray` passes through this box with `t` in `(t_min, t_max)`.
+    pub fn hit(&self, ray: &Ray, mut t_min: f32, mut t_max: f32) -> bool {
+        for axis in 0..3 {
+            let inv_d = 1.0 / ray.direction[axis];
+            let origin = ray.origin[axis];
+            let mut t0 = (self.min[axis] - origin) * inv_d;
+            let mut t1 = (self.max[axis] - origin) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            t_min = t0.max(t_min);
+            t_max = t1.min(t_max);
+            if t_max < t_min {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_union_and_area() {
+        let a = Aabb::new(Vector3::zeros(), Vector3::ones());
+        let b = Aabb::new(Vector3::new(2.0, 0.0, 0.0), Vector3::new(3.0, 1.0, 1.0));
+        let u = a.union(b);
+        assert_eq!(Aabb::new(Vector3::zeros(), Vector3::new(3.0, 1.0, 1.0)), u);
+        assert_eq!(6.0, a.surface_area());
+        assert_eq!(14.0, u.surface_area());
+        assert_eq!(0, u.longest_axis());
+
+        assert!(Aabb::empty().is_empty());
+        assert_eq!(0.0, Aabb::empty().surface_area());
+        assert_eq!(a, Aabb::empty().union(a));
+    }
+
+    #[test]
+    fn test_hit() {
+        let aabb = Aabb::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::ones());
+        let through = Ray::new(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!(aabb.hit(&through, 0.0, f32::INFINITY));
+        assert!(
+            !aabb.hit(&through, 0.0, 3.0),
+            "The box starts at t = 4, beyond t_max."
+        );
+
+        let diagonal_miss = Ray::new(Vector3::new(0.0, 3.0, 3.0), Vector3::new(0.0, 1.0, -1.0));
+        assert!(!aabb.hit(&diagonal_miss, 0.0, f32::INFINITY));
+
+        let behind = Ray::new(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(!aabb.hit(&behind, 0.0, f32::INFINITY));
+    }
+}
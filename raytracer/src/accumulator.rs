@@ -0,0 +1,320 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::framebuffer::{luminance, Framebuffer, ImageError};
+use crate::vector::Color;
+
+/// Identifies an accumulator file, and its format version.
+const MAGIC: &[u8; 8] = b"RTACC\x00\x00\x02";
+
+/// Bytes stored per pixel: three channel sums, the sum of squared luminance, and the count.
+const PIXEL_BYTES: usize = 20;
+
+/// Running statistics of the radiance samples taken through one pixel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PixelStats {
+    pub sum: Color,
+    /// Sum of the squared luminance of every sample.
+    pub squares: f32,
+    pub count: u32,
+}
+
+impl Default for PixelStats {
+    fn default() -> Self {
+        PixelStats {
+            sum: Color::zeros(),
+            squares: 0.0,
+            count: 0,
+        }
+    }
+}
+
+impl PixelStats {
+    /// Add one sample.
+    pub fn add(&mut self, color: Color) {
+        self.sum += color;
+        self.squares += luminance(color).powi(2);
+        self.count += 1;
+    }
+
+    /// Return the mean of the samples, or black if there are none.
+    pub fn mean(&self) -> Color {
+        if self.count == 0 {
+            Color::zeros()
+        } else {
+            self.sum / self.count as f32
+        }
+    }
+
+    /// Return the unbiased sample variance of the luminance of one sample, or zero if there are
+    /// fewer than two.
+    pub fn variance(&self) -> f32 {
+        if self.count < 2 {
+            return 0.0;
+        }
+        let n = self.count as f32;
+        let mean = luminance(self.sum) / n;
+        ((self.squares - n * mean * mean) / (n - 1.0)).max(0.0)
+    }
+
+    /// Return the estimated variance of the mean's luminance.
+    pub fn variance_of_mean(&self) -> f32 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.variance() / self.count as f32
+        }
+    }
+
+    /// Return whether the standard error of the mean luminance is within `threshold` times the
+    /// mean. Near-black pixels are held to an absolute error of `threshold / 1000` instead.
+    pub fn converged(&self, threshold: f32) -> bool {
+        let mean = luminance(self.mean()).max(1e-3);
+        self.count >= 2 && self.variance_of_mean().sqrt() <= threshold * mean
+    }
+}
+
+/// Running per-pixel statistics of radiance samples, built up over successive render passes.
+///
+/// An accumulator remembers the seed it was rendered with and how many passes it holds, so a
+/// render saved part-way through can be resumed and finish exactly as if it had never stopped.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Accumulator {
+    width: usize,
+    height: usize,
+    pixels: Vec<PixelStats>,
+    seed: u64,
+    passes: u32,
+    samples: u32,
+}
+
+impl Accumulator {
+    /// Create a new, empty `Accumulator` for a render of `width` by `height` pixels with `seed`.
+    pub fn new(width: usize, height: usize, seed: u64) -> Self {
+        Accumulator {
+            width,
+            height,
+            pixels: vec![PixelStats::default(); width * height],
+            seed,
+            passes: 0,
+            samples: 0,
+        }
+    }
+
+    /// Return the width of the image in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Return the height of the image in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Return the seed this accumulator is rendered with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Return the number of passes added so far.
+    pub fn passes(&self) -> u32 {
+        self.passes
+    }
+
+    /// Return the number of samples per pixel requested so far. Pixels that converged early
+    /// under adaptive sampling hold fewer.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Return the statistics of the pixel at column `x` and row `y`.
+    pub fn pixel(&self, x: usize, y: usize) -> PixelStats {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Return the statistics of every pixel, in row-major order from the top-left.
+    pub fn pixels(&self) -> &[PixelStats] {
+        &self.pixels
+    }
+
+    /// Finish a pass of up to `samples` samples per pixel, replacing the statistics with
+    /// `pixels`, the running totals including that pass.
+    ///
+    /// Panics if `pixels` does not match the accumulator's size.
+    pub fn add_pass(&mut self, pixels: Vec<PixelStats>, samples: u32) {
+        assert_eq!(
+            self.pixels.len(),
+            pixels.len(),
+            "Pass size does not match the accumulator."
+        );
+        self.pixels = pixels;
+        self.passes += 1;
+        self.samples += samples;
+    }
+
+    /// Return the mean of the samples so far at every pixel, or black where there are none.
+    pub fn image(&self) -> Framebuffer {
+        Framebuffer::from_fn(self.width, self.height, |x, y| self.pixel(x, y).mean())
+    }
+
+    /// Return the estimated variance of the mean luminance at every pixel, in row-major order.
+    pub fn variance(&self) -> Vec<f32> {
+        self.pixels
+            .iter()
+            .map(PixelStats::variance_of_mean)
+            .collect()
+    }
+
+    /// Serialize this accumulator in a compact little-endian binary format.
+    pub fn write(&self, mut w: impl Write) -> std::io::Result<()> {
+        w.write_all(MAGIC)?;
+        w.write_all(&(self.width as u32).to_le_bytes())?;
+        w.write_all(&(self.height as u32).to_le_bytes())?;
+        w.write_all(&self.seed.to_le_bytes())?;
+        w.write_all(&self.passes.to_le_bytes())?;
+        w.write_all(&self.samples.to_le_bytes())?;
+        for pixel in &self.pixels {
+            for c in [pixel.sum.x(), pixel.sum.y(), pixel.sum.z(), pixel.squares] {
+                w.write_all(&c.to_le_bytes())?;
+            }
+            w.write_all(&pixel.count.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Deserialize an accumulator written by `write`.
+    pub fn read(mut r: impl Read) -> Result<Accumulator, ImageError> {
+        let mut magic = [0; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(ImageError::Malformed(
+                "not a raytracer accumulator file".to_string(),
+            ));
+        }
+        let mut u32_bytes = [0; 4];
+        let mut read_u32 = |r: &mut dyn Read| -> std::io::Result<u32> {
+            r.read_exact(&mut u32_bytes)?;
+            Ok(u32::from_le_bytes(u32_bytes))
+        };
+        let width = read_u32(&mut r)? as usize;
+        let height = read_u32(&mut r)? as usize;
+        let mut seed = [0; 8];
+        r.read_exact(&mut seed)?;
+        let passes = read_u32(&mut r)?;
+        let samples = read_u32(&mut r)?;
+
+        let expected = PIXEL_BYTES
+            .checked_mul(width)
+            .and_then(|n| n.checked_mul(height))
+            .ok_or_else(|| {
+                ImageError::Malformed(format!("size {}x{} is too large", width, height))
+            })?;
+        let mut data = Vec::new();
+        r.read_to_end(&mut data)?;
+        if data.len() != expected {
+            return Err(ImageError::Malformed(format!(
+                "expected {} bytes of pixel data for {}x{}, got {}",
+                expected,
+                width,
+                height,
+                data.len()
+            )));
+        }
+        let pixels = data
+            .chunks_exact(PIXEL_BYTES)
+            .map(|bytes| {
+                let word = |i: usize| [bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]];
+                let float = |i: usize| f32::from_le_bytes(word(i));
+                PixelStats {
+                    sum: Color::new(float(0), float(4), float(8)),
+                    squares: float(12),
+                    count: u32::from_le_bytes(word(16)),
+                }
+            })
+            .collect();
+        Ok(Accumulator {
+            width,
+            height,
+            pixels,
+            seed: u64::from_le_bytes(seed),
+            passes,
+            samples,
+        })
+    }
+
+    /// Write this accumulator to `path`. The file is replaced atomically, so an interrupted save
+    /// leaves the previous checkpoint intact.
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        let mut w = BufWriter::new(File::create(&partial)?);
+        self.write(&mut w)?;
+        w.into_inner()?.sync_all()?;
+        fs::rename(&partial, path)
+    }
+
+    /// Load an accumulator saved by `save`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Accumulator, ImageError> {
+        Accumulator::read(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pixel_stats() {
+        let mut stats = PixelStats::default();
+        assert_eq!(Color::zeros(), stats.mean());
+        assert!(!stats.converged(1.0));
+        for _ in 0..4 {
+            stats.add(Color::ones() * 0.5);
+        }
+        assert_eq!(Color::ones() * 0.5, stats.mean());
+        assert!(stats.variance().abs() < 1e-6);
+        assert!(stats.converged(0.01));
+
+        // Samples of 0 and 2 have variance 4/3 with Bessel's correction.
+        let mut noisy = PixelStats::default();
+        for i in 0..4 {
+            noisy.add(Color::ones() * (2 * (i % 2)) as f32);
+        }
+        assert!((noisy.variance() - 4.0 / 3.0).abs() < 1e-5);
+        assert!((noisy.variance_of_mean() - 1.0 / 3.0).abs() < 1e-5);
+        assert!(!noisy.converged(0.1));
+        assert!(noisy.converged(1.0));
+    }
+
+    #[test]
+    fn test_accumulate_and_round_trip() {
+        let mut acc = Accumulator::new(3, 2, 42);
+        assert_eq!(Color::zeros(), acc.image().get(1, 1));
+        let mut pixels = acc.pixels().to_vec();
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            pixel.add(Color::ones() * i as f32);
+            pixel.add(Color::ones() * (i + 1) as f32);
+        }
+        acc.add_pass(pixels, 2);
+        assert_eq!((1, 2), (acc.passes(), acc.samples()));
+        assert_eq!(Color::ones() * 5.5, acc.image().get(2, 1));
+        assert_eq!(2, acc.pixel(2, 1).count);
+        assert_eq!(6, acc.variance().len());
+
+        let mut bytes = Vec::new();
+        acc.write(&mut bytes).unwrap();
+        assert_eq!(acc, Accumulator::read(&bytes[..]).unwrap());
+
+        let err = Accumulator::read(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert!(matches!(err, ImageError::Malformed(_)), "Got {}.", err);
+        let mut huge = bytes.clone();
+        huge[8..16].fill(0xff);
+        let err = Accumulator::read(&huge[..]).unwrap_err();
+        assert!(err.to_string().contains("too large"), "Got {}.", err);
+        bytes[0] = b'P';
+        let err = Accumulator::read(&bytes[..]).unwrap_err();
+        assert!(matches!(err, ImageError::Malformed(_)), "Got {}.", err);
+    }
+}
@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+
+use crate::aabb::Aabb;
+use crate::camera::CameraSettings;
+use crate::hittable::{HitRecord, Hittable, TraversalStats};
+use crate::ray::Ray;
+use crate::vector::Vector3;
+
+/// Find `at` among `keys`, which must be non-empty and strictly ascending by `key`. Returns the
+/// indices of the keys on either side and how far `at` is from the first to the second. Outside
+/// the keys, both indices are those of the nearest end.
+fn locate<T>(keys: &[T], key: impl Fn(&T) -> f32, at: f32) -> (usize, usize, f32) {
+    let next = keys.partition_point(|k| key(k) <= at);
+    if next == 0 {
+        return (0, 0, 0.0);
+    }
+    if next == keys.len() {
+        return (next - 1, next - 1, 0.0);
+    }
+    let (a, b) = (key(&keys[next - 1]), key(&keys[next]));
+    (next - 1, next, (at - a) / (b - a))
+}
+
+/// Where a moving object is at one instant, as an offset from where it is described.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MotionKey {
+    pub time: f32,
+    pub offset: Vector3,
+}
+
+impl MotionKey {
+    /// Create a new `MotionKey` placing an object at `offset` at `time`.
+    pub fn new(time: f32, offset: Vector3) -> Self {
+        MotionKey { time, offset }
+    }
+}
+
+/// A hittable that moves over time, translated by offsets interpolated linearly between `keys`.
+/// Before the first key and after the last it holds still.
+///
+/// The keys must be in ascending order of time. Rays find the object where it is at their own
+/// time, so rays spread over a shutter interval blur it along its path.
+pub struct Moving {
+    pub object: Box<dyn Hittable>,
+    pub keys: Vec<MotionKey>,
+}
+
+impl Moving {
+    /// Create a new `Moving` object following `keys`.
+    pub fn new(object: impl Hittable + 'static, keys: Vec<MotionKey>) -> Self {
+        Moving {
+            object: Box::new(object),
+            keys,
+        }
+    }
+
+    /// Return the offset of the object at `time`.
+    pub fn offset(&self, time: f32) -> Vector3 {
+        if self.keys.is_empty() {
+            return Vector3::zeros();
+        }
+        let (a, b, t) = locate(&self.keys, |k| k.time, time);
+        self.keys[a].offset.lerp(self.keys[b].offset, t)
+    }
+}
+
+impl Hittable for Moving {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.hit_counted(ray, t_min, t_max, &mut TraversalStats::default())
+    }
+
+    fn hit_counted(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord> {
+        // Move the ray rather than the object. Translation keeps t and the normal unchanged.
+        let offset = self.offset(ray.time);
+        let local = Ray::with_time(ray.origin - offset, ray.direction, ray.time);
+        let mut hit = self.object.hit_counted(&local, t_min, t_max, stats)?;
+        hit.point = ray.at(hit.t);
+        Some(hit)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // The path is a chain of straight segments, so the boxes at its corners cover it.
+        let bounds = self.object.bounding_box()?;
+        if self.keys.is_empty() {
+            return Some(bounds);
+        }
+        Some(self.keys.iter().fold(Aabb::empty(), |all, key| {
+            all.union(Aabb::new(bounds.min + key.offset, bounds.max + key.offset))
+        }))
+    }
+}
+
+/// Camera settings at a keyframe of an animation. Settings that are left out are taken from the
+/// scene's camera.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraKeyframe {
+    pub frame: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub look_from: Option<Vector3>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub look_at: Option<Vector3>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub up: Option<Vector3>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vfov: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aperture: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub focus_distance: Option<f32>,
+}
+
+impl CameraKeyframe {
+    /// Return `camera` with the settings of this keyframe applied.
+    pub fn apply(&self, camera: &CameraSettings) -> CameraSettings {
+        CameraSettings {
+            look_from: self.look_from.unwrap_or(camera.look_from),
+            look_at: self.look_at.unwrap_or(camera.look_at),
+            up: self.up.unwrap_or(camera.up),
+            vfov: self.vfov.unwrap_or(camera.vfov),
+            aperture: self.aperture.unwrap_or(camera.aperture),
+            focus_distance: self.focus_distance.or(camera.focus_distance),
+            ..camera.clone()
+        }
+    }
+}
+
+fn default_frame_rate() -> f32 {
+    24.0
+}
+
+/// How a scene changes over a sequence of frames.
+///
+/// Frame `i` starts at time `i / frame_rate`, in the same units as the camera shutter and the
+/// keys of moving objects, and is exposed from `shutter_open` to `shutter_close` after that.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Animation {
+    /// Number of frames to render.
+    pub frames: u32,
+    /// Frames per unit of time.
+    #[serde(default = "default_frame_rate")]
+    pub frame_rate: f32,
+    /// Camera keyframes, in ascending order of frame. The camera moves linearly from each to the
+    /// next, and holds still before the first and after the last.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub camera: Vec<CameraKeyframe>,
+}
+
+impl Animation {
+    /// Create a new `Animation` of `frames` frames at `frame_rate` with a still camera.
+    pub fn new(frames: u32, frame_rate: f32) -> Self {
+        Animation {
+            frames,
+            frame_rate,
+            camera: Vec::new(),
+        }
+    }
+
+    /// Return the time at which `frame` starts.
+    pub fn frame_time(&self, frame: u32) -> f32 {
+        frame as f32 / self.frame_rate
+    }
+
+    /// Return the settings of `camera` as the keyframes animate them at `time`.
+    pub fn camera_at(&self, camera: &CameraSettings, time: f32) -> CameraSettings {
+        if self.camera.is_empty() {
+            return camera.clone();
+        }
+        let (a, b, t) = locate(&self.camera, |k| k.frame as f32, time * self.frame_rate);
+        self.camera[a]
+            .apply(camera)
+            .lerp(&self.camera[b].apply(camera), t)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hittable::MaterialId;
+    use crate::shapes::Sphere;
+
+    #[test]
+    fn test_moving() {
+        let sphere = Sphere::new(Vector3::zeros(), 1.0, MaterialId(0));
+        let moving = Moving::new(
+            sphere,
+            vec![
+                MotionKey::new(0.0, Vector3::zeros()),
+                MotionKey::new(1.0, Vector3::new(4.0, 0.0, 0.0)),
+                MotionKey::new(2.0, Vector3::new(4.0, 2.0, 0.0)),
+            ],
+        );
+        assert_eq!(Vector3::zeros(), moving.offset(-1.0));
+        assert_eq!(Vector3::new(1.0, 0.0, 0.0), moving.offset(0.25));
+        assert_eq!(Vector3::new(4.0, 1.0, 0.0), moving.offset(1.5));
+        assert_eq!(Vector3::new(4.0, 2.0, 0.0), moving.offset(5.0));
+        assert_eq!(
+            Some(Aabb::new(
+                Vector3::new(-1.0, -1.0, -1.0),
+                Vector3::new(5.0, 3.0, 1.0)
+            )),
+            moving.bounding_box()
+        );
+
+        // A ray down the z axis hits the sphere only while it is passing the origin.
+        let origin = Vector3::new(2.0, 0.0, 5.0);
+        let direction = Vector3::new(0.0, 0.0, -1.0);
+        assert!(moving
+            .hit(&Ray::with_time(origin, direction, 0.0), 0.0, f32::INFINITY)
+            .is_none());
+        let hit = moving
+            .hit(&Ray::with_time(origin, direction, 0.5), 0.0, f32::INFINITY)
+            .unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-5, "Expected t = 4, got {}.", hit.t);
+        assert_eq!(Vector3::new(2.0, 0.0, 1.0), hit.point);
+        assert_eq!(Vector3::new(0.0, 0.0, 1.0), hit.normal);
+    }
+
+    #[test]
+    fn test_camera_keyframes() {
+        let camera = CameraSettings::new(Vector3::new(0.0, 0.0, 5.0), Vector3::zeros(), 40.0);
+        let mut animation = Animation::new(30, 10.0);
+        assert_eq!(camera, animation.camera_at(&camera, 1.0));
+        animation.camera = vec![
+            CameraKeyframe {
+                frame: 10,
+                ..CameraKeyframe::default()
+            },
+            CameraKeyframe {
+                frame: 20,
+                look_from: Some(Vector3::new(10.0, 0.0, 5.0)),
+                vfov: Some(60.0),
+                ..CameraKeyframe::default()
+            },
+        ];
+        assert_eq!(1.5, animation.frame_time(15));
+
+        // Frame 15 is halfway between the keyframes.
+        let halfway = animation.camera_at(&camera, 1.5);
+        assert_eq!(Vector3::new(5.0, 0.0, 5.0), halfway.look_from);
+        assert_eq!(Vector3::zeros(), halfway.look_at);
+        assert_eq!(50.0, halfway.vfov);
+        // Outside the keyframes, the camera holds still.
+        assert_eq!(camera, animation.camera_at(&camera, 0.0));
+        assert_eq!(
+            Vector3::new(10.0, 0.0, 5.0),
+            animation.camera_at(&camera, 2.9).look_from
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::framebuffer::Framebuffer;
+use crate::hittable::{HitRecord, TraversalStats};
+use crate::ray::Ray;
+use crate::vector::Color;
+use crate::world::World;
+
+/// An arbitrary output variable: a per-pixel quantity other than radiance, rendered as an image
+/// of its own for debugging scenes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Aov {
+    /// Shading normal of the first surface hit, facing the camera.
+    Normal,
+    /// Distance from the camera to the first surface hit.
+    Depth,
+    /// Surface color of the first surface hit, free of lighting.
+    Albedo,
+    /// Index of the scene object first hit, plus one so that misses are zero.
+    ObjectId,
+    /// Acceleration structure nodes visited plus primitives tested by the camera ray.
+    Cost,
+    /// Primitive tests by the camera ray that found an intersection.
+    Hits,
+}
+
+impl Aov {
+    /// Every AOV, in the order they are listed in help text.
+    pub const ALL: [Aov; 6] = [
+        Aov::Normal,
+        Aov::Depth,
+        Aov::Albedo,
+        Aov::ObjectId,
+        Aov::Cost,
+        Aov::Hits,
+    ];
+
+    /// Return the name of this AOV as it appears in scene files and on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Aov::Normal => "normal",
+            Aov::Depth => "depth",
+            Aov::Albedo => "albedo",
+            Aov::ObjectId => "object_id",
+            Aov::Cost => "cost",
+            Aov::Hits => "hits",
+        }
+    }
+
+    /// Return whether the raw values of this AOV may be averaged over samples within a pixel.
+    /// Object IDs may not; they come from a pixel's first sample alone.
+    pub fn filtered(self) -> bool {
+        self != Aov::ObjectId
+    }
+
+    /// Return the raw value of this AOV for a camera `ray` whose closest intersection is `hit`,
+    /// found with the work counted in `stats`. Scalars are stored in every channel.
+    pub fn value(
+        self,
+        world: &World,
+        ray: &Ray,
+        hit: Option<&HitRecord>,
+        stats: &TraversalStats,
+    ) -> Color {
+        let scalar = |value: f32| Color::ones() * value;
+        match (self, hit) {
+            (Aov::Cost, _) => scalar((stats.nodes + stats.tests) as f32),
+            (Aov::Hits, _) => scalar(stats.hits as f32),
+            (_, None) => Color::zeros(),
+            (Aov::Normal, Some(hit)) => hit.normal,
+            (Aov::Depth, Some(hit)) => scalar((hit.point - ray.origin).norm()),
+            (Aov::Albedo, Some(hit)) => world.material(hit.material).albedo(hit),
+            (Aov::ObjectId, Some(hit)) => scalar(hit.object.map_or(0.0, |id| id as f32 + 1.0)),
+        }
+    }
+
+    /// Map an image of raw values of this AOV to colors in `[0, 1]` for display.
+    ///
+    /// Normals map each component from `[-1, 1]`. Depth is shaded from white at the nearest
+    /// surface to dark grey at the farthest, leaving misses black. Object IDs get arbitrary distinct
+    /// colors. Cost and hit counts become a heatmap scaled to the image's maximum.
+    pub fn visualize(self, image: &Framebuffer) -> Framebuffer {
+        let (width, height) = (image.width(), image.height());
+        let (min, max) = image
+            .pixels()
+            .iter()
+            .map(|c| c.x())
+            .filter(|&v| v > 0.0)
+            .fold((f32::INFINITY, 0.0f32), |(lo, hi), v| {
+                (lo.min(v), hi.max(v))
+            });
+
+        Framebuffer::from_fn(width, height, |x, y| {
+            let raw = image.get(x, y);
+            match self {
+                Aov::Normal => (raw + Color::ones()) * 0.5,
+                Aov::Albedo => raw,
+                Aov::Depth if raw.x() <= 0.0 => Color::zeros(),
+                Aov::Depth if max > min => {
+                    Color::ones() * (0.1 + 0.9 * (max - raw.x()) / (max - min))
+                }
+                Aov::Depth => Color::ones(),
+                Aov::ObjectId => id_color(raw.x().round() as u32),
+                Aov::Cost | Aov::Hits => heat(if max > 0.0 { raw.x() / max } else { 0.0 }),
+            }
+        })
+    }
+}
+
+impl fmt::Display for Aov {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Aov {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Aov::ALL
+            .into_iter()
+            .find(|aov| aov.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Aov::ALL.iter().map(|aov| aov.name()).collect();
+                format!("unknown AOV '{}' (expected one of {})", s, names.join(", "))
+            })
+    }
+}
+
+/// Return a color for object `id`, black for zero and otherwise picked by hashing so that
+/// neighbouring IDs look different.
+fn id_color(id: u32) -> Color {
+    if id == 0 {
+        return Color::zeros();
+    }
+    // A 32-bit integer hash (lowbias32).
+    let mut h = id;
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^= h >> 16;
+    let channel = |shift: u32| 0.2 + 0.8 * ((h >> shift) & 0xff) as f32 / 255.0;
+    Color::new(channel(0), channel(8), channel(16))
+}
+
+/// Map `t` in `[0, 1]` to a heatmap running from black through red and yellow to white.
+fn heat(t: f32) -> Color {
+    let t = t.clamp(0.0, 1.0) * 3.0;
+    Color::new(
+        t.min(1.0),
+        (t - 1.0).clamp(0.0, 1.0),
+        (t - 2.0).clamp(0.0, 1.0),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_names() {
+        for aov in Aov::ALL {
+            assert_eq!(Ok(aov), aov.name().parse());
+            let json = serde_json::to_string(&aov).unwrap();
+            assert_eq!(format!("\"{}\"", aov.name()), json);
+        }
+        let err = "shadows".parse::<Aov>().unwrap_err();
+        assert!(err.contains("object_id"), "Got {}.", err);
+    }
+
+    #[test]
+    fn test_visualize() {
+        let image = Framebuffer::from_fn(3, 1, |x, _| Color::ones() * x as f32);
+
+        let depth = Aov::Depth.visualize(&image);
+        assert_eq!(Color::zeros(), depth.get(0, 0), "Misses are black.");
+        assert_eq!(Color::ones(), depth.get(1, 0), "The nearest hit is white.");
+        assert!((depth.get(2, 0) - Color::ones() * 0.1).norm() < 1e-6);
+
+        let cost = Aov::Cost.visualize(&image);
+        assert_eq!(Color::zeros(), cost.get(0, 0));
+        assert_eq!(Color::new(1.0, 0.5, 0.0), cost.get(1, 0));
+        assert_eq!(Color::ones(), cost.get(2, 0));
+
+        let ids = Aov::ObjectId.visualize(&image);
+        assert_eq!(Color::zeros(), ids.get(0, 0));
+        assert_ne!(ids.get(1, 0), ids.get(2, 0));
+
+        let normal = Framebuffer::from_fn(1, 1, |_, _| Color::new(0.0, 0.0, -1.0));
+        let shown = Aov::Normal.visualize(&normal);
+        assert_eq!(Color::new(0.5, 0.5, 0.0), shown.get(0, 0));
+    }
+}
@@ -0,0 +1,481 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, TraversalStats};
+use crate::ray::Ray;
+use crate::vector::Vector3;
+
+/// Number of buckets primitive centroids are binned into when evaluating split candidates.
+const SAH_BUCKETS: usize = 16;
+/// Cost of traversing an interior node, relative to intersecting one primitive.
+const TRAVERSAL_COST: f32 = 0.125;
+/// Nodes with at most this many primitives may become leaves when splitting does not pay off.
+const MAX_LEAF_SIZE: usize = 8;
+/// Most levels a tree may have, which bounds the traversal stack. Where surface area heuristic
+/// splits are so lopsided that they would exceed it, the build falls back to median splits.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Clone, Copy, Debug)]
+enum NodeKind {
+    /// Primitives `first..first + count` of the ordered primitive list.
+    Leaf { first: usize, count: usize },
+    /// The first child immediately follows this node; the second is at `second`.
+    Interior { second: usize, axis: usize },
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    bounds: Aabb,
+    kind: NodeKind,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct PrimitiveInfo {
+    index: usize,
+    bounds: Aabb,
+    centroid: Vector3,
+}
+
+/// A bounding volume hierarchy over a set of hittables, built with the surface area heuristic.
+///
+/// Objects without a bounding box (such as infinite planes) cannot be placed in the hierarchy;
+/// they are kept aside and tested against every ray.
+pub struct Bvh {
+    nodes: Vec<Node>,
+    objects: Vec<Box<dyn Hittable>>,
+    unbounded: Vec<Box<dyn Hittable>>,
+}
+
+impl Bvh {
+    /// Build a new `Bvh` over `objects`.
+    pub fn new(objects: Vec<Box<dyn Hittable>>) -> Self {
+        Bvh::with_max_depth(objects, MAX_DEPTH)
+    }
+
+    /// Build a new `Bvh` over `objects` with at most `max_depth` levels, which must not exceed
+    /// `MAX_DEPTH`.
+    fn with_max_depth(objects: Vec<Box<dyn Hittable>>, max_depth: usize) -> Self {
+        let mut bounded = Vec::with_capacity(objects.len());
+        let mut unbounded = Vec::new();
+        for object in objects {
+            match object.bounding_box() {
+                Some(_) => bounded.push(Some(object)),
+                None => unbounded.push(object),
+            }
+        }
+
+        let mut primitives: Vec<PrimitiveInfo> = bounded
+            .iter()
+            .enumerate()
+            .map(|(index, object)| {
+                let bounds = object.as_ref().unwrap().bounding_box().unwrap();
+                PrimitiveInfo {
+                    index,
+                    bounds,
+                    centroid: bounds.centroid(),
+                }
+            })
+            .collect();
+
+        let mut nodes = Vec::new();
+        if !primitives.is_empty() {
+            nodes.reserve(2 * primitives.len());
+            build(&mut nodes, &mut primitives, 0, max_depth);
+        }
+
+        // Store the objects in leaf order so every leaf refers to a contiguous range.
+        let objects = primitives
+            .iter()
+            .map(|info| bounded[info.index].take().unwrap())
+            .collect();
+
+        Bvh {
+            nodes,
+            objects,
+            unbounded,
+        }
+    }
+
+    /// Return the number of objects in this hierarchy, including unbounded ones.
+    pub fn len(&self) -> usize {
+        self.objects.len() + self.unbounded.len()
+    }
+
+    /// Return whether this hierarchy contains no objects.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the depth of the deepest leaf in the tree.
+    pub fn depth(&self) -> usize {
+        fn depth_from(nodes: &[Node], i: usize) -> usize {
+            match nodes[i].kind {
+                NodeKind::Leaf { .. } => 1,
+                NodeKind::Interior { second, .. } => {
+                    1 + depth_from(nodes, i + 1).max(depth_from(nodes, second))
+                }
+            }
+        }
+
+        if self.nodes.is_empty() {
+            0
+        } else {
+            depth_from(&self.nodes, 0)
+        }
+    }
+}
+
+/// Return the number of times `n`, which must be positive, must be halved, rounding up, to reach
+/// one.
+fn ceil_log2(n: usize) -> usize {
+    (usize::BITS - (n - 1).leading_zeros()) as usize
+}
+
+/// Recursively build the subtree over `primitives`, which start at `offset` in the final object
+/// order, appending its nodes to `nodes` in depth-first order. The subtree may have at most
+/// `levels` levels, which must be enough for median splits. Return the index of its root.
+fn build(
+    nodes: &mut Vec<Node>,
+    primitives: &mut [PrimitiveInfo],
+    offset: usize,
+    levels: usize,
+) -> usize {
+    let (bounds, centroid_bounds) = primitives.iter().fold(
+        (Aabb::empty(), Aabb::empty()),
+        |(bounds, centroids), info| {
+            (
+                bounds.union(info.bounds),
+                centroids.union(Aabb::new(info.centroid, info.centroid)),
+            )
+        },
+    );
+
+    let count = primitives.len();
+    let index = nodes.len();
+    nodes.push(Node {
+        bounds,
+        kind: NodeKind::Leaf {
+            first: offset,
+            count,
+        },
+    });
+
+    let axis = centroid_bounds.longest_axis();
+    let axis_min = centroid_bounds.min[axis];
+    let axis_extent = centroid_bounds.max[axis] - axis_min;
+
+    // Coincident centroids cannot be separated; make a leaf regardless of size.
+    if count == 1 || axis_extent <= 0.0 {
+        return index;
+    }
+
+    let bucket_of = |info: &PrimitiveInfo| {
+        let c = info.centroid[axis];
+        let b = (SAH_BUCKETS as f32 * (c - axis_min) / axis_extent) as usize;
+        b.min(SAH_BUCKETS - 1)
+    };
+
+    // A child of an SAH split may hold all but one primitive, so it must still have room for
+    // median splits of nearly as many.
+    let mid = if count <= 2 || ceil_log2(count) + 2 > levels {
+        primitives.sort_by(|a, b| a.centroid[axis].total_cmp(&b.centroid[axis]));
+        count / 2
+    } else {
+        let mut counts = [0usize; SAH_BUCKETS];
+        let mut bucket_bounds = [Aabb::empty(); SAH_BUCKETS];
+        for info in primitives.iter() {
+            let b = bucket_of(info);
+            counts[b] += 1;
+            bucket_bounds[b] = bucket_bounds[b].union(info.bounds);
+        }
+
+        // Cost of splitting after each bucket, sweeping from both ends.
+        let mut cost = [0.0f32; SAH_BUCKETS - 1];
+        let (mut left_bounds, mut left_count) = (Aabb::empty(), 0);
+        for b in 0..SAH_BUCKETS - 1 {
+            left_bounds = left_bounds.union(bucket_bounds[b]);
+            left_count += counts[b];
+            cost[b] = left_count as f32 * left_bounds.surface_area();
+        }
+        let (mut right_bounds, mut right_count) = (Aabb::empty(), 0);
+        for b in (1..SAH_BUCKETS).rev() {
+            right_bounds = right_bounds.union(bucket_bounds[b]);
+            right_count += counts[b];
+            cost[b - 1] += right_count as f32 * right_bounds.surface_area();
+        }
+
+        let (best_bucket, best_cost) = cost
+            .iter()
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap();
+        let best_cost = TRAVERSAL_COST + best_cost / bounds.surface_area();
+        let leaf_cost = count as f32;
+
+        if count <= MAX_LEAF_SIZE && leaf_cost <= best_cost {
+            return index;
+        }
+
+        let mid = partition(primitives, |info| bucket_of(info) <= best_bucket);
+        if mid == 0 || mid == count {
+            // Binning failed to separate the primitives; fall back to a median split.
+            primitives.sort_by(|a, b| a.centroid[axis].total_cmp(&b.centroid[axis]));
+            count / 2
+        } else {
+            mid
+        }
+    };
+
+    let (left, right) = primitives.split_at_mut(mid);
+    build(nodes, left, offset, levels - 1);
+    let second = build(nodes, right, offset + mid, levels - 1);
+    nodes[index].kind = NodeKind::Interior { second, axis };
+    index
+}
+
+/// Reorder `items` so that every element satisfying `pred` comes first, and return the number of
+/// such elements.
+fn partition<T>(items: &mut [T], pred: impl Fn(&T) -> bool) -> usize {
+    let mut mid = 0;
+    for i in 0..items.len() {
+        if pred(&items[i]) {
+            items.swap(i, mid);
+            mid += 1;
+        }
+    }
+    mid
+}
+
+impl Hittable for Bvh {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.hit_counted(ray, t_min, t_max, &mut TraversalStats::default())
+    }
+
+    fn hit_counted(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord> {
+        let mut closest = None;
+        let mut t_closest = t_max;
+
+        for object in &self.unbounded {
+            if let Some(hit) = object.hit_counted(ray, t_min, t_closest, stats) {
+                t_closest = hit.t;
+                closest = Some(hit);
+            }
+        }
+
+        if self.nodes.is_empty() {
+            return closest;
+        }
+
+        let negative = [
+            ray.direction.x() < 0.0,
+            ray.direction.y() < 0.0,
+            ray.direction.z() < 0.0,
+        ];
+        // Each level of the path to the current node leaves at most one sibling on the stack.
+        let mut stack = [0; MAX_DEPTH];
+        let mut len = 1;
+        while len > 0 {
+            len -= 1;
+            let i = stack[len];
+            let node = &self.nodes[i];
+            stats.nodes += 1;
+            if !node.bounds.hit(ray, t_min, t_closest) {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf { first, count } => {
+                    for object in &self.objects[first..first + count] {
+                        if let Some(hit) = object.hit_counted(ray, t_min, t_closest, stats) {
+                            t_closest = hit.t;
+                            closest = Some(hit);
+                        }
+                    }
+                }
+                NodeKind::Interior { second, axis } => {
+                    // Visit the child nearer the ray origin first so that hits there can cull
+                    // the farther child.
+                    let (near, far) = if negative[axis] {
+                        (second, i + 1)
+                    } else {
+                        (i + 1, second)
+                    };
+                    stack[len] = far;
+                    stack[len + 1] = near;
+                    len += 2;
+                }
+            }
+        }
+
+        closest
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        if !self.unbounded.is_empty() || self.nodes.is_empty() {
+            return None;
+        }
+        Some(self.nodes[0].bounds)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hittable::{HittableList, MaterialId};
+    use crate::rng::Pcg32;
+    use crate::shapes::{Plane, Sphere};
+
+    fn random_spheres(n: usize, rng: &mut Pcg32) -> Vec<Sphere> {
+        (0..n)
+            .map(|i| {
+                let center = Vector3::new(
+                    20.0 * rng.next_f32() - 10.0,
+                    20.0 * rng.next_f32() - 10.0,
+                    20.0 * rng.next_f32() - 10.0,
+                );
+                Sphere::new(center, 0.05 + 0.3 * rng.next_f32(), MaterialId(i))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_linear_search() {
+        let mut rng = Pcg32::new(7, 0);
+        let spheres = random_spheres(500, &mut rng);
+
+        let mut list = HittableList::new();
+        let mut objects: Vec<Box<dyn Hittable>> = Vec::new();
+        for sphere in &spheres {
+            list.add(*sphere);
+            objects.push(Box::new(*sphere));
+        }
+        let floor = Plane::new(
+            Vector3::new(0.0, -11.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            MaterialId(usize::MAX),
+        );
+        list.add(floor);
+        objects.push(Box::new(floor));
+
+        let bvh = Bvh::new(objects);
+        assert_eq!(501, bvh.len());
+        assert!(bvh.depth() > 1, "Expected a non-trivial tree.");
+        assert!(bvh.bounding_box().is_none(), "The plane is unbounded.");
+
+        for _ in 0..2000 {
+            let origin = Vector3::new(
+                30.0 * rng.next_f32() - 15.0,
+                30.0 * rng.next_f32() - 15.0,
+                30.0 * rng.next_f32() - 15.0,
+            );
+            let direction = Vector3::new(
+                rng.next_f32() - 0.5,
+                rng.next_f32() - 0.5,
+                rng.next_f32() - 0.5,
+            );
+            let ray = Ray::new(origin, direction);
+
+            let expected = list.hit(&ray, 0.001, f32::INFINITY);
+            let actual = bvh.hit(&ray, 0.001, f32::INFINITY);
+            assert_eq!(
+                expected.map(|h| h.material),
+                actual.map(|h| h.material),
+                "BVH and linear search disagree on {:?}.",
+                ray
+            );
+        }
+    }
+
+    #[test]
+    fn test_bounds_and_degenerate_input() {
+        let bvh = Bvh::new(Vec::new());
+        assert!(bvh.is_empty());
+        let ray = Ray::new(Vector3::zeros(), Vector3::new(1.0, 0.0, 0.0));
+        assert!(bvh.hit(&ray, 0.0, f32::INFINITY).is_none());
+
+        // Many primitives with the same centroid must still build.
+        let objects: Vec<Box<dyn Hittable>> = (0..100)
+            .map(|i| {
+                Box::new(Sphere::new(Vector3::zeros(), 1.0 + i as f32, MaterialId(i)))
+                    as Box<dyn Hittable>
+            })
+            .collect();
+        let bvh = Bvh::new(objects);
+        let bounds = bvh.bounding_box().unwrap();
+        assert_eq!(Vector3::new(100.0, 100.0, 100.0), bounds.max);
+        let hit = bvh.hit(&ray, 0.0, f32::INFINITY).unwrap();
+        assert_eq!(1.0, hit.t, "Expected the smallest sphere, got {:?}.", hit);
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        // Spheres growing geometrically along x, which SAH peels off one or two at a time.
+        let spheres: Vec<Sphere> = (0..100)
+            .map(|i| {
+                let x = 1.5f32.powi(i);
+                Sphere::new(Vector3::new(x, 0.0, 0.0), 0.1 * x, MaterialId(i as usize))
+            })
+            .collect();
+        let boxed = || {
+            spheres
+                .iter()
+                .map(|&sphere| Box::new(sphere) as Box<dyn Hittable>)
+                .collect()
+        };
+        let deep = Bvh::new(boxed());
+        assert!(
+            deep.depth() > 16,
+            "Expected a lopsided tree, got {}.",
+            deep.depth()
+        );
+
+        let limited = Bvh::with_max_depth(boxed(), 16);
+        assert!(limited.depth() <= 16, "Got depth {}.", limited.depth());
+        for (i, sphere) in spheres.iter().enumerate() {
+            let ray = Ray::new(
+                sphere.center + Vector3::new(0.0, 0.0, 2.0 * sphere.radius),
+                Vector3::new(0.0, 0.0, -1.0),
+            );
+            let hit = limited.hit(&ray, 0.0, f32::INFINITY);
+            assert_eq!(Some(MaterialId(i)), hit.map(|h| h.material));
+            let deep_hit = deep.hit(&ray, 0.0, f32::INFINITY);
+            assert_eq!(deep_hit.map(|h| h.material), hit.map(|h| h.material));
+        }
+    }
+
+    #[test]
+    fn test_traversal_stats() {
+        let mut rng = Pcg32::new(3, 0);
+        let objects = random_spheres(200, &mut rng)
+            .into_iter()
+            .map(|sphere| Box::new(sphere) as Box<dyn Hittable>)
+            .collect();
+        let bvh = Bvh::new(objects);
+
+        // A ray that misses the root's box visits one node and tests nothing.
+        let away = Ray::new(Vector3::new(0.0, 20.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let mut stats = TraversalStats::default();
+        assert!(bvh
+            .hit_counted(&away, 0.001, f32::INFINITY, &mut stats)
+            .is_none());
+        assert_eq!(
+            TraversalStats {
+                nodes: 1,
+                tests: 0,
+                hits: 0
+            },
+            stats
+        );
+
+        // A ray through the cloud tests far fewer than all the spheres.
+        let through = Ray::new(Vector3::new(-15.0, 0.1, 0.2), Vector3::new(1.0, 0.0, 0.0));
+        let mut stats = TraversalStats::default();
+        let hit = bvh.hit_counted(&through, 0.001, f32::INFINITY, &mut stats);
+        assert_eq!(hit, bvh.hit(&through, 0.001, f32::INFINITY));
+        assert!(stats.nodes > 1 && stats.tests < 100, "Got {:?}.", stats);
+        assert_eq!(hit.is_some(), stats.hits > 0);
+    }
+}
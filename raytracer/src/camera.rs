@@ -0,0 +1,287 @@
+use serde::{Deserialize, Serialize};
+
+use crate::ray::Ray;
+use crate::sampling::concentric_disk;
+use crate::vector::Vector3;
+
+fn default_up() -> Vector3 {
+    Vector3::new(0.0, 1.0, 0.0)
+}
+
+fn default_vfov() -> f32 {
+    90.0
+}
+
+/// Serializable description of a camera, as stored in the scene file.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraSettings {
+    pub look_from: Vector3,
+    pub look_at: Vector3,
+    #[serde(default = "default_up")]
+    pub up: Vector3,
+    /// Vertical field of view, in degrees.
+    #[serde(default = "default_vfov")]
+    pub vfov: f32,
+    /// Width over height of the image plane. Defaults to the aspect ratio of the rendered image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aspect_ratio: Option<f32>,
+    /// Diameter of the lens. Zero gives a pinhole camera with everything in focus.
+    #[serde(default)]
+    pub aperture: f32,
+    /// Distance from `look_from` to the plane of perfect focus. Defaults to the distance to
+    /// `look_at`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub focus_distance: Option<f32>,
+    /// Time at which the shutter opens. Rays are spread evenly over the time it is open, which
+    /// blurs anything that moves in the meantime.
+    #[serde(default)]
+    pub shutter_open: f32,
+    /// Time at which the shutter closes. Equal to `shutter_open` for an instantaneous exposure.
+    #[serde(default)]
+    pub shutter_close: f32,
+}
+
+impl CameraSettings {
+    /// Create settings for a pinhole camera at `look_from` looking at `look_at`.
+    pub fn new(look_from: Vector3, look_at: Vector3, vfov: f32) -> Self {
+        CameraSettings {
+            look_from,
+            look_at,
+            up: default_up(),
+            vfov,
+            aspect_ratio: None,
+            aperture: 0.0,
+            focus_distance: None,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        }
+    }
+
+    /// Return the distance from `look_from` to the plane of perfect focus.
+    pub fn focus_distance(&self) -> f32 {
+        self.focus_distance
+            .unwrap_or_else(|| (self.look_at - self.look_from).norm())
+    }
+
+    /// Interpolate linearly between these settings, at `t = 0`, and `other`, at `t = 1`. The
+    /// aspect ratio and shutter are taken from `self`.
+    pub fn lerp(&self, other: &CameraSettings, t: f32) -> CameraSettings {
+        let flerp = |a: f32, b: f32| a + (b - a) * t;
+        let focus_distance = match (self.focus_distance, other.focus_distance) {
+            (None, None) => None,
+            _ => Some(flerp(self.focus_distance(), other.focus_distance())),
+        };
+        CameraSettings {
+            look_from: self.look_from.lerp(other.look_from, t),
+            look_at: self.look_at.lerp(other.look_at, t),
+            up: self.up.lerp(other.up, t),
+            vfov: flerp(self.vfov, other.vfov),
+            aperture: flerp(self.aperture, other.aperture),
+            focus_distance,
+            ..self.clone()
+        }
+    }
+}
+
+/// Where a camera is and how it is set up at one instant.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Pose {
+    origin: Vector3,
+    upper_left: Vector3,
+    horizontal: Vector3,
+    vertical: Vector3,
+    u: Vector3,
+    v: Vector3,
+    lens_radius: f32,
+}
+
+impl Pose {
+    fn new(settings: &CameraSettings, aspect_ratio: f32) -> Self {
+        let focus_distance = settings.focus_distance();
+        let viewport_height = 2.0 * (settings.vfov.to_radians() / 2.0).tan();
+        let viewport_width = aspect_ratio * viewport_height;
+
+        // Orthonormal camera basis: `w` points backwards, `u` right and `v` up.
+        let w = (settings.look_from - settings.look_at).normalized();
+        let u = settings.up.cross(w).normalized();
+        let v = w.cross(u);
+
+        let origin = settings.look_from;
+        let horizontal = focus_distance * viewport_width * u;
+        let vertical = focus_distance * viewport_height * v;
+        let upper_left = origin - 0.5 * horizontal + 0.5 * vertical - focus_distance * w;
+
+        Pose {
+            origin,
+            upper_left,
+            horizontal,
+            vertical,
+            u,
+            v,
+            lens_radius: settings.aperture / 2.0,
+        }
+    }
+
+    fn lerp(&self, other: &Pose, t: f32) -> Pose {
+        Pose {
+            origin: self.origin.lerp(other.origin, t),
+            upper_left: self.upper_left.lerp(other.upper_left, t),
+            horizontal: self.horizontal.lerp(other.horizontal, t),
+            vertical: self.vertical.lerp(other.vertical, t),
+            u: self.u.lerp(other.u, t),
+            v: self.v.lerp(other.v, t),
+            lens_radius: self.lens_radius + (other.lens_radius - self.lens_radius) * t,
+        }
+    }
+}
+
+/// A thin-lens camera that generates primary rays. With a zero aperture it is a pinhole camera.
+///
+/// The camera may move while its shutter is open; rays at times in between see it part of the
+/// way from where it was when the shutter opened to where it was when it closed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    open: Pose,
+    close: Pose,
+    shutter: (f32, f32),
+}
+
+impl Camera {
+    /// Build a camera from `settings`, using `default_aspect_ratio` unless the settings specify
+    /// their own.
+    pub fn new(settings: &CameraSettings, default_aspect_ratio: f32) -> Self {
+        Camera::moving(
+            settings,
+            settings,
+            default_aspect_ratio,
+            (settings.shutter_open, settings.shutter_close),
+        )
+    }
+
+    /// Build a camera set up as `open` describes at the start of the `shutter` interval and as
+    /// `close` describes at its end. The aspect ratio comes from `open`, or
+    /// `default_aspect_ratio` if it does not specify one.
+    pub fn moving(
+        open: &CameraSettings,
+        close: &CameraSettings,
+        default_aspect_ratio: f32,
+        shutter: (f32, f32),
+    ) -> Self {
+        let aspect_ratio = open.aspect_ratio.unwrap_or(default_aspect_ratio);
+        Camera {
+            open: Pose::new(open, aspect_ratio),
+            close: Pose::new(close, aspect_ratio),
+            shutter,
+        }
+    }
+
+    /// Return the times at which the shutter opens and closes.
+    pub fn shutter(&self) -> (f32, f32) {
+        self.shutter
+    }
+
+    /// Generate the primary ray through normalized image coordinates `(s, t)`, where `(0, 0)` is
+    /// the top-left corner of the image and `(1, 1)` the bottom-right. `lens_sample` is a uniform
+    /// sample in `[0, 1)^2` used to pick a point on the lens; it has no effect for pinhole cameras.
+    /// `time_sample`, uniform in `[0, 1)`, picks the time the ray is traced at while the shutter
+    /// is open.
+    pub fn get_ray(&self, s: f32, t: f32, lens_sample: [f32; 2], time_sample: f32) -> Ray {
+        let (open, close) = self.shutter;
+        let time = open + (close - open) * time_sample;
+        let pose = if self.open == self.close {
+            self.open
+        } else {
+            self.open.lerp(&self.close, time_sample)
+        };
+
+        let lens = concentric_disk(lens_sample);
+        let offset = pose.lens_radius * (lens.x() * pose.u + lens.y() * pose.v);
+        let origin = pose.origin + offset;
+        let target = pose.upper_left + s * pose.horizontal - t * pose.vertical;
+        Ray::with_time(origin, target - origin, time)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(expected: Vector3, actual: Vector3) {
+        assert!(
+            (expected - actual).norm() < 1e-5,
+            "Expected {:.5}, got {:.5}.",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_pinhole_rays() {
+        let settings = CameraSettings::new(Vector3::zeros(), Vector3::new(0.0, 0.0, -1.0), 90.0);
+        let camera = Camera::new(&settings, 2.0);
+
+        let center = camera.get_ray(0.5, 0.5, [0.3, 0.9], 0.5);
+        assert_eq!(Vector3::zeros(), center.origin);
+        assert_close(Vector3::new(0.0, 0.0, -1.0), center.direction);
+
+        // A 90 degree vertical FOV spans [-1, 1] vertically at unit distance, and twice that
+        // horizontally with an aspect ratio of 2.
+        let top_left = camera.get_ray(0.0, 0.0, [0.5, 0.5], 0.5);
+        assert_close(Vector3::new(-2.0, 1.0, -1.0), top_left.direction);
+        let bottom_right = camera.get_ray(1.0, 1.0, [0.5, 0.5], 0.5);
+        assert_close(Vector3::new(2.0, -1.0, -1.0), bottom_right.direction);
+    }
+
+    #[test]
+    fn test_aspect_ratio_override() {
+        let mut settings =
+            CameraSettings::new(Vector3::zeros(), Vector3::new(0.0, 0.0, -1.0), 90.0);
+        settings.aspect_ratio = Some(1.0);
+        let camera = Camera::new(&settings, 2.0);
+        let right = camera.get_ray(1.0, 0.5, [0.5, 0.5], 0.5);
+        assert_close(Vector3::new(1.0, 0.0, -1.0), right.direction);
+    }
+
+    #[test]
+    fn test_thin_lens_focus() {
+        let mut settings = CameraSettings::new(Vector3::new(0.0, 0.0, 5.0), Vector3::zeros(), 40.0);
+        settings.aperture = 0.5;
+        let camera = Camera::new(&settings, 1.5);
+
+        // Every ray through the same image point converges on the focus plane.
+        let focus = camera.get_ray(0.25, 0.75, [0.5, 0.5], 0.5).at(1.0);
+        for lens_sample in [[0.0, 0.0], [0.9, 0.1], [0.3, 0.7]] {
+            let ray = camera.get_ray(0.25, 0.75, lens_sample, 0.5);
+            assert!((ray.origin - settings.look_from).norm() <= 0.25 + 1e-6);
+            assert_close(focus, ray.at(1.0));
+        }
+    }
+
+    #[test]
+    fn test_motion() {
+        let mut open = CameraSettings::new(Vector3::zeros(), Vector3::new(0.0, 0.0, -1.0), 90.0);
+        open.shutter_close = 0.5;
+        let still = Camera::new(&open, 1.0);
+        assert_eq!((0.0, 0.5), still.shutter());
+        let ray = still.get_ray(0.5, 0.5, [0.5, 0.5], 0.5);
+        assert_eq!(0.25, ray.time);
+        assert_eq!(Vector3::zeros(), ray.origin);
+
+        // Halfway through the shutter interval, the camera is halfway along its path.
+        let mut close = open.clone();
+        close.look_from = Vector3::new(2.0, 0.0, 0.0);
+        close.look_at = Vector3::new(2.0, 0.0, -1.0);
+        let camera = Camera::moving(&open, &close, 1.0, (1.0, 2.0));
+        let ray = camera.get_ray(0.5, 0.5, [0.5, 0.5], 0.5);
+        assert_eq!(1.5, ray.time);
+        assert_close(Vector3::new(1.0, 0.0, 0.0), ray.origin);
+        assert_close(Vector3::new(0.0, 0.0, -1.0), ray.direction);
+
+        let halfway = open.lerp(&close, 0.5);
+        assert_eq!(Vector3::new(1.0, 0.0, -1.0), halfway.look_at);
+        assert_eq!(None, halfway.focus_distance);
+        close.focus_distance = Some(3.0);
+        assert_eq!(Some(2.0), open.lerp(&close, 0.5).focus_distance);
+    }
+}
@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, TraversalStats};
+use crate::ray::Ray;
+
+/// How a `Csg` node combines its two solids.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CsgOp {
+    /// Points in either solid.
+    Union,
+    /// Points in both solids.
+    Intersection,
+    /// Points in the left solid but not the right.
+    Difference,
+}
+
+impl CsgOp {
+    /// Return whether a point is in the combined solid, given whether it is in each operand.
+    fn contains(self, left: bool, right: bool) -> bool {
+        match self {
+            CsgOp::Union => left || right,
+            CsgOp::Intersection => left && right,
+            CsgOp::Difference => left && !right,
+        }
+    }
+}
+
+/// A constructive solid geometry node combining two solids with a boolean operation.
+///
+/// Both operands must be closed and bounded, so that every ray starts and ends outside them and
+/// the `front_face` of each hit says whether the ray is entering or leaving. Their surfaces keep
+/// their own materials and texture coordinates.
+pub struct Csg {
+    pub op: CsgOp,
+    pub left: Box<dyn Hittable>,
+    pub right: Box<dyn Hittable>,
+}
+
+impl Csg {
+    /// Create a new `Csg` node combining `left` and `right` with `op`.
+    pub fn new(op: CsgOp, left: impl Hittable + 'static, right: impl Hittable + 'static) -> Self {
+        Csg {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.hit_counted(ray, t_min, t_max, &mut TraversalStats::default())
+    }
+
+    fn hit_counted(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord> {
+        // Walk the operands' surfaces in order along the whole line, from outside both, tracking
+        // which solids the ray is in. The first crossing of the combined surface after `t_min`
+        // is the hit.
+        let start = f32::NEG_INFINITY;
+        let mut next_left = self.left.hit_counted(ray, start, t_max, stats);
+        let mut next_right = self.right.hit_counted(ray, start, t_max, stats);
+        let (mut in_left, mut in_right) = (false, false);
+        let mut inside = false;
+        loop {
+            let is_left = match (&next_left, &next_right) {
+                (None, None) => return None,
+                (Some(l), Some(r)) => l.t <= r.t,
+                (left, _) => left.is_some(),
+            };
+            let mut hit = if is_left {
+                let hit = next_left.take().unwrap();
+                in_left = hit.front_face;
+                next_left = self.left.hit_counted(ray, hit.t, t_max, stats);
+                hit
+            } else {
+                let hit = next_right.take().unwrap();
+                in_right = hit.front_face;
+                next_right = self.right.hit_counted(ray, hit.t, t_max, stats);
+                hit
+            };
+
+            let now_inside = self.op.contains(in_left, in_right);
+            if now_inside != inside && hit.t > t_min {
+                // The normal already faces against the ray; the ray enters the combined solid
+                // exactly when it becomes inside.
+                hit.front_face = now_inside;
+                return Some(hit);
+            }
+            inside = now_inside;
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let left = self.left.bounding_box();
+        let right = self.right.bounding_box();
+        match self.op {
+            CsgOp::Union => Some(left?.union(right?)),
+            CsgOp::Intersection => match (left, right) {
+                (Some(l), Some(r)) => Some(Aabb::new(
+                    l.min.cwise(r.min, f32::max),
+                    l.max.cwise(r.max, f32::min),
+                )),
+                (bounds, None) | (None, bounds) => bounds,
+            },
+            CsgOp::Difference => left,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hittable::MaterialId;
+    use crate::shapes::{Cuboid, Sphere};
+    use crate::vector::Vector3;
+
+    /// Return every hit along `ray` as `(t, front_face, material)`.
+    fn hits(csg: &Csg, ray: &Ray) -> Vec<(f32, bool, usize)> {
+        let mut hits = Vec::new();
+        let mut t = 0.0;
+        while let Some(hit) = csg.hit(ray, t, f32::INFINITY) {
+            hits.push((hit.t, hit.front_face, hit.material.0));
+            t = hit.t;
+        }
+        hits
+    }
+
+    fn spheres(op: CsgOp) -> Csg {
+        // Unit spheres at x = -0.5 and x = 0.5.
+        Csg::new(
+            op,
+            Sphere::new(Vector3::new(-0.5, 0.0, 0.0), 1.0, MaterialId(0)),
+            Sphere::new(Vector3::new(0.5, 0.0, 0.0), 1.0, MaterialId(1)),
+        )
+    }
+
+    #[test]
+    fn test_operations() {
+        let ray = Ray::new(Vector3::new(-5.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(
+            vec![(3.5, true, 0), (6.5, false, 1)],
+            hits(&spheres(CsgOp::Union), &ray)
+        );
+        assert_eq!(
+            vec![(4.5, true, 1), (5.5, false, 0)],
+            hits(&spheres(CsgOp::Intersection), &ray)
+        );
+        // The right sphere's surface bounds the difference from the inside.
+        assert_eq!(
+            vec![(3.5, true, 0), (4.5, false, 1)],
+            hits(&spheres(CsgOp::Difference), &ray)
+        );
+
+        // Starting inside the union, the first hit is where the ray leaves it.
+        let inside = Ray::new(Vector3::zeros(), Vector3::new(1.0, 0.0, 0.0));
+        let hit = spheres(CsgOp::Union)
+            .hit(&inside, 0.0, f32::INFINITY)
+            .unwrap();
+        assert_eq!((1.5, false), (hit.t, hit.front_face));
+        assert!(spheres(CsgOp::Union).hit(&ray, 0.0, 3.0).is_none());
+    }
+
+    #[test]
+    fn test_drilled_block() {
+        // A block with a spherical bite taken out of its top face.
+        let csg = Csg::new(
+            CsgOp::Difference,
+            Cuboid::new(-Vector3::ones(), Vector3::ones(), MaterialId(0)),
+            Sphere::new(Vector3::new(0.0, 1.0, 0.0), 0.5, MaterialId(1)),
+        );
+        let down = Ray::new(Vector3::new(0.0, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let hit = csg.hit(&down, 0.0, f32::INFINITY).unwrap();
+        assert_eq!(
+            4.5, hit.t,
+            "Expected the bottom of the bite, got {:?}.",
+            hit
+        );
+        assert_eq!(MaterialId(1), hit.material);
+        assert!(hit.front_face);
+        assert_eq!(Vector3::new(0.0, 1.0, 0.0), hit.normal);
+
+        let beside = Ray::new(Vector3::new(0.8, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let hit = csg.hit(&beside, 0.0, f32::INFINITY).unwrap();
+        assert_eq!((4.0, 0), (hit.t, hit.material.0));
+        assert_eq!(
+            Some(Aabb::new(-Vector3::ones(), Vector3::ones())),
+            csg.bounding_box()
+        );
+        assert_eq!(
+            Some(Aabb::new(
+                Vector3::new(-0.5, -1.0, -1.0),
+                Vector3::new(1.0, 1.0, 1.0)
+            )),
+            Csg::new(
+                CsgOp::Intersection,
+                Cuboid::new(-Vector3::ones(), Vector3::ones(), MaterialId(0)),
+                Cuboid::new(
+                    Vector3::new(-0.5, -2.0, -2.0),
+                    Vector3::ones() * 2.0,
+                    MaterialId(0)
+                ),
+            )
+            .bounding_box()
+        );
+    }
+}
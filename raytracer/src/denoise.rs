@@ -0,0 +1,149 @@
+use rayon::prelude::*;
+
+use crate::framebuffer::Framebuffer;
+use crate::vector::Color;
+
+/// Noise-free images of the first surface seen through each pixel, which tell a denoiser where
+/// the edges are.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Guides {
+    /// Surface color, free of lighting.
+    pub albedo: Framebuffer,
+    /// Shading normal, facing the camera. Averaged normals at silhouettes may be shorter than
+    /// one.
+    pub normal: Framebuffer,
+}
+
+/// An edge-aware joint bilateral filter.
+///
+/// Each pixel becomes a weighted average of its neighbours within `radius`. Weights fall off
+/// with distance, and with differences in the albedo and normal guides so that the filter does
+/// not blur across edges. They also fall off with differences in color, measured against the
+/// pixels' variance, so that well-converged detail is kept.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Denoiser {
+    /// Half-width of the filter window, in pixels.
+    pub radius: usize,
+    /// Standard deviation of the spatial falloff, in pixels.
+    pub sigma_spatial: f32,
+    /// Standard deviation of the albedo difference falloff.
+    pub sigma_albedo: f32,
+    /// Standard deviation of the normal difference falloff.
+    pub sigma_normal: f32,
+    /// Color differences are compared with this many standard errors of the two pixels.
+    pub sigma_color: f32,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Denoiser {
+            radius: 6,
+            sigma_spatial: 3.0,
+            sigma_albedo: 0.1,
+            sigma_normal: 0.2,
+            sigma_color: 3.0,
+        }
+    }
+}
+
+impl Denoiser {
+    /// Denoise `color`, whose per-pixel variance of the mean luminance is `variance`, guided by
+    /// `guides`. Pixels are filtered in parallel on the current rayon thread pool; see
+    /// `Renderer::install` to use a renderer's.
+    ///
+    /// Panics if the images and `variance` differ in size.
+    pub fn denoise(&self, color: &Framebuffer, variance: &[f32], guides: &Guides) -> Framebuffer {
+        let (width, height) = (color.width(), color.height());
+        assert_eq!(width * height, variance.len(), "Variance size mismatch.");
+        for guide in [&guides.albedo, &guides.normal] {
+            assert_eq!(
+                (width, height),
+                (guide.width(), guide.height()),
+                "Guide size mismatch."
+            );
+        }
+
+        let radius = self.radius as isize;
+        let spatial = -0.5 / (self.sigma_spatial * self.sigma_spatial);
+        let albedo_falloff = -0.5 / (self.sigma_albedo * self.sigma_albedo);
+        let normal_falloff = -0.5 / (self.sigma_normal * self.sigma_normal);
+        let color_scale = self.sigma_color * self.sigma_color;
+
+        let filter = |index: usize| {
+            let (x, y) = ((index % width) as isize, (index / width) as isize);
+            let center = color.pixels()[index];
+            let albedo = guides.albedo.pixels()[index];
+            let normal = guides.normal.pixels()[index];
+
+            let mut sum = Color::zeros();
+            let mut total = 0.0;
+            for qy in (y - radius).max(0)..=(y + radius).min(height as isize - 1) {
+                for qx in (x - radius).max(0)..=(x + radius).min(width as isize - 1) {
+                    let q = qy as usize * width + qx as usize;
+                    let distance = ((qx - x).pow(2) + (qy - y).pow(2)) as f32;
+                    let exponent = spatial * distance
+                        + albedo_falloff * (guides.albedo.pixels()[q] - albedo).squared_norm()
+                        + normal_falloff * (guides.normal.pixels()[q] - normal).squared_norm()
+                        - (color.pixels()[q] - center).squared_norm()
+                            / (color_scale * (variance[index] + variance[q]) + 1e-4);
+                    let weight = exponent.exp();
+                    sum += color.pixels()[q] * weight;
+                    total += weight;
+                }
+            }
+            // The center pixel always has weight one, so `total` is never zero.
+            sum / total
+        };
+
+        let pixels: Vec<Color> = (0..width * height).into_par_iter().map(filter).collect();
+        Framebuffer::from_fn(width, height, |x, y| pixels[y * width + x])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rng::Pcg32;
+
+    #[test]
+    fn test_denoise_preserves_edges() {
+        // Left half dark, right half light, with noise of variance 1/300 added to each pixel.
+        let (width, height) = (32, 16);
+        let mut rng = Pcg32::new(0, 0);
+        let truth = |x: usize| if x < width / 2 { 0.2 } else { 0.8 };
+        let noisy = Framebuffer::from_fn(width, height, |x, _| {
+            Color::ones() * (truth(x) + 0.2 * (rng.next_f32() - 0.5))
+        });
+        let variance = vec![0.04 / 12.0; width * height];
+        let guides = Guides {
+            albedo: Framebuffer::from_fn(width, height, |x, _| Color::ones() * truth(x)),
+            normal: Framebuffer::from_fn(width, height, |_, _| Color::new(0.0, 0.0, 1.0)),
+        };
+
+        let denoised = Denoiser::default().denoise(&noisy, &variance, &guides);
+        let error = |image: &Framebuffer| {
+            let sum: f32 = (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| (image.get(x, y).x() - truth(x)).powi(2))
+                .sum();
+            sum / (width * height) as f32
+        };
+        assert!(
+            error(&denoised) < 0.25 * error(&noisy),
+            "Denoising did not reduce the error: {} vs {}.",
+            error(&denoised),
+            error(&noisy)
+        );
+        // Pixels beside the edge are not blurred into the other side.
+        for y in 0..height {
+            let left = denoised.get(width / 2 - 1, y).x();
+            let right = denoised.get(width / 2, y).x();
+            assert!(
+                left < 0.3 && right > 0.7,
+                "Edge blurred: {} | {}.",
+                left,
+                right
+            );
+        }
+    }
+}
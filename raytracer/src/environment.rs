@@ -0,0 +1,260 @@
+use std::f32::consts::PI;
+
+use crate::framebuffer::{luminance, Framebuffer};
+use crate::light::{Light, LightSample};
+use crate::rng::Pcg32;
+use crate::sampling::{uniform_sphere, Distribution2D};
+use crate::vector::{Color, Vector3};
+
+/// An equirectangular image of the radiance arriving from every direction. The center of the
+/// image looks along `-z`, its top straight up along `+y`, and `+x` lies a quarter of the way
+/// to the right of the center.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvironmentMap {
+    image: Framebuffer,
+    /// Rotation of the map about the `y` axis, in radians.
+    rotation: f32,
+    /// Density over image coordinates, proportional to the power arriving through each pixel.
+    distribution: Distribution2D,
+}
+
+impl EnvironmentMap {
+    /// Create a new `EnvironmentMap` from `image`, whose pixels must be linear radiance, turned
+    /// by `rotation` radians about the `y` axis. A quarter turn brings the center of the image
+    /// round to `+x`.
+    pub fn new(image: Framebuffer, rotation: f32) -> Self {
+        let (width, height) = (image.width(), image.height());
+        // Rows near the poles cover less solid angle than rows at the horizon.
+        let weights: Vec<f32> = image
+            .pixels()
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| {
+                let theta = PI * ((i / width) as f32 + 0.5) / height as f32;
+                luminance(c).max(0.0) * theta.sin()
+            })
+            .collect();
+        EnvironmentMap {
+            distribution: Distribution2D::new(&weights, width),
+            image,
+            rotation,
+        }
+    }
+
+    /// Return the image this map looks up.
+    pub fn image(&self) -> &Framebuffer {
+        &self.image
+    }
+
+    /// Return the image coordinates in `[0, 1)^2` of the unit `direction`.
+    fn uv_of(&self, direction: Vector3) -> [f32; 2] {
+        let phi = direction.x().atan2(-direction.z()) - self.rotation;
+        let u = (0.5 + phi / (2.0 * PI)).rem_euclid(1.0);
+        let v = direction.y().clamp(-1.0, 1.0).acos() / PI;
+        [u, v]
+    }
+
+    /// Return the unit direction at image coordinates `[u, v]`.
+    fn direction_at(&self, [u, v]: [f32; 2]) -> Vector3 {
+        let phi = (u - 0.5) * 2.0 * PI + self.rotation;
+        let theta = v * PI;
+        Vector3::new(
+            theta.sin() * phi.sin(),
+            theta.cos(),
+            -theta.sin() * phi.cos(),
+        )
+    }
+
+    /// Return the radiance arriving from the unit `direction`.
+    pub fn radiance(&self, direction: Vector3) -> Color {
+        let [u, v] = self.uv_of(direction);
+        let (width, height) = (self.image.width(), self.image.height());
+        let x = ((u * width as f32) as usize).min(width - 1);
+        let y = ((v * height as f32) as usize).min(height - 1);
+        self.image.get(x, y)
+    }
+
+    /// Map a uniform sample in `[0, 1)^2` to a direction, chosen in proportion to the light
+    /// arriving from it. Returns the direction and its density in solid angle.
+    pub fn sample(&self, sample: [f32; 2]) -> (Vector3, f32) {
+        let uv = self.distribution.sample(sample);
+        let direction = self.direction_at(uv);
+        (direction, self.pdf_uv(uv))
+    }
+
+    /// Return the density, in solid angle, with which `sample` picks the unit `direction`.
+    pub fn pdf(&self, direction: Vector3) -> f32 {
+        self.pdf_uv(self.uv_of(direction))
+    }
+
+    /// Convert the density of image coordinates `uv` to one in solid angle. A pixel spans
+    /// `2 pi^2 sin(theta)` times as much solid angle as it does image area.
+    fn pdf_uv(&self, uv: [f32; 2]) -> f32 {
+        let sin_theta = (uv[1] * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        self.distribution.pdf(uv) / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+/// Light arriving from infinitely far away along every ray that leaves the scene.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Environment {
+    /// The same radiance from every direction.
+    Constant(Color),
+    /// A sky blending linearly with height from `horizon`, at and below the horizon, to `zenith`
+    /// straight up.
+    Gradient { horizon: Color, zenith: Color },
+    /// Radiance looked up in an image.
+    Map(EnvironmentMap),
+}
+
+impl Environment {
+    /// Return the radiance arriving from `direction`, which need not be normalized.
+    pub fn radiance(&self, direction: Vector3) -> Color {
+        let direction = direction.normalized();
+        match self {
+            Environment::Constant(color) => *color,
+            Environment::Gradient { horizon, zenith } => {
+                horizon.lerp(*zenith, direction.y().max(0.0))
+            }
+            Environment::Map(map) => map.radiance(direction),
+        }
+    }
+}
+
+impl Light for Environment {
+    fn sample(&self, _point: Vector3, rng: &mut Pcg32) -> Option<LightSample> {
+        let (direction, pdf) = match self {
+            // Smooth environments are sampled uniformly, which multiple importance sampling
+            // balances against the material.
+            Environment::Constant(_) | Environment::Gradient { .. } => {
+                (uniform_sphere(rng.next_2d()), 1.0 / (4.0 * PI))
+            }
+            Environment::Map(map) => map.sample(rng.next_2d()),
+        };
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance: f32::INFINITY,
+            radiance: self.radiance(direction),
+            pdf,
+            delta: false,
+        })
+    }
+
+    fn pdf(&self, _point: Vector3, direction: Vector3) -> f32 {
+        match self {
+            Environment::Constant(_) | Environment::Gradient { .. } => 1.0 / (4.0 * PI),
+            Environment::Map(map) => map.pdf(direction.normalized()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_constant_and_gradient() {
+        let constant = Environment::Constant(Color::new(1.0, 2.0, 3.0));
+        assert_eq!(
+            Color::new(1.0, 2.0, 3.0),
+            constant.radiance(Vector3::new(0.0, -5.0, 1.0))
+        );
+
+        let sky = Environment::Gradient {
+            horizon: Color::ones(),
+            zenith: Color::new(0.0, 0.0, 1.0),
+        };
+        assert_eq!(
+            Color::new(0.0, 0.0, 1.0),
+            sky.radiance(Vector3::new(0.0, 2.0, 0.0))
+        );
+        assert_eq!(Color::ones(), sky.radiance(Vector3::new(1.0, 0.0, 0.0)));
+        assert_eq!(Color::ones(), sky.radiance(Vector3::new(0.0, -1.0, 0.0)));
+        let mut rng = Pcg32::new(0, 0);
+        let sample = sky.sample(Vector3::zeros(), &mut rng).unwrap();
+        assert_eq!(sky.radiance(sample.direction), sample.radiance);
+        assert_eq!(sample.pdf, sky.pdf(Vector3::zeros(), sample.direction));
+        assert_eq!(f32::INFINITY, sample.distance);
+    }
+
+    #[test]
+    fn test_map_directions() {
+        let image = Framebuffer::from_fn(4, 2, |x, y| Color::new(x as f32, y as f32, 0.0));
+        let map = EnvironmentMap::new(image.clone(), 0.0);
+        // The center of the image looks along -z, with +x a quarter of the way to its right.
+        assert_eq!(
+            Color::new(2.0, 0.0, 0.0),
+            map.radiance(Vector3::unit(0.0, 0.1, -1.0))
+        );
+        assert_eq!(
+            Color::new(3.0, 1.0, 0.0),
+            map.radiance(Vector3::unit(1.0, -0.1, 0.0))
+        );
+        assert_eq!(
+            Color::new(0.0, 0.0, 0.0),
+            map.radiance(Vector3::unit(0.0, 0.1, 1.0))
+        );
+        // Turning the map a quarter turn brings +x to the center.
+        let turned = EnvironmentMap::new(image, PI / 2.0);
+        assert_eq!(
+            Color::new(2.0, 0.0, 0.0),
+            turned.radiance(Vector3::unit(1.0, 0.1, 0.0))
+        );
+        for uv in [[0.3, 0.2], [0.9, 0.7], [0.55, 0.5]] {
+            let [u, v] = turned.uv_of(turned.direction_at(uv));
+            assert!((u - uv[0]).abs() < 1e-5 && (v - uv[1]).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_map_importance_sampling() {
+        // A dim map with one bright pixel above the horizon.
+        let image = Framebuffer::from_fn(16, 8, |x, y| {
+            if (x, y) == (5, 2) {
+                Color::ones() * 1000.0
+            } else {
+                Color::ones() * 0.1
+            }
+        });
+        let environment = Environment::Map(EnvironmentMap::new(image, 0.3));
+        let mut rng = Pcg32::new(7, 0);
+
+        // Most samples head for the bright pixel. Their densities match `pdf`, and weighting
+        // by them gives an unbiased estimate of the power arriving from the whole sphere.
+        let n = 20000;
+        let mut bright = 0;
+        let mut estimate = 0.0;
+        for _ in 0..n {
+            let sample = environment.sample(Vector3::zeros(), &mut rng).unwrap();
+            let pdf = environment.pdf(Vector3::zeros(), sample.direction);
+            assert!(
+                (pdf - sample.pdf).abs() < 1e-3 * pdf,
+                "Sampled with density {}, but pdf gives {}.",
+                sample.pdf,
+                pdf
+            );
+            if sample.radiance.x() > 1.0 {
+                bright += 1;
+            }
+            estimate += sample.radiance.x() / sample.pdf / n as f32;
+        }
+        assert!(bright > n * 9 / 10, "Only {} samples were bright.", bright);
+
+        // Each pixel in row y spans 2 pi / 16 in azimuth and cos(theta0) - cos(theta1) in
+        // height.
+        let row = |y: f32| (PI * y / 8.0).cos() - (PI * (y + 1.0) / 8.0).cos();
+        let expected = 4.0 * PI * 0.1 + (1000.0 - 0.1) * 2.0 * PI / 16.0 * row(2.0);
+        assert!(
+            (estimate - expected).abs() < 0.01 * expected,
+            "Expected {}, got {}.",
+            expected,
+            estimate
+        );
+    }
+}
@@ -0,0 +1,231 @@
+use std::fmt::{Debug, Display};
+use std::iter::Sum;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+/// A floating-point scalar that vectors can be built from, implemented for `f32` and `f64`.
+///
+/// Besides the usual arithmetic, the trait carries the three-component kernels that `Vector3`
+/// is built on. They default to plain scalar code; with the `simd` feature enabled, `f32`
+/// overrides them with SSE intrinsics on x86-64.
+pub trait Float:
+    Copy
+    + Debug
+    + Default
+    + Display
+    + PartialOrd
+    + Send
+    + Sync
+    + Sum
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + 'static
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    /// Convert from an `f64`, rounding if necessary.
+    fn from_f64(value: f64) -> Self;
+    /// Convert to an `f64`.
+    fn to_f64(self) -> f64;
+
+    fn sqrt(self) -> Self;
+    fn abs(self) -> Self;
+    fn powi(self, n: i32) -> Self;
+    fn min(self, other: Self) -> Self;
+    fn max(self, other: Self) -> Self;
+
+    /// Component-wise sum of `a` and `b`.
+    fn add3(a: [Self; 3], b: [Self; 3]) -> [Self; 3] {
+        [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+    }
+
+    /// Component-wise difference of `a` and `b`.
+    fn sub3(a: [Self; 3], b: [Self; 3]) -> [Self; 3] {
+        [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+    }
+
+    /// Component-wise product of `a` and `b`.
+    fn mul3(a: [Self; 3], b: [Self; 3]) -> [Self; 3] {
+        [a[0] * b[0], a[1] * b[1], a[2] * b[2]]
+    }
+
+    /// Product of `a` and the scalar `s`.
+    fn scale3(a: [Self; 3], s: Self) -> [Self; 3] {
+        [a[0] * s, a[1] * s, a[2] * s]
+    }
+
+    /// Dot product of `a` and `b`.
+    fn dot3(a: [Self; 3], b: [Self; 3]) -> Self {
+        a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+    }
+}
+
+macro_rules! float_methods {
+    ($t:ty) => {
+        const ZERO: Self = 0.0;
+        const ONE: Self = 1.0;
+
+        fn from_f64(value: f64) -> Self {
+            value as $t
+        }
+
+        fn to_f64(self) -> f64 {
+            self as f64
+        }
+
+        fn sqrt(self) -> Self {
+            <$t>::sqrt(self)
+        }
+
+        fn abs(self) -> Self {
+            <$t>::abs(self)
+        }
+
+        fn powi(self, n: i32) -> Self {
+            <$t>::powi(self, n)
+        }
+
+        fn min(self, other: Self) -> Self {
+            <$t>::min(self, other)
+        }
+
+        fn max(self, other: Self) -> Self {
+            <$t>::max(self, other)
+        }
+    };
+}
+
+impl Float for f32 {
+    float_methods!(f32);
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[inline]
+    fn add3(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+        simd::add3(a, b)
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[inline]
+    fn sub3(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+        simd::sub3(a, b)
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[inline]
+    fn mul3(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+        simd::mul3(a, b)
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[inline]
+    fn scale3(a: [f32; 3], s: f32) -> [f32; 3] {
+        simd::scale3(a, s)
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[inline]
+    fn dot3(a: [f32; 3], b: [f32; 3]) -> f32 {
+        simd::dot3(a, b)
+    }
+}
+
+impl Float for f64 {
+    float_methods!(f64);
+}
+
+/// SSE kernels for three-component `f32` vectors. SSE2 is part of the x86-64 baseline, so no
+/// runtime feature detection is needed.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod simd {
+    use std::arch::x86_64::*;
+
+    #[inline(always)]
+    fn load(a: [f32; 3]) -> __m128 {
+        // SAFETY: SSE2 is always available on x86-64.
+        unsafe { _mm_set_ps(0.0, a[2], a[1], a[0]) }
+    }
+
+    #[inline(always)]
+    fn store(v: __m128) -> [f32; 3] {
+        let mut out = [0.0f32; 4];
+        // SAFETY: `out` has room for four lanes, and `_mm_storeu_ps` has no alignment needs.
+        unsafe { _mm_storeu_ps(out.as_mut_ptr(), v) };
+        [out[0], out[1], out[2]]
+    }
+
+    #[inline(always)]
+    pub fn add3(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+        // SAFETY: SSE2 is always available on x86-64.
+        store(unsafe { _mm_add_ps(load(a), load(b)) })
+    }
+
+    #[inline(always)]
+    pub fn sub3(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+        // SAFETY: SSE2 is always available on x86-64.
+        store(unsafe { _mm_sub_ps(load(a), load(b)) })
+    }
+
+    #[inline(always)]
+    pub fn mul3(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+        // SAFETY: SSE2 is always available on x86-64.
+        store(unsafe { _mm_mul_ps(load(a), load(b)) })
+    }
+
+    #[inline(always)]
+    pub fn scale3(a: [f32; 3], s: f32) -> [f32; 3] {
+        // SAFETY: SSE2 is always available on x86-64.
+        store(unsafe { _mm_mul_ps(load(a), _mm_set1_ps(s)) })
+    }
+
+    #[inline(always)]
+    pub fn dot3(a: [f32; 3], b: [f32; 3]) -> f32 {
+        // SAFETY: SSE2 is always available on x86-64.
+        unsafe {
+            // Sum the lanes pairwise: [x, y, z, 0] + [z, 0, x, y], then add lanes 0 and 1.
+            let p = _mm_mul_ps(load(a), load(b));
+            let s = _mm_add_ps(p, _mm_movehl_ps(p, p));
+            let s = _mm_add_ss(s, _mm_shuffle_ps(s, s, 0b01));
+            _mm_cvtss_f32(s)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn kernels<T: Float>(a: [T; 3], b: [T; 3]) -> ([T; 3], [T; 3], [T; 3], [T; 3], T) {
+        (
+            T::add3(a, b),
+            T::sub3(a, b),
+            T::mul3(a, b),
+            T::scale3(a, T::from_f64(2.0)),
+            T::dot3(a, b),
+        )
+    }
+
+    #[test]
+    fn test_kernels() {
+        let (add, sub, mul, scale, dot) = kernels([1.0f32, 2.0, 3.0], [4.0, -5.0, 0.5]);
+        assert_eq!([5.0, -3.0, 3.5], add);
+        assert_eq!([-3.0, 7.0, 2.5], sub);
+        assert_eq!([4.0, -10.0, 1.5], mul);
+        assert_eq!([2.0, 4.0, 6.0], scale);
+        assert_eq!(-4.5, dot);
+
+        let (add, _, _, _, dot) = kernels([1.0f64, 2.0, 3.0], [4.0, -5.0, 0.5]);
+        assert_eq!([5.0, -3.0, 3.5], add);
+        assert_eq!(-4.5, dot);
+    }
+
+    #[test]
+    fn test_conversion() {
+        assert_eq!(0.1f32, f32::from_f64(0.1));
+        assert_eq!(0.5, 0.5f32.to_f64());
+        assert_eq!(3.0, <f64 as Float>::sqrt(9.0));
+        assert_eq!(-2.0, Float::min(-2.0f32, 1.0));
+    }
+}
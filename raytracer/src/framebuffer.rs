@@ -0,0 +1,868 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::vector::{Color, Vector3};
+
+/// An error produced while reading, decoding, encoding or writing an image.
+#[derive(Debug)]
+pub enum ImageError {
+    /// The image file could not be read or written.
+    Io(io::Error),
+    /// The PNG encoder rejected the image.
+    Png(png::EncodingError),
+    /// The PNG decoder rejected the file.
+    PngDecode(png::DecodingError),
+    /// The file is not a well-formed image of its format.
+    Malformed(String),
+    /// The output path has an extension we do not know how to write.
+    UnsupportedFormat(String),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::Io(err) => write!(f, "{}", err),
+            ImageError::Png(err) => write!(f, "PNG encoding failed: {}", err),
+            ImageError::PngDecode(err) => write!(f, "PNG decoding failed: {}", err),
+            ImageError::Malformed(message) => write!(f, "malformed image: {}", message),
+            ImageError::UnsupportedFormat(ext) => {
+                write!(f, "unsupported image format \"{}\"", ext)
+            }
+        }
+    }
+}
+
+impl Error for ImageError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ImageError::Io(err) => Some(err),
+            ImageError::Png(err) => Some(err),
+            ImageError::PngDecode(err) => Some(err),
+            ImageError::Malformed(_) | ImageError::UnsupportedFormat(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for ImageError {
+    fn from(err: io::Error) -> Self {
+        ImageError::Io(err)
+    }
+}
+
+impl From<png::EncodingError> for ImageError {
+    fn from(err: png::EncodingError) -> Self {
+        ImageError::Png(err)
+    }
+}
+
+impl From<png::DecodingError> for ImageError {
+    fn from(err: png::DecodingError) -> Self {
+        ImageError::PngDecode(err)
+    }
+}
+
+/// Return the lowercased extension of `path`, or an empty string if it has none.
+pub(crate) fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase()
+}
+
+/// Encode a linear color as Radiance RGBE: three 8-bit mantissas sharing one exponent.
+fn to_rgbe(color: Color) -> [u8; 4] {
+    let channel = |c: f32| if c > 0.0 { c } else { 0.0 };
+    let (r, g, b) = (channel(color.x()), channel(color.y()), channel(color.z()));
+    let max = r.max(g).max(b);
+    if max < 1e-32 || !max.is_finite() {
+        return [0; 4];
+    }
+    // Write max as m * 2^e with m in [0.5, 1), so that it takes a mantissa of at least 128.
+    let e = max.log2().floor() as i32 + 1;
+    let scale = 256.0 / 2f32.powi(e);
+    let mantissa = |c: f32| (c * scale).min(255.0) as u8;
+    [mantissa(r), mantissa(g), mantissa(b), (e + 128) as u8]
+}
+
+/// Decode a Radiance RGBE pixel into a linear color.
+fn from_rgbe([r, g, b, e]: [u8; 4]) -> Color {
+    if e == 0 {
+        return Color::zeros();
+    }
+    // Take the middle of the range each mantissa stands for.
+    let scale = 2f32.powi(e as i32 - (128 + 8));
+    Color::new(r as f32 + 0.5, g as f32 + 0.5, b as f32 + 0.5) * scale
+}
+
+/// Return the luminance of a linear Rec. 709 color.
+pub fn luminance(color: Color) -> f32 {
+    0.2126 * color.x() + 0.7152 * color.y() + 0.0722 * color.z()
+}
+
+/// Operator used to compress high-dynamic-range radiance into `[0, 1]` before display.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ToneMap {
+    /// Clamp each channel to `[0, 1]`.
+    #[default]
+    Clamp,
+    /// Per-channel Reinhard operator, `c / (1 + c)`.
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve.
+    Aces,
+}
+
+impl ToneMap {
+    /// Apply this operator to a single linear channel value.
+    pub fn apply(self, c: f32) -> f32 {
+        let mapped = match self {
+            ToneMap::Clamp => c,
+            ToneMap::Reinhard => c / (1.0 + c),
+            ToneMap::Aces => (c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14),
+        };
+        mapped.clamp(0.0, 1.0)
+    }
+}
+
+/// How linear framebuffer values are converted into 8-bit display values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DisplaySettings {
+    /// Multiplier applied to radiance before tone mapping.
+    pub exposure: f32,
+    pub tone_map: ToneMap,
+    /// Display gamma; channels are raised to `1 / gamma` after tone mapping.
+    pub gamma: f32,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        DisplaySettings {
+            exposure: 1.0,
+            tone_map: ToneMap::Clamp,
+            gamma: 2.2,
+        }
+    }
+}
+
+impl DisplaySettings {
+    /// Convert a linear color into gamma-encoded 8-bit RGB.
+    pub fn encode(&self, color: Color) -> [u8; 3] {
+        let channel = |c: f32| {
+            // NaNs from degenerate samples become black rather than poisoning the image.
+            let c = if c.is_nan() { 0.0 } else { c * self.exposure };
+            let c = self.tone_map.apply(c).powf(1.0 / self.gamma);
+            (255.0 * c).round() as u8
+        };
+        [channel(color.x()), channel(color.y()), channel(color.z())]
+    }
+}
+
+/// A `width` by `height` image of linear RGB colors, stored in row-major order from the top-left.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Framebuffer {
+    /// Create a new black `Framebuffer` of the given size.
+    pub fn new(width: usize, height: usize) -> Self {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![Vector3::zeros(); width * height],
+        }
+    }
+
+    /// Create a new `Framebuffer` by evaluating `f(x, y)` at every pixel.
+    pub fn from_fn(width: usize, height: usize, mut f: impl FnMut(usize, usize) -> Color) -> Self {
+        let pixels = (0..width * height)
+            .map(|i| f(i % width, i / width))
+            .collect();
+        Framebuffer {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Return the width of this framebuffer in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Return the height of this framebuffer in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Return the color of the pixel at column `x` and row `y`.
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Set the color of the pixel at column `x` and row `y`.
+    pub fn set(&mut self, x: usize, y: usize, color: Color) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    /// Return all pixels in row-major order.
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    /// Return all pixels in row-major order, mutably.
+    pub fn pixels_mut(&mut self) -> &mut [Color] {
+        &mut self.pixels
+    }
+
+    /// Encode every pixel as 8-bit RGB, row-major from the top-left.
+    pub fn to_rgb8(&self, settings: &DisplaySettings) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|&color| settings.encode(color))
+            .collect()
+    }
+
+    /// Write this framebuffer as an ASCII (P3) PPM image.
+    pub fn write_ppm_ascii(&self, mut w: impl Write, settings: &DisplaySettings) -> io::Result<()> {
+        writeln!(w, "P3\n{} {}\n255", self.width, self.height)?;
+        for row in self.to_rgb8(settings).chunks(3 * self.width) {
+            let line: Vec<String> = row.iter().map(|c| c.to_string()).collect();
+            writeln!(w, "{}", line.join(" "))?;
+        }
+        Ok(())
+    }
+
+    /// Write this framebuffer as a binary (P6) PPM image.
+    pub fn write_ppm_binary(
+        &self,
+        mut w: impl Write,
+        settings: &DisplaySettings,
+    ) -> io::Result<()> {
+        write!(w, "P6\n{} {}\n255\n", self.width, self.height)?;
+        w.write_all(&self.to_rgb8(settings))
+    }
+
+    /// Write this framebuffer as an 8-bit RGB PNG image.
+    pub fn write_png(&self, w: impl Write, settings: &DisplaySettings) -> Result<(), ImageError> {
+        let mut encoder = png::Encoder::new(w, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.to_rgb8(settings))?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Write this framebuffer as an uncompressed Radiance RGBE (`.hdr`) image of its linear
+    /// values.
+    pub fn write_hdr(&self, mut w: impl Write) -> io::Result<()> {
+        write!(
+            w,
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+            self.height, self.width
+        )?;
+        let data: Vec<u8> = self.pixels.iter().flat_map(|&c| to_rgbe(c)).collect();
+        w.write_all(&data)
+    }
+
+    /// Write this framebuffer as a color PFM (portable float map) image of its linear values,
+    /// in little-endian 32-bit floats.
+    pub fn write_pfm(&self, mut w: impl Write) -> io::Result<()> {
+        // A negative scale marks little-endian data, which is stored from the bottom row up.
+        write!(w, "PF\n{} {}\n-1.0\n", self.width, self.height)?;
+        let mut data = Vec::with_capacity(12 * self.pixels.len());
+        for row in self.pixels.chunks(self.width).rev() {
+            for c in row {
+                for channel in [c.x(), c.y(), c.z()] {
+                    data.extend_from_slice(&channel.to_le_bytes());
+                }
+            }
+        }
+        w.write_all(&data)
+    }
+
+    /// Save this framebuffer to `path`, choosing the format from its extension: `.png` or `.ppm`
+    /// (binary), which are encoded with `settings`, or `.hdr` or `.pfm`, which keep the linear
+    /// values and ignore `settings`.
+    pub fn save(
+        &self,
+        path: impl AsRef<Path>,
+        settings: &DisplaySettings,
+    ) -> Result<(), ImageError> {
+        let path = path.as_ref();
+        let ext = extension(path);
+        match ext.as_str() {
+            "png" => self.write_png(BufWriter::new(File::create(path)?), settings),
+            "ppm" => {
+                let mut w = BufWriter::new(File::create(path)?);
+                self.write_ppm_binary(&mut w, settings)?;
+                w.flush()?;
+                Ok(())
+            }
+            "hdr" | "pfm" => {
+                let mut w = BufWriter::new(File::create(path)?);
+                if ext == "hdr" {
+                    self.write_hdr(&mut w)?;
+                } else {
+                    self.write_pfm(&mut w)?;
+                }
+                w.flush()?;
+                Ok(())
+            }
+            _ => Err(ImageError::UnsupportedFormat(ext)),
+        }
+    }
+
+    /// Decode a PNG image. Channels are scaled to `[0, 1]` but otherwise kept as stored, so
+    /// gamma-encoded images stay gamma-encoded; alpha is ignored.
+    pub fn read_png(r: impl Read) -> Result<Framebuffer, ImageError> {
+        let mut decoder = png::Decoder::new(r);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+
+        let channels = info.color_type.samples();
+        let pixels = buf[..info.buffer_size()]
+            .chunks_exact(channels)
+            .map(|p| {
+                let c = |i: usize| p[i] as f32 / 255.0;
+                match channels {
+                    // Grayscale, with or without alpha.
+                    1 | 2 => Vector3::new(c(0), c(0), c(0)),
+                    _ => Vector3::new(c(0), c(1), c(2)),
+                }
+            })
+            .collect();
+        Ok(Framebuffer {
+            width: info.width as usize,
+            height: info.height as usize,
+            pixels,
+        })
+    }
+
+    /// Decode an ASCII (P3) or binary (P6) PPM image, scaling channels to `[0, 1]` as
+    /// `read_png` does.
+    pub fn read_ppm(mut r: impl Read) -> Result<Framebuffer, ImageError> {
+        let mut bytes = Vec::new();
+        r.read_to_end(&mut bytes)?;
+        let malformed = |message: &str| ImageError::Malformed(format!("PPM {}", message));
+
+        // The header is four whitespace-separated tokens, with `#` comments running to the end
+        // of a line. A binary image's data starts after the single whitespace byte that ends
+        // the last token.
+        let mut pos = 0;
+        let mut header = Vec::with_capacity(4);
+        while header.len() < 4 {
+            while pos < bytes.len() && (bytes[pos].is_ascii_whitespace() || bytes[pos] == b'#') {
+                if bytes[pos] == b'#' {
+                    while pos < bytes.len() && bytes[pos] != b'\n' {
+                        pos += 1;
+                    }
+                } else {
+                    pos += 1;
+                }
+            }
+            let start = pos;
+            while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if start == pos {
+                return Err(malformed("header is truncated"));
+            }
+            header.push(String::from_utf8_lossy(&bytes[start..pos]).into_owned());
+        }
+
+        let binary = match header[0].as_str() {
+            "P3" => false,
+            "P6" => true,
+            magic => return Err(malformed(&format!("magic \"{}\" is not P3 or P6", magic))),
+        };
+        let number = |token: &str, what: &str| {
+            token
+                .parse::<usize>()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| {
+                    malformed(&format!("{} \"{}\" is not a positive integer", what, token))
+                })
+        };
+        let width = number(&header[1], "width")?;
+        let height = number(&header[2], "height")?;
+        let max = number(&header[3], "maximum value")?;
+        if max > 65535 {
+            return Err(malformed("maximum value exceeds 65535"));
+        }
+        let count = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(3))
+            .ok_or_else(|| malformed(&format!("size {}x{} is too large", width, height)))?;
+
+        let values: Vec<usize> = if binary {
+            let data = bytes.get(pos + 1..).unwrap_or(&[]);
+            // Samples above 255 take two big-endian bytes.
+            let size = if max > 255 { 2 } else { 1 };
+            if data.len() / size < count {
+                return Err(malformed("pixel data is truncated"));
+            }
+            data.chunks_exact(size)
+                .take(count)
+                .map(|b| b.iter().fold(0, |acc, &byte| acc << 8 | byte as usize))
+                .collect()
+        } else {
+            let text = String::from_utf8_lossy(&bytes[pos..]);
+            let values = text
+                .split_ascii_whitespace()
+                .take(count)
+                .map(|token| {
+                    token
+                        .parse::<usize>()
+                        .map_err(|_| malformed(&format!("sample \"{}\" is not an integer", token)))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if values.len() < count {
+                return Err(malformed("pixel data is truncated"));
+            }
+            values
+        };
+
+        let scale = 1.0 / max as f32;
+        let pixels = values
+            .chunks_exact(3)
+            .map(|p| Vector3::new(p[0] as f32, p[1] as f32, p[2] as f32) * scale)
+            .collect();
+        Ok(Framebuffer {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Decode a Radiance RGBE (`.hdr`) image into linear colors. Scanlines may be stored flat or
+    /// with the usual per-channel run-length encoding.
+    pub fn read_hdr(mut r: impl Read) -> Result<Framebuffer, ImageError> {
+        let mut bytes = Vec::new();
+        r.read_to_end(&mut bytes)?;
+        let malformed = |message: &str| ImageError::Malformed(format!("HDR {}", message));
+
+        // The header is a block of text lines ended by a blank one, followed by the resolution.
+        let mut pos = 0;
+        let mut line = || {
+            let start = pos;
+            let end = bytes[start..].iter().position(|&b| b == b'\n')? + start;
+            pos = end + 1;
+            Some(String::from_utf8_lossy(&bytes[start..end]).into_owned())
+        };
+        let magic = line().unwrap_or_default();
+        if !magic.starts_with("#?") {
+            return Err(malformed("magic is missing"));
+        }
+        loop {
+            match line() {
+                None => return Err(malformed("header is truncated")),
+                Some(text) if text.is_empty() => break,
+                Some(text) => {
+                    if let Some(format) = text.strip_prefix("FORMAT=") {
+                        if format != "32-bit_rle_rgbe" {
+                            return Err(malformed(&format!("format \"{}\" is not RGBE", format)));
+                        }
+                    }
+                }
+            }
+        }
+        let resolution = line().ok_or_else(|| malformed("resolution is missing"))?;
+        let (height, width) = match resolution.split_ascii_whitespace().collect::<Vec<_>>()[..] {
+            ["-Y", height, "+X", width] => (height.parse::<usize>(), width.parse::<usize>()),
+            _ => {
+                return Err(malformed(&format!(
+                    "resolution \"{}\" is not \"-Y <height> +X <width>\"",
+                    resolution
+                )))
+            }
+        };
+        let (width, height) = match (width, height) {
+            (Ok(width), Ok(height)) if width > 0 && height > 0 => (width, height),
+            _ => return Err(malformed("size is not a pair of positive integers")),
+        };
+
+        let data = &bytes[pos..];
+        // Reject sizes the data could not hold even at the best compression, before allocating
+        // for them. Run-length encoded scanlines take at least a 4-byte marker and a 2-byte run
+        // per 127 bytes of each channel; others take 4 bytes per pixel.
+        let encodable = (8..0x8000).contains(&width);
+        let scanline_bytes = if encodable {
+            4 + 4 * 2 * width.div_ceil(127)
+        } else {
+            width.saturating_mul(4)
+        };
+        if scanline_bytes
+            .checked_mul(height)
+            .is_none_or(|n| n > data.len())
+        {
+            return Err(malformed(&format!(
+                "size {}x{} needs more pixel data than the file holds",
+                width, height
+            )));
+        }
+        let mut pos = 0;
+        let mut next = || {
+            let byte = data.get(pos).copied();
+            pos += 1;
+            byte.ok_or_else(|| malformed("pixel data is truncated"))
+        };
+        let mut pixels = Vec::with_capacity(width * height);
+        let mut scanline = vec![[0u8; 4]; width];
+        for _ in 0..height {
+            let first = [next()?, next()?, next()?, next()?];
+            let encoded = encodable && first[0] == 2 && first[1] == 2 && first[2] & 0x80 == 0;
+            if !encoded {
+                scanline[0] = first;
+                for pixel in &mut scanline[1..] {
+                    *pixel = [next()?, next()?, next()?, next()?];
+                }
+            } else {
+                if (first[2] as usize) << 8 | first[3] as usize != width {
+                    return Err(malformed("scanline width does not match the image"));
+                }
+                // Each channel is stored in turn, as runs of one repeated byte or of literals.
+                for channel in 0..4 {
+                    let mut x = 0;
+                    while x < width {
+                        let count = next()? as usize;
+                        let (count, run) = if count > 128 {
+                            (count - 128, true)
+                        } else {
+                            (count, false)
+                        };
+                        if count == 0 || x + count > width {
+                            return Err(malformed("run overflows its scanline"));
+                        }
+                        let value = if run { next()? } else { 0 };
+                        for pixel in &mut scanline[x..x + count] {
+                            pixel[channel] = if run { value } else { next()? };
+                        }
+                        x += count;
+                    }
+                }
+            }
+            pixels.extend(scanline.iter().map(|&p| from_rgbe(p)));
+        }
+        Ok(Framebuffer {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Decode a color (`PF`) or grayscale (`Pf`) PFM image into linear colors.
+    pub fn read_pfm(mut r: impl Read) -> Result<Framebuffer, ImageError> {
+        let mut bytes = Vec::new();
+        r.read_to_end(&mut bytes)?;
+        let malformed = |message: &str| ImageError::Malformed(format!("PFM {}", message));
+
+        // The header is four whitespace-separated tokens, the last followed by a single
+        // whitespace byte.
+        let mut pos = 0;
+        let mut header = Vec::with_capacity(4);
+        while header.len() < 4 {
+            while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            let start = pos;
+            while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if start == pos {
+                return Err(malformed("header is truncated"));
+            }
+            header.push(String::from_utf8_lossy(&bytes[start..pos]).into_owned());
+        }
+
+        let channels = match header[0].as_str() {
+            "PF" => 3,
+            "Pf" => 1,
+            magic => return Err(malformed(&format!("magic \"{}\" is not PF or Pf", magic))),
+        };
+        let number = |token: &str, what: &str| {
+            token
+                .parse::<usize>()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| {
+                    malformed(&format!("{} \"{}\" is not a positive integer", what, token))
+                })
+        };
+        let width = number(&header[1], "width")?;
+        let height = number(&header[2], "height")?;
+        let little_endian = match header[3].parse::<f32>() {
+            Ok(scale) if scale != 0.0 => scale < 0.0,
+            _ => {
+                return Err(malformed(&format!(
+                    "scale \"{}\" is not a non-zero number",
+                    header[3]
+                )))
+            }
+        };
+
+        let data = bytes.get(pos + 1..).unwrap_or(&[]);
+        let count = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(channels))
+            .ok_or_else(|| malformed(&format!("size {}x{} is too large", width, height)))?;
+        if data.len() / 4 < count {
+            return Err(malformed("pixel data is truncated"));
+        }
+        let values: Vec<f32> = data
+            .chunks_exact(4)
+            .take(count)
+            .map(|b| {
+                let b = [b[0], b[1], b[2], b[3]];
+                if little_endian {
+                    f32::from_le_bytes(b)
+                } else {
+                    f32::from_be_bytes(b)
+                }
+            })
+            .collect();
+        // Rows are stored from the bottom up.
+        let mut pixels = Vec::with_capacity(width * height);
+        for row in values.chunks_exact(channels * width).rev() {
+            pixels.extend(row.chunks_exact(channels).map(|p| match p {
+                [r, g, b] => Vector3::new(*r, *g, *b),
+                _ => Vector3::ones() * p[0],
+            }));
+        }
+        Ok(Framebuffer {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Load an image from `path`, choosing the format from its extension as `save` does.
+    pub fn load(path: impl AsRef<Path>) -> Result<Framebuffer, ImageError> {
+        let path = path.as_ref();
+        let ext = extension(path);
+        match ext.as_str() {
+            "png" => Framebuffer::read_png(BufReader::new(File::open(path)?)),
+            "ppm" => Framebuffer::read_ppm(BufReader::new(File::open(path)?)),
+            "hdr" => Framebuffer::read_hdr(BufReader::new(File::open(path)?)),
+            "pfm" => Framebuffer::read_pfm(BufReader::new(File::open(path)?)),
+            _ => Err(ImageError::UnsupportedFormat(ext)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn gradient() -> Framebuffer {
+        Framebuffer::from_fn(3, 2, |x, y| Vector3::new(x as f32 / 2.0, y as f32, 0.0))
+    }
+
+    #[test]
+    fn test_from_fn_layout() {
+        let fb = gradient();
+        assert_eq!(Vector3::new(1.0, 0.0, 0.0), fb.get(2, 0));
+        assert_eq!(Vector3::new(0.5, 1.0, 0.0), fb.get(1, 1));
+        assert_eq!(6, fb.pixels().len());
+    }
+
+    #[test]
+    fn test_encode() {
+        let linear = DisplaySettings {
+            gamma: 1.0,
+            ..DisplaySettings::default()
+        };
+        assert_eq!([0, 128, 255], linear.encode(Vector3::new(0.0, 0.5, 2.0)));
+
+        let gamma = DisplaySettings::default();
+        let [c, _, _] = gamma.encode(Vector3::new(0.5, 0.0, 0.0));
+        assert_eq!(
+            186, c,
+            "Expected 0.5 to encode as 186 with gamma 2.2, got {}.",
+            c
+        );
+
+        let nan = gamma.encode(Vector3::new(f32::NAN, 0.0, 0.0));
+        assert_eq!(
+            [0, 0, 0],
+            nan,
+            "Expected NaN to encode as black, got {:?}.",
+            nan
+        );
+    }
+
+    #[test]
+    fn test_tone_map() {
+        assert_eq!(0.5, ToneMap::Reinhard.apply(1.0));
+        assert_eq!(1.0, ToneMap::Clamp.apply(3.0));
+        for op in [ToneMap::Clamp, ToneMap::Reinhard, ToneMap::Aces] {
+            let low = op.apply(0.2);
+            let high = op.apply(100.0);
+            assert!(
+                low < high,
+                "{:?} is not monotonic: {} >= {}.",
+                op,
+                low,
+                high
+            );
+            assert!(high <= 1.0, "{:?} exceeded 1: {}.", op, high);
+        }
+    }
+
+    #[test]
+    fn test_ppm() {
+        let fb = gradient();
+        let settings = DisplaySettings {
+            gamma: 1.0,
+            ..DisplaySettings::default()
+        };
+
+        let mut ascii = Vec::new();
+        fb.write_ppm_ascii(&mut ascii, &settings).unwrap();
+        let ascii = String::from_utf8(ascii).unwrap();
+        assert_eq!(
+            "P3\n3 2\n255\n0 0 0 128 0 0 255 0 0\n0 255 0 128 255 0 255 255 0\n",
+            ascii
+        );
+
+        let mut binary = Vec::new();
+        fb.write_ppm_binary(&mut binary, &settings).unwrap();
+        let header = b"P6\n3 2\n255\n";
+        assert_eq!(&header[..], &binary[..header.len()]);
+        assert_eq!(header.len() + 18, binary.len());
+        assert_eq!(
+            &[128, 255, 0],
+            &binary[header.len() + 12..header.len() + 15]
+        );
+    }
+
+    #[test]
+    fn test_png() {
+        let fb = gradient();
+        let settings = DisplaySettings::default();
+        let mut bytes = Vec::new();
+        fb.write_png(&mut bytes, &settings).unwrap();
+
+        let decoder = png::Decoder::new(bytes.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).unwrap();
+        assert_eq!((3, 2), (info.width, info.height));
+        assert_eq!(fb.to_rgb8(&settings), &buf[..info.buffer_size()]);
+    }
+
+    #[test]
+    fn test_read() {
+        let fb = gradient();
+        let settings = DisplaySettings {
+            gamma: 1.0,
+            ..DisplaySettings::default()
+        };
+        let round_trip = |decoded: Framebuffer| {
+            assert_eq!((3, 2), (decoded.width(), decoded.height()));
+            assert_eq!(fb.to_rgb8(&settings), decoded.to_rgb8(&settings));
+        };
+
+        let mut bytes = Vec::new();
+        fb.write_png(&mut bytes, &settings).unwrap();
+        round_trip(Framebuffer::read_png(bytes.as_slice()).unwrap());
+
+        let mut bytes = Vec::new();
+        fb.write_ppm_binary(&mut bytes, &settings).unwrap();
+        round_trip(Framebuffer::read_ppm(bytes.as_slice()).unwrap());
+
+        let mut bytes = Vec::new();
+        fb.write_ppm_ascii(&mut bytes, &settings).unwrap();
+        round_trip(Framebuffer::read_ppm(bytes.as_slice()).unwrap());
+
+        let commented = "P3 # comment\n1 1\n# another\n15\n15 0 5\n";
+        let decoded = Framebuffer::read_ppm(commented.as_bytes()).unwrap();
+        assert_eq!(Vector3::new(1.0, 0.0, 1.0 / 3.0), decoded.get(0, 0));
+
+        for bad in [
+            "P5\n1 1\n255\n",
+            "P3\n1 1\n255\n1 2",
+            "P3\n0 1\n255\n",
+            "P6\n2",
+            "P6\n4000000000 4000000000\n255\n\0\0\0",
+        ] {
+            let err = Framebuffer::read_ppm(bad.as_bytes()).unwrap_err();
+            assert!(matches!(err, ImageError::Malformed(_)), "Got {:?}.", err);
+        }
+    }
+
+    #[test]
+    fn test_hdr_formats() {
+        let fb = Framebuffer::from_fn(3, 2, |x, y| {
+            Vector3::new(x as f32 * 100.0, 0.001 * y as f32, 0.5 + x as f32)
+        });
+
+        // PFM stores floats exactly.
+        let mut bytes = Vec::new();
+        fb.write_pfm(&mut bytes).unwrap();
+        assert!(bytes.starts_with(b"PF\n3 2\n-1.0\n"));
+        assert_eq!(fb, Framebuffer::read_pfm(bytes.as_slice()).unwrap());
+        let gray = b"Pf\n1 2\n1.0\n\x3f\x80\x00\x00\x40\x00\x00\x00";
+        let decoded = Framebuffer::read_pfm(&gray[..]).unwrap();
+        assert_eq!(
+            Vector3::ones() * 2.0,
+            decoded.get(0, 0),
+            "Expected the bottom row last."
+        );
+        assert_eq!(Vector3::ones(), decoded.get(0, 1));
+
+        // RGBE keeps each channel to within 1% of the brightest one in its pixel.
+        let mut bytes = Vec::new();
+        fb.write_hdr(&mut bytes).unwrap();
+        let decoded = Framebuffer::read_hdr(bytes.as_slice()).unwrap();
+        assert_eq!((3, 2), (decoded.width(), decoded.height()));
+        for (expected, got) in fb.pixels().iter().zip(decoded.pixels()) {
+            let tolerance = 0.01 * expected.max_component();
+            assert!(
+                expected.approx_eq(*got, tolerance),
+                "Expected {}, got {}.",
+                expected,
+                got
+            );
+        }
+
+        // A run-length encoded scanline of 8 pixels: red as literals, the rest as runs.
+        let mut rle = b"#?RADIANCE\n\n-Y 1 +X 8\n\x02\x02\x00\x08".to_vec();
+        rle.extend([8, 128, 144, 160, 176, 192, 208, 224, 240]);
+        rle.extend([136, 0, 136, 128, 136, 129]);
+        let decoded = Framebuffer::read_hdr(rle.as_slice()).unwrap();
+        assert_eq!(8, decoded.width());
+        // Mantissas decode to the middle of their range, scaled by 2^(129 - 136).
+        assert_eq!(Vector3::new(128.5, 0.5, 128.5) / 128.0, decoded.get(0, 0));
+        assert_eq!(Vector3::new(240.5, 0.5, 128.5) / 128.0, decoded.get(7, 0));
+
+        for bad in [
+            &b"P6\n1 1\n255\n"[..],
+            b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n\x80\x80\x80\x80",
+            b"#?RADIANCE\n\n+Y 1 +X 1\n\x80\x80\x80\x80",
+            b"#?RADIANCE\n\n-Y 2 +X 1\n\x80\x80\x80\x80",
+            b"#?RADIANCE\n\n-Y 1 +X 8\n\x02\x02\x00\x08\x89\x00",
+            // Sizes too large for the data fail without allocating for them.
+            b"#?RADIANCE\n\n-Y 1000000000 +X 1000000000\n\x80\x80\x80\x80",
+            b"#?RADIANCE\n\n-Y 1000000 +X 1000\n\x02\x02\x03\xe8",
+        ] {
+            let err = Framebuffer::read_hdr(bad).unwrap_err();
+            assert!(matches!(err, ImageError::Malformed(_)), "Got {:?}.", err);
+        }
+        for bad in [
+            &b"PF\n1 1\n-1.0\n\x00"[..],
+            b"PF\n4000000000 4000000000\n-1.0\n\x00",
+        ] {
+            let err = Framebuffer::read_pfm(bad).unwrap_err();
+            assert!(matches!(err, ImageError::Malformed(_)), "Got {:?}.", err);
+        }
+    }
+}
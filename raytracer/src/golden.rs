@@ -0,0 +1,203 @@
+//! Comparison of rendered images against stored references, for golden-image regression tests.
+//!
+//! Images are compared as they would be displayed, after tone mapping and gamma encoding, so
+//! that differences count roughly as much as they show.
+
+use crate::framebuffer::{DisplaySettings, Framebuffer};
+use crate::vector::Vector3;
+
+/// Width of the square blocks of pixels averaged before measuring `ImageDiff::block_rmse`.
+pub const BLOCK: usize = 8;
+
+/// How far an image may stray from its reference and still match it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+    /// Largest root-mean-square difference allowed over all channels, in display units of
+    /// `[0, 1]`.
+    pub rmse: f32,
+    /// Largest root-mean-square difference allowed between the averages of blocks of pixels.
+    /// Averaging smooths away most of the noise, so this can be much tighter than `rmse` and
+    /// still catch a slight change in brightness or color.
+    pub block_rmse: f32,
+    /// Difference in any channel beyond which a pixel counts as an outlier.
+    pub pixel: f32,
+    /// Largest fraction of outlier pixels allowed, so that a few stray noisy samples do not fail
+    /// a comparison.
+    pub outliers: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance {
+            rmse: 0.02,
+            block_rmse: 0.003,
+            pixel: 0.25,
+            outliers: 0.01,
+        }
+    }
+}
+
+/// Return `image`, which holds linear radiance, as display values in `[0, 1]`, exactly as they
+/// would be stored in an 8-bit image written with `settings`.
+pub fn display_encoded(image: &Framebuffer, settings: &DisplaySettings) -> Framebuffer {
+    Framebuffer::from_fn(image.width(), image.height(), |x, y| {
+        let c = settings.encode(image.get(x, y)).map(|c| c as f32 / 255.0);
+        Vector3::new(c[0], c[1], c[2])
+    })
+}
+
+/// The differences between an image and its reference, both holding display values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageDiff {
+    /// Largest difference in any channel of each pixel, row-major from the top-left.
+    errors: Vec<f32>,
+    /// Absolute difference of each channel.
+    difference: Framebuffer,
+    /// Root-mean-square difference over all channels.
+    pub rmse: f32,
+    /// Root-mean-square difference over all channels between the averages of each block of
+    /// `BLOCK` by `BLOCK` pixels.
+    pub block_rmse: f32,
+    /// Largest difference in any channel of any pixel.
+    pub max_error: f32,
+}
+
+impl ImageDiff {
+    /// Compare `image` with `reference`, or return `None` if their sizes differ.
+    pub fn new(reference: &Framebuffer, image: &Framebuffer) -> Option<Self> {
+        if (reference.width(), reference.height()) != (image.width(), image.height()) {
+            return None;
+        }
+        let difference = Framebuffer::from_fn(image.width(), image.height(), |x, y| {
+            let d = image.get(x, y) - reference.get(x, y);
+            Vector3::new(d.x().abs(), d.y().abs(), d.z().abs())
+        });
+        let errors: Vec<f32> = difference
+            .pixels()
+            .iter()
+            .map(|d| d.max_component())
+            .collect();
+        let squared: f32 = difference.pixels().iter().map(|d| d.squared_norm()).sum();
+        let rmse = (squared / (3 * errors.len()).max(1) as f32).sqrt();
+        let max_error = errors.iter().copied().fold(0.0, f32::max);
+        Some(ImageDiff {
+            errors,
+            difference,
+            rmse,
+            block_rmse: block_rmse(reference, image),
+            max_error,
+        })
+    }
+
+    /// Return the fraction of pixels differing by more than `threshold` in some channel.
+    pub fn outliers(&self, threshold: f32) -> f32 {
+        let count = self.errors.iter().filter(|&&e| e > threshold).count();
+        count as f32 / self.errors.len().max(1) as f32
+    }
+
+    /// Return whether the image matches its reference within `tolerance`.
+    pub fn within(&self, tolerance: &Tolerance) -> bool {
+        self.rmse <= tolerance.rmse
+            && self.block_rmse <= tolerance.block_rmse
+            && self.outliers(tolerance.pixel) <= tolerance.outliers
+    }
+
+    /// Return an image of the differences, amplified four times so that small ones show. Write
+    /// it with a gamma of one to see them as they are.
+    pub fn image(&self) -> Framebuffer {
+        let mut image = self.difference.clone();
+        for pixel in image.pixels_mut() {
+            *pixel = (*pixel * 4.0).min(Vector3::ones());
+        }
+        image
+    }
+}
+
+/// Return the root-mean-square difference between the averages of each block of `BLOCK` by
+/// `BLOCK` pixels of two images of the same size. Blocks at the right and bottom edges may be
+/// smaller.
+fn block_rmse(reference: &Framebuffer, image: &Framebuffer) -> f32 {
+    let (width, height) = (image.width(), image.height());
+    let mut squared = 0.0;
+    let mut blocks = 0;
+    for y0 in (0..height).step_by(BLOCK) {
+        for x0 in (0..width).step_by(BLOCK) {
+            let (x1, y1) = ((x0 + BLOCK).min(width), (y0 + BLOCK).min(height));
+            let mut sum = Vector3::zeros();
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    sum += image.get(x, y) - reference.get(x, y);
+                }
+            }
+            squared += (sum / ((x1 - x0) * (y1 - y0)) as f32).squared_norm();
+            blocks += 1;
+        }
+    }
+    (squared / (3 * blocks).max(1) as f32).sqrt()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_image_diff() {
+        let reference = Framebuffer::from_fn(40, 40, |x, y| {
+            Vector3::new(x as f32 / 40.0, y as f32 / 40.0, 0.5)
+        });
+        let same = ImageDiff::new(&reference, &reference).unwrap();
+        assert_eq!(
+            (0.0, 0.0, 0.0),
+            (same.rmse, same.max_error, same.outliers(0.0))
+        );
+        assert!(same.within(&Tolerance::default()));
+
+        // One pixel far off is an outlier that the default tolerance lets through.
+        let mut speck = reference.clone();
+        speck.set(12, 4, Vector3::new(1.0, 0.1, 0.5));
+        let diff = ImageDiff::new(&reference, &speck).unwrap();
+        assert_eq!(0.7, diff.max_error);
+        assert_eq!(1.0 / 1600.0, diff.outliers(0.25));
+        assert!((diff.rmse - (0.49f32 / 4800.0).sqrt()).abs() < 1e-6);
+        assert!(diff.within(&Tolerance::default()));
+        assert_eq!(Vector3::new(1.0, 0.0, 0.0), diff.image().get(12, 4));
+        assert_eq!(Vector3::zeros(), diff.image().get(4, 12));
+
+        // Noise that averages to nothing over each block passes the block RMSE, but a slight
+        // shift everywhere fails it.
+        let mut noisy = reference.clone();
+        for (i, pixel) in noisy.pixels_mut().iter_mut().enumerate() {
+            let sign = if (i + i / 40) % 2 == 0 { 1.0 } else { -1.0 };
+            *pixel += Vector3::ones() * (0.015 * sign);
+        }
+        let diff = ImageDiff::new(&reference, &noisy).unwrap();
+        assert!(diff.block_rmse < 1e-6 && diff.within(&Tolerance::default()));
+
+        let mut tinted = reference.clone();
+        for pixel in tinted.pixels_mut() {
+            *pixel += Vector3::new(0.01, 0.01, 0.01);
+        }
+        let diff = ImageDiff::new(&reference, &tinted).unwrap();
+        assert_eq!(0.0, diff.outliers(0.25));
+        assert!(diff.rmse < 0.02 && (diff.block_rmse - 0.01).abs() < 1e-5);
+        assert!(!diff.within(&Tolerance::default()));
+
+        assert!(ImageDiff::new(&reference, &Framebuffer::new(40, 39)).is_none());
+    }
+
+    #[test]
+    fn test_display_encoded() {
+        let image = Framebuffer::from_fn(2, 1, |x, _| Vector3::ones() * (x as f32 * 4.0));
+        let settings = DisplaySettings::default();
+        let encoded = display_encoded(&image, &settings);
+        assert_eq!(Vector3::zeros(), encoded.get(0, 0));
+        assert_eq!(Vector3::ones(), encoded.get(1, 0));
+
+        // Display values survive a round trip through an 8-bit image unchanged.
+        let image = Framebuffer::from_fn(3, 2, |x, y| Vector3::new(0.1 * x as f32, 0.3, y as f32));
+        let encoded = display_encoded(&image, &settings);
+        let mut bytes = Vec::new();
+        image.write_png(&mut bytes, &settings).unwrap();
+        assert_eq!(encoded, Framebuffer::read_png(bytes.as_slice()).unwrap());
+    }
+}
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+
+use crate::quaternion::Quaternion;
+use crate::transform::Transform;
+use crate::vector::Vector3;
+
+/// A rotation by `angle` degrees counter-clockwise about `axis`, which need not be normalized.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AxisAngle {
+    pub axis: Vector3,
+    pub angle: f32,
+}
+
+/// The placement of a node within its parent: scaled by `scale`, then rotated by `rotate`, then
+/// moved by `translate`.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NodeTransform {
+    pub translate: Vector3,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotate: Option<AxisAngle>,
+    /// Factor to scale each axis by, none of which may be zero.
+    pub scale: Vector3,
+}
+
+impl Default for NodeTransform {
+    fn default() -> Self {
+        NodeTransform {
+            translate: Vector3::zeros(),
+            rotate: None,
+            scale: Vector3::ones(),
+        }
+    }
+}
+
+impl NodeTransform {
+    /// Return whether this leaves a node where its parent is.
+    pub fn is_identity(&self) -> bool {
+        *self == NodeTransform::default()
+    }
+
+    /// Return the transform from the node's space into its parent's.
+    pub fn transform(&self) -> Transform {
+        let rotation = match self.rotate {
+            Some(AxisAngle { axis, angle }) => {
+                Transform::rotation(Quaternion::from_axis_angle(axis, angle.to_radians()))
+            }
+            None => Transform::identity(),
+        };
+        Transform::translation(self.translate) * rotation * Transform::scaling(self.scale)
+    }
+}
+
+/// A node of a scene graph: a frame placed within its parent's, which may hold a copy of a shared
+/// asset and further nodes placed within it.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Node {
+    /// Name to find the node by, unique within its scene.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "NodeTransform::is_identity")]
+    pub transform: NodeTransform,
+    /// Name of the asset placed at this node, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+    /// Material to use for every surface of this node's asset in place of its own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub material: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    /// Call `visit` with this node and then each of its descendants, depth first, stopping at the
+    /// first error. Each is passed with its path, starting from `path` for this node, and the
+    /// transform from its space into the world, given that this node's parent is placed by
+    /// `parent`.
+    pub fn walk<'a, E>(
+        &'a self,
+        path: &str,
+        parent: Transform,
+        visit: &mut impl FnMut(&str, &'a Node, Transform) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let transform = parent * self.transform.transform();
+        visit(path, self, transform)?;
+        for (i, child) in self.children.iter().enumerate() {
+            child.walk(&format!("{}.children[{}]", path, i), transform, visit)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(expected: Vector3, actual: Vector3) {
+        assert!(
+            (expected - actual).norm() < 1e-5,
+            "Expected {:.4}, got {:.4}.",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_walk() {
+        // A parent turned a quarter turn about y, with a child scaled and moved along its x axis.
+        let child = Node {
+            name: Some("child".to_string()),
+            transform: NodeTransform {
+                translate: Vector3::new(2.0, 0.0, 0.0),
+                scale: Vector3::new(1.0, 3.0, 1.0),
+                ..NodeTransform::default()
+            },
+            ..Node::default()
+        };
+        let root = Node {
+            transform: NodeTransform {
+                translate: Vector3::new(0.0, 1.0, 0.0),
+                rotate: Some(AxisAngle {
+                    axis: Vector3::new(0.0, 2.0, 0.0),
+                    angle: 90.0,
+                }),
+                ..NodeTransform::default()
+            },
+            children: vec![Node::default(), child],
+            ..Node::default()
+        };
+
+        let mut visited = Vec::new();
+        root.walk(
+            "nodes[0]",
+            Transform::identity(),
+            &mut |path, node, transform| {
+                visited.push((path.to_string(), node.name.clone(), transform));
+                Ok::<_, ()>(())
+            },
+        )
+        .unwrap();
+        let paths: Vec<&str> = visited.iter().map(|(path, ..)| path.as_str()).collect();
+        assert_eq!(
+            vec!["nodes[0]", "nodes[0].children[0]", "nodes[0].children[1]"],
+            paths
+        );
+
+        // The parent's x axis points along -z once turned, and the child scales before moving.
+        let (_, name, transform) = &visited[2];
+        assert_eq!(Some("child"), name.as_deref());
+        assert_close(
+            Vector3::new(0.0, 1.0, -2.0),
+            transform.point(Vector3::zeros()),
+        );
+        assert_close(
+            Vector3::new(0.0, 4.0, -2.0),
+            transform.point(Vector3::new(0.0, 1.0, 0.0)),
+        );
+        assert!(visited[1].2 == visited[0].2 && NodeTransform::default().is_identity());
+
+        // The walk stops at the first error.
+        let mut count = 0;
+        let result = root.walk("root", Transform::identity(), &mut |path, _, _| {
+            count += 1;
+            if path.ends_with("[0]") {
+                return Err(path.to_string());
+            }
+            Ok(())
+        });
+        assert_eq!(Err("root.children[0]".to_string()), result);
+        assert_eq!(2, count);
+    }
+}
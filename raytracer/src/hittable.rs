@@ -0,0 +1,279 @@
+use serde::{Deserialize, Serialize};
+
+use crate::aabb::Aabb;
+use crate::ray::Ray;
+use crate::rng::Pcg32;
+use crate::vector::Vector3;
+
+/// Handle to a material owned by the scene. Geometry stores the handle rather than the material
+/// itself so that many primitives can share one material.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct MaterialId(pub usize);
+
+/// Information about a ray-surface intersection.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HitRecord {
+    /// The point of intersection.
+    pub point: Vector3,
+    /// The unit surface normal at `point`, always facing against the incoming ray.
+    pub normal: Vector3,
+    /// The ray parameter at which the intersection occurred.
+    pub t: f32,
+    /// Whether the ray hit the outside of the surface.
+    pub front_face: bool,
+    /// The material of the surface that was hit.
+    pub material: MaterialId,
+    /// Surface texture coordinates of `point`.
+    pub u: f32,
+    pub v: f32,
+    /// Index of the scene object that was hit, if it was tagged with one by `Tagged`.
+    pub object: Option<usize>,
+}
+
+/// Counts of the work done to intersect a ray with a scene.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TraversalStats {
+    /// Acceleration structure nodes visited.
+    pub nodes: u32,
+    /// Primitives tested for intersection.
+    pub tests: u32,
+    /// Primitive tests that found an intersection.
+    pub hits: u32,
+}
+
+impl HitRecord {
+    /// Create a new `HitRecord` for `ray` at parameter `t`, orienting the unit `outward_normal`
+    /// so that it faces against the ray.
+    pub fn new(ray: &Ray, t: f32, outward_normal: Vector3, material: MaterialId) -> Self {
+        let front_face = ray.direction.dot(outward_normal) < 0.0;
+        let normal = if front_face {
+            outward_normal
+        } else {
+            -outward_normal
+        };
+
+        HitRecord {
+            point: ray.at(t),
+            normal,
+            t,
+            front_face,
+            material,
+            u: 0.0,
+            v: 0.0,
+            object: None,
+        }
+    }
+
+    /// Set the texture coordinates of this record.
+    pub fn with_uv(mut self, u: f32, v: f32) -> Self {
+        self.u = u;
+        self.v = v;
+        self
+    }
+}
+
+/// Anything that a ray can intersect.
+pub trait Hittable: Send + Sync {
+    /// Return the closest intersection of `ray` with this object with `t` in `(t_min, t_max)`,
+    /// if there is one.
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord>;
+
+    /// Return a box enclosing this object, or `None` if it is unbounded.
+    fn bounding_box(&self) -> Option<Aabb>;
+
+    /// Intersect as `hit` does, adding the work done to `stats`. Primitives count as one test;
+    /// aggregates override this to count their members and nodes.
+    fn hit_counted(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord> {
+        stats.tests += 1;
+        let hit = self.hit(ray, t_min, t_max);
+        if hit.is_some() {
+            stats.hits += 1;
+        }
+        hit
+    }
+
+    /// Sample a direction from `origin` towards this object, so that emissive objects can be
+    /// sampled directly as lights. Return `None` if the object does not support sampling or
+    /// cannot be seen from `origin`.
+    fn sample_direction(&self, _origin: Vector3, _rng: &mut Pcg32) -> Option<Vector3> {
+        None
+    }
+
+    /// Return the density, in solid angle, with which `sample_direction` picks `direction` from
+    /// `origin`.
+    fn pdf_direction(&self, _origin: Vector3, _direction: Vector3) -> f32 {
+        0.0
+    }
+}
+
+/// A collection of hittables that is intersected by testing every member in turn.
+#[derive(Default)]
+pub struct HittableList {
+    objects: Vec<Box<dyn Hittable>>,
+}
+
+impl HittableList {
+    /// Create a new, empty `HittableList`.
+    pub fn new() -> Self {
+        HittableList {
+            objects: Vec::new(),
+        }
+    }
+
+    /// Add `object` to this list.
+    pub fn add(&mut self, object: impl Hittable + 'static) {
+        self.objects.push(Box::new(object));
+    }
+
+    /// Return the number of objects in this list.
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Return whether this list contains no objects.
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Consume this list, returning its objects.
+    pub fn into_objects(self) -> Vec<Box<dyn Hittable>> {
+        self.objects
+    }
+}
+
+impl Hittable for HittableList {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.hit_counted(ray, t_min, t_max, &mut TraversalStats::default())
+    }
+
+    fn hit_counted(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord> {
+        let mut closest = None;
+        let mut t_closest = t_max;
+        for object in &self.objects {
+            if let Some(hit) = object.hit_counted(ray, t_min, t_closest, stats) {
+                t_closest = hit.t;
+                closest = Some(hit);
+            }
+        }
+        closest
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        if self.objects.is_empty() {
+            return None;
+        }
+        let mut bounds = Aabb::empty();
+        for object in &self.objects {
+            bounds = bounds.union(object.bounding_box()?);
+        }
+        Some(bounds)
+    }
+}
+
+/// Wraps a hittable, marking every hit on it with the index of the scene object it came from.
+pub struct Tagged<H> {
+    pub object: H,
+    pub id: usize,
+}
+
+impl<H: Hittable> Tagged<H> {
+    /// Create a new `Tagged` hittable marking hits on `object` with `id`.
+    pub fn new(object: H, id: usize) -> Self {
+        Tagged { object, id }
+    }
+}
+
+impl<H: Hittable> Hittable for Tagged<H> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.hit_counted(ray, t_min, t_max, &mut TraversalStats::default())
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.object.bounding_box()
+    }
+
+    fn hit_counted(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord> {
+        let mut hit = self.object.hit_counted(ray, t_min, t_max, stats)?;
+        hit.object = Some(self.id);
+        Some(hit)
+    }
+
+    fn sample_direction(&self, origin: Vector3, rng: &mut Pcg32) -> Option<Vector3> {
+        self.object.sample_direction(origin, rng)
+    }
+
+    fn pdf_direction(&self, origin: Vector3, direction: Vector3) -> f32 {
+        self.object.pdf_direction(origin, direction)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::shapes::Sphere;
+
+    #[test]
+    fn test_face_normal() {
+        let ray = Ray::new(Vector3::zeros(), Vector3::new(0.0, 0.0, -1.0));
+        let outward = Vector3::new(0.0, 0.0, 1.0);
+
+        let hit = HitRecord::new(&ray, 1.0, outward, MaterialId(0));
+        assert!(hit.front_face, "Expected a front-face hit, got {:?}.", hit);
+        assert_eq!(
+            outward, hit.normal,
+            "HitRecord::new() failed. Expected normal {}, got {}.",
+            outward, hit.normal
+        );
+
+        let hit = HitRecord::new(&ray, 1.0, -outward, MaterialId(0));
+        assert!(!hit.front_face, "Expected a back-face hit, got {:?}.", hit);
+        assert_eq!(
+            outward, hit.normal,
+            "HitRecord::new() failed. Expected normal {}, got {}.",
+            outward, hit.normal
+        );
+    }
+
+    #[test]
+    fn test_list_closest() {
+        let mut list = HittableList::new();
+        list.add(Sphere::new(
+            Vector3::new(0.0, 0.0, -10.0),
+            1.0,
+            MaterialId(0),
+        ));
+        list.add(Sphere::new(
+            Vector3::new(0.0, 0.0, -5.0),
+            1.0,
+            MaterialId(1),
+        ));
+
+        let ray = Ray::new(Vector3::zeros(), Vector3::new(0.0, 0.0, -1.0));
+        let hit = list.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert_eq!(
+            MaterialId(1),
+            hit.material,
+            "HittableList::hit() returned {:?}, expected the nearer sphere.",
+            hit
+        );
+        assert_eq!(4.0, hit.t, "Expected t = 4, got {}.", hit.t);
+    }
+}
@@ -0,0 +1,327 @@
+use crate::hittable::HitRecord;
+use crate::ray::Ray;
+use crate::rng::Pcg32;
+use crate::sampling::power_heuristic;
+use crate::scene::RenderSettings;
+use crate::vector::Color;
+use crate::world::World;
+
+/// A unidirectional Monte Carlo path tracer.
+///
+/// At every diffuse bounce the tracer samples a light directly (next-event estimation) as well as
+/// the material, and combines the two estimates with multiple importance sampling. Paths longer
+/// than `roulette_depth` are terminated at random in proportion to their remaining throughput.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PathTracer {
+    /// Maximum number of bounces along a path.
+    pub max_depth: u32,
+    /// Number of bounces before Russian roulette starts.
+    pub roulette_depth: u32,
+}
+
+impl PathTracer {
+    /// Create a new `PathTracer` following at most `max_depth` bounces.
+    pub fn new(max_depth: u32, roulette_depth: u32) -> Self {
+        PathTracer {
+            max_depth,
+            roulette_depth,
+        }
+    }
+
+    /// Create a new `PathTracer` from the depth limits in `settings`.
+    pub fn from_settings(settings: &RenderSettings) -> Self {
+        PathTracer::new(settings.max_depth, settings.roulette_depth)
+    }
+
+    /// Estimate the radiance arriving along `ray`.
+    pub fn radiance(&self, world: &World, ray: &Ray, rng: &mut Pcg32) -> Color {
+        let mut radiance = Color::zeros();
+        let mut throughput = Color::ones();
+        let mut ray = *ray;
+        // Density of the material sample that produced `ray`, or `None` if emission it hits
+        // cannot have been found by light sampling.
+        let mut bsdf_pdf: Option<f32> = None;
+
+        for depth in 0..self.max_depth {
+            let hit = match world.hit(&ray) {
+                Some(hit) => hit,
+                None => {
+                    let background = world.background(ray.direction);
+                    if background != Color::zeros() {
+                        let weight = match bsdf_pdf {
+                            Some(pdf) => power_heuristic(pdf, world.environment_pdf(ray.direction)),
+                            None => 1.0,
+                        };
+                        radiance += throughput.cwise_mul(background) * weight;
+                    }
+                    break;
+                }
+            };
+            let material = world.material(hit.material);
+
+            let emitted = material.emitted(&hit);
+            if emitted != Color::zeros() {
+                let weight = match bsdf_pdf {
+                    Some(pdf) => power_heuristic(pdf, world.light_pdf(ray.origin, ray.direction)),
+                    None => 1.0,
+                };
+                radiance += throughput.cwise_mul(emitted) * weight;
+            }
+
+            let scatter = match material.scatter(&ray, &hit, rng) {
+                Some(scatter) => scatter,
+                None => break,
+            };
+
+            if scatter.pdf.is_some() {
+                radiance += throughput.cwise_mul(self.sample_light(world, &ray, &hit, rng));
+            }
+
+            throughput = throughput.cwise_mul(scatter.attenuation);
+            bsdf_pdf = scatter.pdf;
+            ray = scatter.ray;
+
+            if depth + 1 >= self.roulette_depth {
+                let survive = throughput.max_component().min(0.95);
+                if rng.next_f32() >= survive {
+                    break;
+                }
+                throughput /= survive;
+            }
+        }
+
+        radiance
+    }
+
+    /// Estimate the light arriving at `hit` directly from a sampled light and scattered back
+    /// along `ray`, weighted against material sampling.
+    fn sample_light(&self, world: &World, ray: &Ray, hit: &HitRecord, rng: &mut Pcg32) -> Color {
+        let sample = match world.sample_light(hit.point, rng) {
+            Some(sample) if sample.pdf > 0.0 => sample,
+            _ => return Color::zeros(),
+        };
+
+        let material = world.material(hit.material);
+        let f = material.eval(ray, hit, sample.direction);
+        if f == Color::zeros() {
+            return Color::zeros();
+        }
+
+        // The shadow ray must reach the light before anything else.
+        if !world.visible(hit.point, sample.direction, sample.distance, ray.time) {
+            return Color::zeros();
+        }
+
+        // Delta lights can only be found by sampling them, so they take the whole weight.
+        let weight = if sample.delta {
+            1.0
+        } else {
+            power_heuristic(sample.pdf, material.pdf(ray, hit, sample.direction))
+        };
+        f.cwise_mul(sample.radiance) * (weight / sample.pdf)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::environment::{Environment, EnvironmentMap};
+    use crate::framebuffer::Framebuffer;
+    use crate::hittable::{HittableList, MaterialId};
+    use crate::light::{AreaLight, PointLight};
+    use crate::material::{DiffuseLight, Lambertian, Material};
+    use crate::shapes::{Plane, Sphere};
+    use crate::texture::SolidColor;
+    use crate::vector::Vector3;
+
+    #[test]
+    fn test_direct_emission() {
+        let mut objects = HittableList::new();
+        objects.add(Sphere::new(
+            Vector3::new(0.0, 0.0, -2.0),
+            0.5,
+            MaterialId(0),
+        ));
+        let materials: Vec<Box<dyn Material>> =
+            vec![Box::new(DiffuseLight::new(Color::new(1.0, 2.0, 3.0)))];
+        let world = World::new(objects, materials);
+        let tracer = PathTracer::new(4, 3);
+        let mut rng = Pcg32::new(0, 0);
+
+        let toward_light = Ray::new(Vector3::zeros(), Vector3::new(0.0, 0.0, -1.0));
+        assert_eq!(
+            Color::new(1.0, 2.0, 3.0),
+            tracer.radiance(&world, &toward_light, &mut rng)
+        );
+        let no_bounces = PathTracer::new(0, 0);
+        assert_eq!(
+            Color::zeros(),
+            no_bounces.radiance(&world, &toward_light, &mut rng)
+        );
+
+        let miss = Ray::new(Vector3::zeros(), Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(Color::zeros(), tracer.radiance(&world, &miss, &mut rng));
+    }
+
+    /// Radiance reflected straight up off a white diffuse floor lit by a small spherical light.
+    fn floor_radiance(lights: bool, samples: u32) -> f32 {
+        let light = || Sphere::new(Vector3::new(0.0, 2.0, 0.0), 0.25, MaterialId(1));
+        let mut objects = HittableList::new();
+        objects.add(Plane::new(
+            Vector3::zeros(),
+            Vector3::new(0.0, 1.0, 0.0),
+            MaterialId(0),
+        ));
+        objects.add(light());
+        let materials: Vec<Box<dyn Material>> = vec![
+            Box::new(Lambertian::new(Color::ones())),
+            Box::new(DiffuseLight::new(Color::ones() * 10.0)),
+        ];
+        let mut world = World::new(objects, materials);
+        if lights {
+            let emit = Arc::new(SolidColor::new(Color::ones() * 10.0));
+            world = world.with_lights(vec![Box::new(AreaLight::new(Box::new(light()), emit))]);
+        }
+
+        // Only direct light matters: the floor is the only other surface and faces away from
+        // itself.
+        let tracer = PathTracer::new(2, 8);
+        let mut rng = Pcg32::new(1, 0);
+        let ray = Ray::new(Vector3::new(0.5, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let sum: f32 = (0..samples)
+            .map(|_| tracer.radiance(&world, &ray, &mut rng).x())
+            .sum();
+        sum / samples as f32
+    }
+
+    #[test]
+    fn test_light_sampling_is_unbiased() {
+        // The light subtends a cone of half-angle theta from the shaded point at (0.5, 0, 0), so
+        // the reflected radiance is L * (solid angle) * cos / pi, with cos taken at the cone axis
+        // as the cone is narrow.
+        let to_light = Vector3::new(-0.5, 2.0, 0.0);
+        let sin_max: f32 = 0.25 / to_light.norm();
+        let solid_angle = 2.0 * std::f32::consts::PI * (1.0 - (1.0 - sin_max * sin_max).sqrt());
+        let cosine = to_light.normalized().y();
+        let expected = 10.0 * solid_angle * cosine / std::f32::consts::PI;
+
+        let with_nee = floor_radiance(true, 2000);
+        assert!(
+            (with_nee - expected).abs() < 0.03 * expected,
+            "Expected {}, got {} with light sampling.",
+            expected,
+            with_nee
+        );
+        let without_nee = floor_radiance(false, 200000);
+        assert!(
+            (without_nee - expected).abs() < 0.1 * expected,
+            "Expected {}, got {} without light sampling.",
+            expected,
+            without_nee
+        );
+    }
+
+    #[test]
+    fn test_environment_lighting() {
+        // A diffuse floor of albedo a under an environment reflects (a / pi) times the cosine-
+        // weighted integral of the radiance over the upper hemisphere.
+        let floor = |environment: Environment, samples: u32| {
+            let mut objects = HittableList::new();
+            objects.add(Plane::new(
+                Vector3::zeros(),
+                Vector3::new(0.0, 1.0, 0.0),
+                MaterialId(0),
+            ));
+            let materials: Vec<Box<dyn Material>> =
+                vec![Box::new(Lambertian::new(Color::ones() * 0.5))];
+            let world = World::new(objects, materials).with_environment(environment);
+            let tracer = PathTracer::new(2, 8);
+            let mut rng = Pcg32::new(2, 0);
+            let ray = Ray::new(Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+            let sum: f32 = (0..samples)
+                .map(|_| tracer.radiance(&world, &ray, &mut rng).x())
+                .sum();
+            sum / samples as f32
+        };
+
+        let lit = floor(Environment::Constant(Color::ones()), 4000);
+        assert!((lit - 0.5).abs() < 0.01, "Expected 0.5, got {}.", lit);
+        let sky = Ray::new(Vector3::zeros(), Vector3::new(0.0, 1.0, 0.0));
+        let world = World::new(HittableList::new(), Vec::new())
+            .with_environment(Environment::Constant(Color::ones() * 2.0));
+        assert_eq!(
+            Color::ones() * 2.0,
+            PathTracer::new(1, 1).radiance(&world, &sky, &mut Pcg32::new(0, 0))
+        );
+
+        // An 8 by 4 map, with a bright pixel just above the horizon. Pixels in row y span
+        // 2 pi / 8 in azimuth, and their cosine-weighted solid angle integrates to
+        // (sin^2(theta1) - sin^2(theta0)) / 2 in height.
+        let radiance = |x: usize, y: usize| {
+            if (x, y) == (3, 1) {
+                50.0
+            } else {
+                1.0 + x as f32 / 4.0 + y as f32
+            }
+        };
+        let image = Framebuffer::from_fn(8, 4, |x, y| Color::ones() * radiance(x, y));
+        let mut expected = 0.0;
+        for y in 0..2 {
+            let sin = |y: usize| (std::f32::consts::PI * y as f32 / 4.0).sin();
+            let band = (sin(y + 1).powi(2) - sin(y).powi(2)) / 2.0;
+            for x in 0..8 {
+                expected += 0.5 / std::f32::consts::PI
+                    * radiance(x, y)
+                    * (2.0 * std::f32::consts::PI / 8.0)
+                    * band;
+            }
+        }
+        let lit = floor(Environment::Map(EnvironmentMap::new(image, 0.0)), 4000);
+        assert!(
+            (lit - expected).abs() < 0.03 * expected,
+            "Expected {}, got {}.",
+            expected,
+            lit
+        );
+    }
+
+    #[test]
+    fn test_point_light_and_shadow() {
+        // A point light of intensity I at height h gives a white diffuse floor directly beneath
+        // it a radiance of I / h^2 / pi.
+        let build = |blocker: bool| {
+            let mut objects = HittableList::new();
+            objects.add(Plane::new(
+                Vector3::zeros(),
+                Vector3::new(0.0, 1.0, 0.0),
+                MaterialId(0),
+            ));
+            if blocker {
+                objects.add(Sphere::new(
+                    Vector3::new(0.0, 1.0, 0.0),
+                    0.25,
+                    MaterialId(0),
+                ));
+            }
+            let materials: Vec<Box<dyn Material>> = vec![Box::new(Lambertian::new(Color::ones()))];
+            World::new(objects, materials).with_lights(vec![Box::new(PointLight::new(
+                Vector3::new(0.0, 2.0, 0.0),
+                Color::ones() * 4.0,
+            ))])
+        };
+        let tracer = PathTracer::new(1, 8);
+        let mut rng = Pcg32::new(0, 0);
+        let ray = Ray::new(Vector3::new(0.0, 1.0, 0.5), Vector3::new(0.0, -1.0, -0.5));
+
+        let lit = tracer.radiance(&build(false), &ray, &mut rng).x();
+        assert!(
+            (lit - 1.0 / std::f32::consts::PI).abs() < 1e-4,
+            "Got {}.",
+            lit
+        );
+        let shadowed = tracer.radiance(&build(true), &ray, &mut rng);
+        assert_eq!(Color::zeros(), shadowed);
+    }
+}
@@ -1 +1,31 @@
+pub mod aabb;
+pub mod accumulator;
+pub mod animation;
+pub mod aov;
+pub mod bvh;
+pub mod camera;
+pub mod csg;
+pub mod denoise;
+pub mod environment;
+pub mod float;
+pub mod framebuffer;
+pub mod golden;
+pub mod graph;
+pub mod hittable;
+pub mod integrator;
+pub mod light;
+pub mod material;
+pub mod matrix;
+pub mod obj;
+pub mod quaternion;
+pub mod ray;
+pub mod renderer;
+pub mod rng;
+pub mod sampling;
+pub mod scene;
+pub mod shapes;
+pub mod texture;
+pub mod transform;
 pub mod vector;
+pub mod volume;
+pub mod world;
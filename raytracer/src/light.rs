@@ -0,0 +1,303 @@
+use std::sync::Arc;
+
+use crate::hittable::Hittable;
+use crate::ray::Ray;
+use crate::rng::Pcg32;
+use crate::texture::Texture;
+use crate::vector::{Color, Vector3};
+
+/// Light arriving at a point from one sampled direction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LightSample {
+    /// Unit direction from the shaded point toward the light.
+    pub direction: Vector3,
+    /// Distance to the light along `direction`, or infinity for a light at infinity. A shadow ray
+    /// must reach this far unobstructed for the light to be visible.
+    pub distance: f32,
+    /// Incident radiance for area lights, or incident irradiance perpendicular to `direction`
+    /// for delta lights.
+    pub radiance: Color,
+    /// Density, in solid angle, with which `direction` was sampled. Delta lights, which can
+    /// only be reached by sampling them, report a probability of one.
+    pub pdf: f32,
+    /// Whether the light is a point or direction that scattered rays can never hit.
+    pub delta: bool,
+}
+
+/// A source of direct illumination that can be sampled from a shaded point.
+pub trait Light: Send + Sync {
+    /// Sample the light as seen from `point`, or return `None` if it contributes nothing there.
+    fn sample(&self, point: Vector3, rng: &mut Pcg32) -> Option<LightSample>;
+
+    /// Return the density, in solid angle, with which `sample` picks `direction` from `point`.
+    /// Delta lights return zero, since no other strategy can find them.
+    fn pdf(&self, _point: Vector3, _direction: Vector3) -> f32 {
+        0.0
+    }
+}
+
+/// A light at a single point, radiating equally in every direction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointLight {
+    pub position: Vector3,
+    /// Radiant intensity, in power per unit solid angle.
+    pub intensity: Color,
+}
+
+impl PointLight {
+    /// Create a new `PointLight` at `position`.
+    pub fn new(position: Vector3, intensity: Color) -> Self {
+        PointLight {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, point: Vector3, _rng: &mut Pcg32) -> Option<LightSample> {
+        let offset = self.position - point;
+        let distance_squared = offset.squared_norm();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        Some(LightSample {
+            direction: offset / distance,
+            distance,
+            radiance: self.intensity / distance_squared,
+            pdf: 1.0,
+            delta: true,
+        })
+    }
+}
+
+/// A light infinitely far away, such as the sun, whose rays all travel in `direction`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DirectionalLight {
+    /// Unit direction in which the light travels.
+    pub direction: Vector3,
+    /// Irradiance on a surface facing the light.
+    pub irradiance: Color,
+}
+
+impl DirectionalLight {
+    /// Create a new `DirectionalLight` shining along `direction`, which is normalized.
+    pub fn new(direction: Vector3, irradiance: Color) -> Self {
+        DirectionalLight {
+            direction: direction.normalized(),
+            irradiance,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _point: Vector3, _rng: &mut Pcg32) -> Option<LightSample> {
+        Some(LightSample {
+            direction: -self.direction,
+            distance: f32::INFINITY,
+            radiance: self.irradiance,
+            pdf: 1.0,
+            delta: true,
+        })
+    }
+}
+
+/// A point light restricted to a cone, fading smoothly from full intensity at `cos_falloff`
+/// to nothing at `cos_total`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpotLight {
+    pub position: Vector3,
+    /// Unit direction of the cone's axis.
+    pub direction: Vector3,
+    /// Radiant intensity along the axis.
+    pub intensity: Color,
+    /// Cosine of the cone's half-angle.
+    pub cos_total: f32,
+    /// Cosine of the angle at which the intensity starts to fall off.
+    pub cos_falloff: f32,
+}
+
+impl SpotLight {
+    /// Create a new `SpotLight` at `position` pointing along `direction`, lighting a cone of
+    /// half-angle `angle` radians. Intensity falls off over the outer `falloff` fraction of the
+    /// cone, which is clamped to `[0, 1]`.
+    pub fn new(
+        position: Vector3,
+        direction: Vector3,
+        intensity: Color,
+        angle: f32,
+        falloff: f32,
+    ) -> Self {
+        let falloff = falloff.clamp(0.0, 1.0);
+        SpotLight {
+            position,
+            direction: direction.normalized(),
+            intensity,
+            cos_total: angle.cos(),
+            cos_falloff: (angle * (1.0 - falloff)).cos(),
+        }
+    }
+
+    /// Return the fraction of the axial intensity emitted along the unit direction `w`.
+    fn falloff(&self, w: Vector3) -> f32 {
+        let cos = self.direction.dot(w);
+        if cos <= self.cos_total {
+            0.0
+        } else if cos >= self.cos_falloff {
+            1.0
+        } else {
+            // Smoothstep between the two cones.
+            let t = (cos - self.cos_total) / (self.cos_falloff - self.cos_total);
+            t * t * (3.0 - 2.0 * t)
+        }
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, point: Vector3, _rng: &mut Pcg32) -> Option<LightSample> {
+        let offset = self.position - point;
+        let distance_squared = offset.squared_norm();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        let direction = offset / distance;
+        let falloff = self.falloff(-direction);
+        if falloff == 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: self.intensity * (falloff / distance_squared),
+            pdf: 1.0,
+            delta: true,
+        })
+    }
+}
+
+/// A light emitted from the front face of a shape, which casts soft shadows. The shape must
+/// support direction sampling, and should also be placed in the scene with a matching emissive
+/// material so that it is visible.
+pub struct AreaLight {
+    shape: Box<dyn Hittable>,
+    emit: Arc<dyn Texture>,
+}
+
+impl AreaLight {
+    /// Create a new `AreaLight` emitting radiance `emit` from the front face of `shape`.
+    pub fn new(shape: Box<dyn Hittable>, emit: Arc<dyn Texture>) -> Self {
+        AreaLight { shape, emit }
+    }
+}
+
+impl Light for AreaLight {
+    fn sample(&self, point: Vector3, rng: &mut Pcg32) -> Option<LightSample> {
+        let direction = self.shape.sample_direction(point, rng)?.normalized();
+        let hit = self
+            .shape
+            .hit(&Ray::new(point, direction), 0.0, f32::INFINITY)?;
+        if !hit.front_face {
+            return None;
+        }
+        let pdf = self.shape.pdf_direction(point, direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance: hit.t,
+            radiance: self.emit.value(hit.u, hit.v, hit.point),
+            pdf,
+            delta: false,
+        })
+    }
+
+    fn pdf(&self, point: Vector3, direction: Vector3) -> f32 {
+        self.shape.pdf_direction(point, direction)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hittable::MaterialId;
+    use crate::shapes::{Rect, Sphere};
+    use crate::texture::SolidColor;
+    use std::f32::consts::FRAC_PI_4;
+
+    #[test]
+    fn test_delta_lights() {
+        let mut rng = Pcg32::new(0, 0);
+        let point = PointLight::new(Vector3::new(0.0, 2.0, 0.0), Color::ones() * 8.0);
+        let sample = point.sample(Vector3::zeros(), &mut rng).unwrap();
+        assert_eq!(Vector3::new(0.0, 1.0, 0.0), sample.direction);
+        assert_eq!(2.0, sample.distance);
+        assert_eq!(
+            Color::ones() * 2.0,
+            sample.radiance,
+            "Expected 1/r^2 falloff."
+        );
+        assert!(sample.delta);
+        assert_eq!(0.0, point.pdf(Vector3::zeros(), sample.direction));
+
+        let sun = DirectionalLight::new(Vector3::new(0.0, -3.0, 0.0), Color::ones());
+        let sample = sun.sample(Vector3::new(5.0, 0.0, 1.0), &mut rng).unwrap();
+        assert_eq!(Vector3::new(0.0, 1.0, 0.0), sample.direction);
+        assert_eq!(f32::INFINITY, sample.distance);
+
+        let spot = SpotLight::new(
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, -1.0, 0.0),
+            Color::ones(),
+            FRAC_PI_4,
+            0.5,
+        );
+        let at = |x| spot.sample(Vector3::new(x, 0.0, 0.0), &mut Pcg32::new(0, 0));
+        assert_eq!(
+            Color::ones(),
+            at(0.0).unwrap().radiance,
+            "Inside the inner cone."
+        );
+        assert!(at(1.1).is_none(), "Outside the cone.");
+        let edge = at(0.6).unwrap().radiance.x() * (1.0 + 0.6 * 0.6);
+        assert!(
+            edge > 0.0 && edge < 1.0,
+            "Expected partial falloff, got {}.",
+            edge
+        );
+    }
+
+    #[test]
+    fn test_area_lights() {
+        let emit: Arc<dyn Texture> = Arc::new(SolidColor::new(Color::ones() * 3.0));
+        let rect = Rect::new(
+            Vector3::new(-0.5, 2.0, -0.5),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            MaterialId(0),
+        );
+        let light = AreaLight::new(Box::new(rect), emit.clone());
+        let mut rng = Pcg32::new(0, 0);
+        for _ in 0..100 {
+            let sample = light.sample(Vector3::zeros(), &mut rng).unwrap();
+            assert!(!sample.delta && sample.pdf > 0.0);
+            assert_eq!(Color::ones() * 3.0, sample.radiance);
+            assert!((sample.pdf - light.pdf(Vector3::zeros(), sample.direction)).abs() < 1e-3);
+            assert!(sample.distance >= 2.0 && sample.distance < 2.25);
+        }
+        // The back of a one-sided light is dark.
+        assert!(light
+            .sample(Vector3::new(0.0, 4.0, 0.0), &mut rng)
+            .is_none());
+
+        let sphere = Sphere::new(Vector3::new(0.0, 0.0, -3.0), 1.0, MaterialId(0));
+        let light = AreaLight::new(Box::new(sphere), emit);
+        let sample = light.sample(Vector3::zeros(), &mut rng).unwrap();
+        assert!(sample.distance >= 2.0 && sample.distance < 3.0);
+        assert!(light
+            .sample(Vector3::new(0.0, 0.0, -3.0), &mut rng)
+            .is_none());
+    }
+}
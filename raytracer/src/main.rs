@@ -0,0 +1,585 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use raytracer::accumulator::Accumulator;
+use raytracer::aov::Aov;
+use raytracer::camera::Camera;
+use raytracer::denoise::{Denoiser, Guides};
+use raytracer::framebuffer::{DisplaySettings, Framebuffer};
+use raytracer::renderer::Renderer;
+use raytracer::scene::{RenderSettings, Scene};
+use raytracer::vector::Color;
+use raytracer::world::World;
+
+const USAGE: &str = "\
+Usage: raytracer <scene.json> [options]
+
+Options:
+  -o, --output <path>     Image to write, .png or .ppm, or linear .hdr or .pfm
+                          [default: render.png]
+  -w, --width <pixels>    Override the scene's image width
+  -h, --height <pixels>   Override the scene's image height
+  -s, --spp <samples>     Override the scene's samples per pixel
+  -t, --threads <count>   Number of render threads [default: one per CPU]
+      --seed <seed>       Override the scene's random seed
+      --adaptive <error>  Stop sampling pixels once their relative standard error is
+                          below this
+      --denoise           Also write a denoised image, and the albedo, normal and
+                          variance images that guide it, beside the output
+      --aov <names>       Also write these comma-separated debug images beside the
+                          output: normal, depth, albedo, object_id, cost, hits or all
+      --pass-spp <n>      Render progressively, adding n samples per pixel per pass
+      --preview <secs>    Rewrite the output image at most this often while rendering
+                          progressively
+      --checkpoint <path> Save the accumulated samples here after every pass
+      --resume            Continue from the checkpoint instead of starting over
+      --frames <a>[-<b>]  Render only frames a to b of an animated scene [default: all]
+  -q, --quiet             Do not print progress or statistics
+      --help              Print this message";
+
+/// Command-line options.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Options {
+    scene: PathBuf,
+    output: PathBuf,
+    width: Option<u32>,
+    height: Option<u32>,
+    samples_per_pixel: Option<u32>,
+    threads: Option<usize>,
+    seed: Option<u64>,
+    adaptive_threshold: Option<f32>,
+    denoise: bool,
+    aovs: Vec<Aov>,
+    pass_samples: Option<u32>,
+    preview: Option<f32>,
+    checkpoint: Option<PathBuf>,
+    resume: bool,
+    frames: Option<(u32, u32)>,
+    quiet: bool,
+}
+
+impl Options {
+    /// Parse options from the arguments following the program name. Returns `Ok(None)` if help
+    /// was requested.
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Options>, String> {
+        let mut options = Options {
+            output: PathBuf::from("render.png"),
+            ..Options::default()
+        };
+        let mut scene = None;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| format!("{} requires a value", name))
+            };
+            match arg.as_str() {
+                "--help" => return Ok(None),
+                "-o" | "--output" => options.output = PathBuf::from(value(&arg)?),
+                "-w" | "--width" => options.width = Some(parse_number(&arg, &value(&arg)?)?),
+                "-h" | "--height" => options.height = Some(parse_number(&arg, &value(&arg)?)?),
+                "-s" | "--spp" => {
+                    options.samples_per_pixel = Some(parse_number(&arg, &value(&arg)?)?)
+                }
+                "-t" | "--threads" => {
+                    let threads = parse_number(&arg, &value(&arg)?)?;
+                    if threads == 0 {
+                        return Err(format!("{} must be positive", arg));
+                    }
+                    options.threads = Some(threads);
+                }
+                "--seed" => options.seed = Some(parse_number(&arg, &value(&arg)?)?),
+                "--adaptive" => {
+                    let threshold: f32 = parse_number(&arg, &value(&arg)?)?;
+                    if threshold.is_nan() || threshold < 0.0 {
+                        return Err(format!("{} must be non-negative", arg));
+                    }
+                    options.adaptive_threshold = Some(threshold);
+                }
+                "--denoise" => options.denoise = true,
+                "--aov" => {
+                    for name in value(&arg)?.split(',') {
+                        if name == "all" {
+                            options.aovs.extend(Aov::ALL);
+                        } else {
+                            options.aovs.push(name.parse()?);
+                        }
+                    }
+                    options.aovs.sort();
+                    options.aovs.dedup();
+                }
+                "--pass-spp" => {
+                    let samples = parse_number(&arg, &value(&arg)?)?;
+                    if samples == 0 {
+                        return Err(format!("{} must be positive", arg));
+                    }
+                    options.pass_samples = Some(samples);
+                }
+                "--preview" => {
+                    let seconds: f32 = parse_number(&arg, &value(&arg)?)?;
+                    if !(seconds >= 0.0 && seconds.is_finite()) {
+                        return Err(format!("{} must be a non-negative number of seconds", arg));
+                    }
+                    options.preview = Some(seconds);
+                }
+                "--checkpoint" => options.checkpoint = Some(PathBuf::from(value(&arg)?)),
+                "--resume" => options.resume = true,
+                "--frames" => {
+                    let range = value(&arg)?;
+                    let (first, last) = range.split_once('-').unwrap_or((&range, &range));
+                    let (first, last) = (parse_number(&arg, first)?, parse_number(&arg, last)?);
+                    if last < first {
+                        return Err(format!("{} must not end before it starts", arg));
+                    }
+                    options.frames = Some((first, last));
+                }
+                "-q" | "--quiet" => options.quiet = true,
+                _ if arg.starts_with('-') && arg.len() > 1 => {
+                    return Err(format!("unknown option {}", arg));
+                }
+                _ if scene.is_none() => scene = Some(PathBuf::from(arg)),
+                _ => return Err(format!("unexpected argument {}", arg)),
+            }
+        }
+        options.scene = scene.ok_or("no scene file given")?;
+        if options.resume && options.checkpoint.is_none() {
+            return Err("--resume requires --checkpoint".to_string());
+        }
+        Ok(Some(options))
+    }
+
+    /// Return whether to render in several passes rather than all at once.
+    fn progressive(&self) -> bool {
+        self.pass_samples.is_some() || self.preview.is_some() || self.checkpoint.is_some()
+    }
+
+    /// Return these options with the output and checkpoint paths numbered for `frame` of an
+    /// animation, so `render.png` becomes `render.0007.png`.
+    fn for_frame(&self, frame: u32) -> Options {
+        let number = format!("{:04}", frame);
+        Options {
+            output: sibling(&self.output, &number),
+            checkpoint: self.checkpoint.as_ref().map(|path| sibling(path, &number)),
+            ..self.clone()
+        }
+    }
+}
+
+/// Samples per pixel in each pass of a progressive render, unless `--pass-spp` says otherwise.
+const DEFAULT_PASS_SAMPLES: u32 = 4;
+
+fn parse_number<T: FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value \"{}\" for {}", value, name))
+}
+
+/// Render in passes until the scene's samples per pixel are reached, checkpointing and writing
+/// previews along the way, and return the accumulated samples.
+fn render_progressive(
+    options: &Options,
+    renderer: &Renderer,
+    world: &World,
+    camera: &Camera,
+) -> Result<Accumulator, String> {
+    let settings = &renderer.settings;
+    let mut accumulator = match &options.checkpoint {
+        Some(path) if options.resume => {
+            let accumulator =
+                Accumulator::load(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+            let size = (accumulator.width(), accumulator.height());
+            if size != (settings.width as usize, settings.height as usize)
+                || accumulator.seed() != settings.seed
+            {
+                return Err(format!(
+                    "{}: checkpoint is {}x{} with seed {}, but the render is {}x{} with seed {}",
+                    path.display(),
+                    size.0,
+                    size.1,
+                    accumulator.seed(),
+                    settings.width,
+                    settings.height,
+                    settings.seed
+                ));
+            }
+            if !options.quiet {
+                eprintln!(
+                    "Resuming from {} after {} passes ({} samples per pixel)",
+                    path.display(),
+                    accumulator.passes(),
+                    accumulator.samples()
+                );
+            }
+            accumulator
+        }
+        _ => renderer.accumulator(),
+    };
+
+    let pass_samples = options.pass_samples.unwrap_or(DEFAULT_PASS_SAMPLES);
+    let preview_interval = options.preview.map(Duration::from_secs_f32);
+    let mut last_preview = Instant::now();
+    while accumulator.samples() < settings.samples_per_pixel {
+        let samples = pass_samples.min(settings.samples_per_pixel - accumulator.samples());
+        renderer.render_pass(world, camera, &mut accumulator, samples);
+        if !options.quiet {
+            eprint!(
+                "\rPass {}: {}/{} samples per pixel",
+                accumulator.passes(),
+                accumulator.samples(),
+                settings.samples_per_pixel
+            );
+        }
+
+        if let Some(path) = &options.checkpoint {
+            accumulator
+                .save(path)
+                .map_err(|err| format!("{}: {}", path.display(), err))?;
+        }
+        if let Some(interval) = preview_interval {
+            if last_preview.elapsed() >= interval {
+                save_image(&accumulator.image(), &options.output)?;
+                last_preview = Instant::now();
+            }
+        }
+    }
+    Ok(accumulator)
+}
+
+fn save_image(image: &Framebuffer, path: &Path) -> Result<(), String> {
+    save_image_with(image, path, &DisplaySettings::default())
+}
+
+fn save_image_with(
+    image: &Framebuffer,
+    path: &Path,
+    settings: &DisplaySettings,
+) -> Result<(), String> {
+    image
+        .save(path, settings)
+        .map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+/// Return `path` with `suffix` inserted before its extension, so `render.png` becomes
+/// `render.<suffix>.png`.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{}.{}.{}", stem, suffix, ext.to_string_lossy()),
+        None => format!("{}.{}", stem, suffix),
+    };
+    path.with_file_name(name)
+}
+
+/// Render `aovs` and write a visualization of each beside `options.output`. Returns the raw
+/// images and the paths written.
+fn write_aovs(
+    options: &Options,
+    renderer: &Renderer,
+    world: &World,
+    camera: &Camera,
+    aovs: &[Aov],
+) -> Result<(BTreeMap<Aov, Framebuffer>, Vec<PathBuf>), String> {
+    // AOVs are data rather than pictures, so they are written without gamma.
+    let linear = DisplaySettings {
+        gamma: 1.0,
+        ..DisplaySettings::default()
+    };
+    let images = renderer.render_aovs(world, camera, aovs);
+    let mut paths = Vec::with_capacity(aovs.len());
+    for (aov, image) in aovs.iter().zip(&images) {
+        let path = sibling(&options.output, aov.name());
+        save_image_with(&aov.visualize(image), &path, &linear)?;
+        paths.push(path);
+    }
+    Ok((aovs.iter().copied().zip(images).collect(), paths))
+}
+
+/// Denoise the render in `accumulator` with `guides` on `renderer`'s threads, writing the result
+/// and its variance beside `options.output`. Returns the paths written.
+fn write_denoised(
+    options: &Options,
+    renderer: &Renderer,
+    accumulator: &Accumulator,
+    guides: &Guides,
+) -> Result<Vec<PathBuf>, String> {
+    let variance = accumulator.variance();
+    let denoised =
+        renderer.install(|| Denoiser::default().denoise(&accumulator.image(), &variance, guides));
+    let variance = Framebuffer::from_fn(denoised.width(), denoised.height(), |x, y| {
+        Color::ones() * variance[y * denoised.width() + x]
+    });
+
+    let paths = vec![
+        sibling(&options.output, "denoised"),
+        sibling(&options.output, "variance"),
+    ];
+    save_image(&denoised, &paths[0])?;
+    let linear = DisplaySettings {
+        gamma: 1.0,
+        ..DisplaySettings::default()
+    };
+    save_image_with(&variance, &paths[1], &linear)?;
+    Ok(paths)
+}
+
+/// Load the scene and render each of its frames, printing progress unless `options.quiet`.
+fn run(options: &Options) -> Result<(), String> {
+    let start = Instant::now();
+    let scene_name = options.scene.display();
+    let mut scene =
+        Scene::load(&options.scene).map_err(|err| format!("{}: {}", scene_name, err))?;
+    let render = &mut scene.render;
+    render.width = options.width.unwrap_or(render.width);
+    render.height = options.height.unwrap_or(render.height);
+    render.samples_per_pixel = options
+        .samples_per_pixel
+        .unwrap_or(render.samples_per_pixel);
+    render.seed = options.seed.unwrap_or(render.seed);
+    render.adaptive_threshold = options
+        .adaptive_threshold
+        .unwrap_or(render.adaptive_threshold);
+    render.aovs.extend(&options.aovs);
+    render.aovs.sort();
+    render.aovs.dedup();
+    scene
+        .validate()
+        .map_err(|err| format!("{}: {}", scene_name, err))?;
+
+    let frames = scene.frames();
+    let (first, last) = options.frames.unwrap_or((0, frames - 1));
+    if last >= frames {
+        return Err(format!(
+            "{}: --frames {}-{} is out of range for {} frames",
+            scene_name, first, last, frames
+        ));
+    }
+
+    let world = scene
+        .build_world()
+        .map_err(|err| format!("{}: {}", scene_name, err))?;
+    let settings = &scene.render;
+    if !options.quiet {
+        eprintln!("Scene load: {:>10.2?}", start.elapsed());
+        eprintln!(
+            "Rendering {} at {}x{}, {} samples per pixel, seed {}",
+            scene_name, settings.width, settings.height, settings.samples_per_pixel, settings.seed
+        );
+    }
+
+    if scene.animation.is_none() {
+        return render_frame(options, settings, &world, &scene.camera());
+    }
+    for frame in first..=last {
+        if !options.quiet {
+            eprintln!("Frame {} of {}", frame, frames);
+        }
+        // Each frame gets its own seed so that the noise does not stay put while the image moves.
+        let settings = RenderSettings {
+            seed: settings.seed.wrapping_add(frame as u64),
+            ..settings.clone()
+        };
+        render_frame(
+            &options.for_frame(frame),
+            &settings,
+            &world,
+            &scene.frame_camera(frame),
+        )?;
+    }
+    Ok(())
+}
+
+/// Render one image of `world` through `camera` and save it, with any AOVs and denoised
+/// version, printing statistics unless `options.quiet`.
+fn render_frame(
+    options: &Options,
+    settings: &RenderSettings,
+    world: &World,
+    camera: &Camera,
+) -> Result<(), String> {
+    let mut renderer = Renderer::new(settings.clone());
+    if let Some(threads) = options.threads {
+        renderer = renderer
+            .with_threads(threads)
+            .map_err(|err| format!("Error creating render threads: {}", err))?;
+    }
+
+    let start = Instant::now();
+    let accumulator = if options.progressive() {
+        render_progressive(options, &renderer, world, camera)?
+    } else {
+        let mut accumulator = renderer.accumulator();
+        let last_percent = AtomicUsize::new(0);
+        let samples = settings.samples_per_pixel;
+        renderer.render_pass_with_progress(world, camera, &mut accumulator, samples, |progress| {
+            if options.quiet {
+                return;
+            }
+            // Only print when the percentage changes, from whichever thread gets there first.
+            let percent = (progress.fraction() * 100.0) as usize;
+            if last_percent.fetch_max(percent, Ordering::Relaxed) < percent {
+                eprint!("\r{:3}%", percent);
+            }
+        });
+        accumulator
+    };
+    let render_time = start.elapsed();
+
+    save_image(&accumulator.image(), &options.output)?;
+    let mut written = vec![options.output.clone()];
+
+    // The denoiser is guided by the albedo and normal AOVs, which are written too.
+    let start = Instant::now();
+    let mut aovs = settings.aovs.clone();
+    if options.denoise {
+        aovs.extend([Aov::Albedo, Aov::Normal]);
+        aovs.sort();
+        aovs.dedup();
+    }
+    let (mut images, paths) = write_aovs(options, &renderer, world, camera, &aovs)?;
+    written.extend(paths);
+    let aov_time = start.elapsed();
+
+    let start = Instant::now();
+    if options.denoise {
+        let guides = Guides {
+            albedo: images.remove(&Aov::Albedo).unwrap(),
+            normal: images.remove(&Aov::Normal).unwrap(),
+        };
+        written.extend(write_denoised(options, &renderer, &accumulator, &guides)?);
+    }
+    let denoise_time = start.elapsed();
+
+    if !options.quiet {
+        let samples: f64 = accumulator.pixels().iter().map(|p| p.count as f64).sum();
+        let pixels = (settings.width * settings.height) as f64;
+        eprintln!();
+        eprintln!(
+            "Render:     {:>10.2?} ({:.2} Msamples/s, {:.1} samples per pixel on average)",
+            render_time,
+            samples / render_time.as_secs_f64() / 1e6,
+            samples / pixels
+        );
+        if !aovs.is_empty() {
+            eprintln!("AOVs:       {:>10.2?}", aov_time);
+        }
+        if options.denoise {
+            eprintln!("Denoise:    {:>10.2?}", denoise_time);
+        }
+        for path in written {
+            eprintln!("Wrote {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match run(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Option<Options>, String> {
+        Options::parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn test_parse() {
+        let options = parse("scene.json -o out.ppm -w 64 --height 48 -s 4 -t 2 --seed 7 -q")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            Options {
+                scene: PathBuf::from("scene.json"),
+                output: PathBuf::from("out.ppm"),
+                width: Some(64),
+                height: Some(48),
+                samples_per_pixel: Some(4),
+                threads: Some(2),
+                seed: Some(7),
+                quiet: true,
+                ..Options::default()
+            },
+            options
+        );
+        assert!(!options.progressive());
+
+        let options = parse("scene.json --checkpoint run.acc --resume --preview 30")
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some(PathBuf::from("run.acc")), options.checkpoint);
+        assert_eq!(Some(30.0), options.preview);
+        assert!(options.resume && options.progressive());
+
+        let options = parse("scene.json --adaptive 0.02 --denoise")
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some(0.02), options.adaptive_threshold);
+        assert!(options.denoise && !options.progressive());
+        let options = parse("scene.json --aov depth,cost --aov normal,depth")
+            .unwrap()
+            .unwrap();
+        assert_eq!(vec![Aov::Normal, Aov::Depth, Aov::Cost], options.aovs);
+        let options = parse("scene.json --frames 3 --checkpoint run.acc")
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some((3, 3)), options.frames);
+        let frame = options.for_frame(3);
+        assert_eq!(PathBuf::from("render.0003.png"), frame.output);
+        assert_eq!(Some(PathBuf::from("run.0003.acc")), frame.checkpoint);
+        let options = parse("scene.json --frames 2-10").unwrap().unwrap();
+        assert_eq!(Some((2, 10)), options.frames);
+        let options = parse("scene.json --aov all").unwrap().unwrap();
+        assert_eq!(Aov::ALL.to_vec(), options.aovs);
+        assert_eq!(
+            PathBuf::from("out/render.denoised.png"),
+            sibling(Path::new("out/render.png"), "denoised")
+        );
+
+        let defaults = parse("scene.json").unwrap().unwrap();
+        assert_eq!(PathBuf::from("render.png"), defaults.output);
+        assert_eq!(None, defaults.threads);
+        assert_eq!(None, parse("scene.json --help").unwrap());
+
+        assert!(parse("").unwrap_err().contains("no scene"));
+        assert!(parse("a.json b.json").unwrap_err().contains("b.json"));
+        assert!(parse("a.json --width").unwrap_err().contains("requires"));
+        assert!(parse("a.json --width big").unwrap_err().contains("\"big\""));
+        assert!(parse("a.json -t 0").unwrap_err().contains("positive"));
+        assert!(parse("a.json --fast").unwrap_err().contains("--fast"));
+        assert!(parse("a.json --resume")
+            .unwrap_err()
+            .contains("--checkpoint"));
+        assert!(parse("a.json --aov depth,fog").unwrap_err().contains("fog"));
+        assert!(parse("a.json --pass-spp 0")
+            .unwrap_err()
+            .contains("positive"));
+        assert!(parse("a.json --frames 5-2")
+            .unwrap_err()
+            .contains("--frames"));
+        assert!(parse("a.json --frames 1-x").unwrap_err().contains("\"x\""));
+    }
+}
@@ -0,0 +1,388 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::hittable::HitRecord;
+use crate::ray::Ray;
+use crate::rng::Pcg32;
+use crate::sampling::{cosine_hemisphere, uniform_ball, Onb};
+use crate::texture::{SolidColor, Texture};
+use crate::vector::{Color, Vector3};
+use crate::volume::PhaseFunction;
+
+/// The result of a ray scattering off a surface.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scatter {
+    /// Fraction of the light arriving along `ray` that is carried back along the incoming ray.
+    pub attenuation: Color,
+    /// The scattered ray.
+    pub ray: Ray,
+    /// Density, in solid angle, with which `ray.direction` was sampled, or `None` for a specular
+    /// scatter that picks a single direction.
+    pub pdf: Option<f32>,
+}
+
+/// Describes how light interacts with a surface.
+pub trait Material: Send + Sync {
+    /// Scatter `ray` at `hit`, returning `None` if the ray is absorbed.
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut Pcg32) -> Option<Scatter>;
+
+    /// Return the light emitted by the surface at `hit`.
+    fn emitted(&self, _hit: &HitRecord) -> Color {
+        Color::zeros()
+    }
+
+    /// Return the fraction of light arriving at `hit` from `direction` that is scattered back
+    /// along `ray`: the BSDF times the cosine of the angle to the normal. Specular materials,
+    /// which only scatter into directions they sample themselves, return zero.
+    fn eval(&self, _ray: &Ray, _hit: &HitRecord, _direction: Vector3) -> Color {
+        Color::zeros()
+    }
+
+    /// Return the density, in solid angle, with which `scatter` picks `direction` at `hit`.
+    fn pdf(&self, _ray: &Ray, _hit: &HitRecord, _direction: Vector3) -> f32 {
+        0.0
+    }
+
+    /// Return the surface color at `hit`, free of lighting, as a guide for denoising. Materials
+    /// without a color of their own, such as glass, return white.
+    fn albedo(&self, _hit: &HitRecord) -> Color {
+        Color::ones()
+    }
+}
+
+/// An ideal diffuse reflector.
+#[derive(Clone)]
+pub struct Lambertian {
+    pub albedo: Arc<dyn Texture>,
+}
+
+impl Lambertian {
+    /// Create a new `Lambertian` material reflecting `albedo` of incident light.
+    pub fn new(albedo: Color) -> Self {
+        Lambertian::textured(Arc::new(SolidColor::new(albedo)))
+    }
+
+    /// Create a new `Lambertian` material whose albedo varies with `texture`.
+    pub fn textured(texture: Arc<dyn Texture>) -> Self {
+        Lambertian { albedo: texture }
+    }
+}
+
+impl Material for Lambertian {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut Pcg32) -> Option<Scatter> {
+        let local = cosine_hemisphere(rng.next_2d());
+        let direction = Onb::from_w(hit.normal).to_world(local);
+        // The cosine and 1/pi of the BSDF cancel with the sampling density.
+        Some(Scatter {
+            attenuation: self.albedo.value(hit.u, hit.v, hit.point),
+            ray: Ray::with_time(hit.point, direction, ray.time),
+            pdf: Some(local.z() / PI),
+        })
+    }
+
+    fn eval(&self, _ray: &Ray, hit: &HitRecord, direction: Vector3) -> Color {
+        let cosine = hit.normal.dot(direction.normalized()).max(0.0);
+        self.albedo.value(hit.u, hit.v, hit.point) * (cosine / PI)
+    }
+
+    fn pdf(&self, _ray: &Ray, hit: &HitRecord, direction: Vector3) -> f32 {
+        hit.normal.dot(direction.normalized()).max(0.0) / PI
+    }
+
+    fn albedo(&self, hit: &HitRecord) -> Color {
+        self.albedo.value(hit.u, hit.v, hit.point)
+    }
+}
+
+/// A specular reflector whose reflections are blurred by `fuzz`.
+#[derive(Clone)]
+pub struct Metal {
+    pub albedo: Arc<dyn Texture>,
+    pub fuzz: f32,
+}
+
+impl Metal {
+    /// Create a new `Metal` material. `fuzz` is clamped to `[0, 1]`.
+    pub fn new(albedo: Color, fuzz: f32) -> Self {
+        Metal::textured(Arc::new(SolidColor::new(albedo)), fuzz)
+    }
+
+    /// Create a new `Metal` material whose albedo varies with `texture`.
+    pub fn textured(texture: Arc<dyn Texture>, fuzz: f32) -> Self {
+        Metal {
+            albedo: texture,
+            fuzz: fuzz.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl Material for Metal {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut Pcg32) -> Option<Scatter> {
+        let reflected = ray.direction.normalized().reflect(hit.normal);
+        let direction =
+            reflected + self.fuzz * uniform_ball([rng.next_f32(), rng.next_f32(), rng.next_f32()]);
+        if direction.dot(hit.normal) <= 0.0 {
+            // Fuzzed below the surface; absorb.
+            return None;
+        }
+        Some(Scatter {
+            attenuation: self.albedo.value(hit.u, hit.v, hit.point),
+            ray: Ray::with_time(hit.point, direction, ray.time),
+            pdf: None,
+        })
+    }
+
+    fn albedo(&self, hit: &HitRecord) -> Color {
+        self.albedo.value(hit.u, hit.v, hit.point)
+    }
+}
+
+/// A clear refractive material such as glass or water.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dielectric {
+    /// Index of refraction relative to the surrounding medium.
+    pub ior: f32,
+}
+
+impl Dielectric {
+    /// Create a new `Dielectric` material with index of refraction `ior`.
+    pub fn new(ior: f32) -> Self {
+        Dielectric { ior }
+    }
+}
+
+impl Material for Dielectric {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut Pcg32) -> Option<Scatter> {
+        let eta = if hit.front_face {
+            1.0 / self.ior
+        } else {
+            self.ior
+        };
+
+        let unit = ray.direction.normalized();
+        let cos_theta = (-unit).dot(hit.normal).min(1.0);
+        let direction = match unit.refract(hit.normal, eta) {
+            Some(refracted) if schlick(cos_theta, eta) <= rng.next_f32() => refracted,
+            // Total internal reflection, or a Fresnel reflection.
+            _ => unit.reflect(hit.normal),
+        };
+
+        Some(Scatter {
+            attenuation: Color::ones(),
+            ray: Ray::with_time(hit.point, direction, ray.time),
+            pdf: None,
+        })
+    }
+}
+
+/// A light-emitting surface that does not scatter.
+#[derive(Clone)]
+pub struct DiffuseLight {
+    pub emit: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+    /// Create a new `DiffuseLight` emitting `emit` radiance from its front face.
+    pub fn new(emit: Color) -> Self {
+        DiffuseLight::textured(Arc::new(SolidColor::new(emit)))
+    }
+
+    /// Create a new `DiffuseLight` whose emitted radiance varies with `texture`.
+    pub fn textured(texture: Arc<dyn Texture>) -> Self {
+        DiffuseLight { emit: texture }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _ray: &Ray, _hit: &HitRecord, _rng: &mut Pcg32) -> Option<Scatter> {
+        None
+    }
+
+    fn emitted(&self, hit: &HitRecord) -> Color {
+        if hit.front_face {
+            self.emit.value(hit.u, hit.v, hit.point)
+        } else {
+            Color::zeros()
+        }
+    }
+}
+
+/// The scattering of a participating medium such as fog or smoke, for use with a `Volume`.
+/// Light is scattered with probability `albedo`, into directions drawn from `phase`.
+#[derive(Clone)]
+pub struct Medium {
+    pub albedo: Arc<dyn Texture>,
+    pub phase: PhaseFunction,
+}
+
+impl Medium {
+    /// Create a new `Medium` scattering `albedo` of the light it stops according to `phase`.
+    pub fn new(albedo: Color, phase: PhaseFunction) -> Self {
+        Medium::textured(Arc::new(SolidColor::new(albedo)), phase)
+    }
+
+    /// Create a new `Medium` whose albedo varies through space with `texture`.
+    pub fn textured(texture: Arc<dyn Texture>, phase: PhaseFunction) -> Self {
+        Medium {
+            albedo: texture,
+            phase,
+        }
+    }
+}
+
+impl Material for Medium {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut Pcg32) -> Option<Scatter> {
+        let incoming = ray.direction.normalized();
+        let direction = self.phase.sample(incoming, rng.next_2d());
+        // The phase function is sampled exactly, so only the albedo remains.
+        Some(Scatter {
+            attenuation: self.albedo.value(hit.u, hit.v, hit.point),
+            ray: Ray::with_time(hit.point, direction, ray.time),
+            pdf: Some(self.phase.eval(incoming.dot(direction))),
+        })
+    }
+
+    fn eval(&self, ray: &Ray, hit: &HitRecord, direction: Vector3) -> Color {
+        self.albedo.value(hit.u, hit.v, hit.point) * self.pdf(ray, hit, direction)
+    }
+
+    fn pdf(&self, ray: &Ray, _hit: &HitRecord, direction: Vector3) -> f32 {
+        let cos_theta = ray.direction.normalized().dot(direction.normalized());
+        self.phase.eval(cos_theta)
+    }
+
+    fn albedo(&self, hit: &HitRecord) -> Color {
+        self.albedo.value(hit.u, hit.v, hit.point)
+    }
+}
+
+/// Schlick's approximation of the Fresnel reflectance at an interface with relative index of
+/// refraction `eta`, for light arriving at `cos_theta` to the normal.
+pub fn schlick(cos_theta: f32, eta: f32) -> f32 {
+    let r0 = ((1.0 - eta) / (1.0 + eta)).powi(2);
+    r0 + (1.0 - r0) * (1.0 - cos_theta).powi(5)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::framebuffer::Framebuffer;
+    use crate::hittable::MaterialId;
+    use crate::texture::ImageTexture;
+
+    fn hit_from_above() -> (Ray, HitRecord) {
+        let ray = Ray::new(Vector3::new(-1.0, 1.0, 0.0), Vector3::new(1.0, -1.0, 0.0));
+        let hit = HitRecord::new(&ray, 1.0, Vector3::new(0.0, 1.0, 0.0), MaterialId(0));
+        (ray, hit)
+    }
+
+    #[test]
+    fn test_schlick() {
+        assert!((schlick(1.0, 1.0 / 1.5) - 0.04).abs() < 1e-6);
+        assert_eq!(1.0, schlick(0.0, 1.0 / 1.5));
+    }
+
+    #[test]
+    fn test_lambertian() {
+        let (ray, hit) = hit_from_above();
+        let albedo = Color::new(0.2, 0.4, 0.6);
+        let material = Lambertian::new(albedo);
+        let mut rng = Pcg32::new(0, 0);
+        for _ in 0..100 {
+            let scatter = material.scatter(&ray, &hit, &mut rng).unwrap();
+            assert_eq!(albedo, scatter.attenuation);
+            assert!(
+                scatter.ray.direction.dot(hit.normal) >= 0.0,
+                "Lambertian scattered below the surface: {}.",
+                scatter.ray.direction
+            );
+        }
+
+        // Textured albedo is looked up at the hit's texture coordinates.
+        let image = Framebuffer::from_fn(2, 1, |x, _| Color::new(x as f32, 0.5, 0.0));
+        let material = Lambertian::textured(Arc::new(ImageTexture::new(image)));
+        let scatter = material
+            .scatter(&ray, &hit.with_uv(0.75, 0.5), &mut rng)
+            .unwrap();
+        assert_eq!(Color::new(1.0, 0.5, 0.0), scatter.attenuation);
+        assert_eq!(
+            Color::new(1.0, 0.5, 0.0),
+            material.albedo(&hit.with_uv(0.75, 0.5))
+        );
+    }
+
+    #[test]
+    fn test_metal() {
+        let (ray, hit) = hit_from_above();
+        let material = Metal::new(Color::ones(), 0.0);
+        let mut rng = Pcg32::new(0, 0);
+        let scatter = material.scatter(&ray, &hit, &mut rng).unwrap();
+        let expected = Vector3::unit(1.0, 1.0, 0.0);
+        assert!(
+            (scatter.ray.direction - expected).norm() < 1e-6,
+            "Expected mirror reflection {}, got {}.",
+            expected,
+            scatter.ray.direction
+        );
+    }
+
+    #[test]
+    fn test_dielectric_normal_incidence() {
+        let ray = Ray::new(Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let hit = HitRecord::new(&ray, 1.0, Vector3::new(0.0, 1.0, 0.0), MaterialId(0));
+        let material = Dielectric::new(1.5);
+        let mut rng = Pcg32::new(0, 0);
+
+        // At normal incidence about 4% of rays reflect and the rest pass straight through.
+        let mut reflected = 0;
+        for _ in 0..10000 {
+            let scatter = material.scatter(&ray, &hit, &mut rng).unwrap();
+            assert_eq!(Color::ones(), scatter.attenuation);
+            if scatter.ray.direction.y() > 0.0 {
+                reflected += 1;
+            } else {
+                assert!((scatter.ray.direction - ray.direction).norm() < 1e-6);
+            }
+        }
+        assert!(
+            (300..500).contains(&reflected),
+            "Expected ~400 reflections, got {}.",
+            reflected
+        );
+    }
+
+    #[test]
+    fn test_diffuse_light() {
+        let (ray, hit) = hit_from_above();
+        let emit = Color::new(4.0, 4.0, 4.0);
+        let material = DiffuseLight::new(emit);
+        let mut rng = Pcg32::new(0, 0);
+        assert!(material.scatter(&ray, &hit, &mut rng).is_none());
+        assert_eq!(emit, material.emitted(&hit));
+
+        let back = HitRecord::new(&ray, 1.0, Vector3::new(0.0, -1.0, 0.0), MaterialId(0));
+        assert_eq!(Color::zeros(), material.emitted(&back));
+    }
+
+    #[test]
+    fn test_medium() {
+        let (ray, hit) = hit_from_above();
+        let albedo = Color::new(0.9, 0.8, 0.7);
+        let material = Medium::new(albedo, PhaseFunction::HenyeyGreenstein { g: 0.8 });
+        let mut rng = Pcg32::new(0, 0);
+        let mut forward = 0;
+        for _ in 0..1000 {
+            let scatter = material.scatter(&ray, &hit, &mut rng).unwrap();
+            assert_eq!(albedo, scatter.attenuation);
+            let direction = scatter.ray.direction;
+            let pdf = material.pdf(&ray, &hit, direction);
+            assert!((scatter.pdf.unwrap() - pdf).abs() <= 1e-3 * pdf);
+            assert_eq!(albedo * pdf, material.eval(&ray, &hit, direction));
+            if direction.dot(ray.direction) > 0.0 {
+                forward += 1;
+            }
+        }
+        // Unlike a surface, the medium scatters straight on through the hit point.
+        assert!(forward > 900, "Only {} of 1000 scattered forward.", forward);
+    }
+}
@@ -0,0 +1,271 @@
+use std::fmt;
+use std::ops::Mul;
+
+use crate::quaternion::Quaternion;
+use crate::vector::{binop_ref_impl, Vector3};
+
+/// A 4x4 matrix of `f32`, stored in row-major order, acting on column vectors in homogeneous
+/// coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Matrix4 {
+    m: [[f32; 4]; 4],
+}
+
+impl Matrix4 {
+    /// Create a new `Matrix4` from its rows.
+    pub fn new(rows: [[f32; 4]; 4]) -> Self {
+        Matrix4 { m: rows }
+    }
+
+    /// Create a new identity `Matrix4`.
+    pub fn identity() -> Self {
+        Matrix4::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Create a new `Matrix4` translating by `offset`.
+    pub fn translation(offset: Vector3) -> Self {
+        Matrix4::new([
+            [1.0, 0.0, 0.0, offset.x()],
+            [0.0, 1.0, 0.0, offset.y()],
+            [0.0, 0.0, 1.0, offset.z()],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Create a new `Matrix4` scaling each axis by the matching component of `factors`.
+    pub fn scaling(factors: Vector3) -> Self {
+        Matrix4::new([
+            [factors.x(), 0.0, 0.0, 0.0],
+            [0.0, factors.y(), 0.0, 0.0],
+            [0.0, 0.0, factors.z(), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Create a new `Matrix4` applying the rotation represented by the unit quaternion `q`.
+    pub fn rotation(q: Quaternion) -> Self {
+        let (w, x, y, z) = (q.w(), q.x(), q.y(), q.z());
+        Matrix4::new([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+                0.0,
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+                0.0,
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Return the element at `row`, `col`.
+    pub fn get(&self, row: usize, col: usize) -> f32 {
+        self.m[row][col]
+    }
+
+    /// Return the rows of this matrix.
+    pub fn rows(&self) -> [[f32; 4]; 4] {
+        self.m
+    }
+
+    /// Return the transpose of this matrix.
+    pub fn transpose(&self) -> Matrix4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        Matrix4::new(m)
+    }
+
+    /// Return the inverse of this matrix, or `None` if it is singular.
+    pub fn inverse(&self) -> Option<Matrix4> {
+        // Gauss-Jordan elimination with partial pivoting on [self | I].
+        let mut a = self.m;
+        let mut inv = Matrix4::identity().m;
+
+        for col in 0..4 {
+            let pivot = (col..4)
+                .max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))
+                .unwrap();
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let scale = 1.0 / a[col][col];
+            for k in 0..4 {
+                a[col][k] *= scale;
+                inv[col][k] *= scale;
+            }
+
+            for row in 0..4 {
+                if row == col {
+                    continue;
+                }
+                let factor = a[row][col];
+                if factor == 0.0 {
+                    continue;
+                }
+                for k in 0..4 {
+                    a[row][k] -= factor * a[col][k];
+                    inv[row][k] -= factor * inv[col][k];
+                }
+            }
+        }
+
+        Some(Matrix4::new(inv))
+    }
+
+    /// Transform the point `p`, including translation.
+    pub fn transform_point(&self, p: Vector3) -> Vector3 {
+        let m = &self.m;
+        let x = m[0][0] * p.x() + m[0][1] * p.y() + m[0][2] * p.z() + m[0][3];
+        let y = m[1][0] * p.x() + m[1][1] * p.y() + m[1][2] * p.z() + m[1][3];
+        let z = m[2][0] * p.x() + m[2][1] * p.y() + m[2][2] * p.z() + m[2][3];
+        let w = m[3][0] * p.x() + m[3][1] * p.y() + m[3][2] * p.z() + m[3][3];
+        if w == 1.0 {
+            Vector3::new(x, y, z)
+        } else {
+            Vector3::new(x / w, y / w, z / w)
+        }
+    }
+
+    /// Transform the direction `v`, ignoring translation.
+    pub fn transform_vector(&self, v: Vector3) -> Vector3 {
+        let m = &self.m;
+        Vector3::new(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        )
+    }
+}
+
+impl Default for Matrix4 {
+    fn default() -> Self {
+        Matrix4::identity()
+    }
+}
+
+impl Mul<Matrix4> for Matrix4 {
+    type Output = Matrix4;
+
+    fn mul(self, rhs: Matrix4) -> Self::Output {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Matrix4::new(m)
+    }
+}
+
+binop_ref_impl! { impl Mul<Matrix4> for Matrix4, mul -> Matrix4 }
+
+impl fmt::Display for Matrix4 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let p = f.precision().unwrap_or(2);
+        write!(f, "[")?;
+        for (i, row) in self.m.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(
+                f,
+                "[{:.*}, {:.*}, {:.*}, {:.*}]",
+                p, row[0], p, row[1], p, row[2], p, row[3]
+            )?;
+        }
+        write!(f, "]")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(expected: Matrix4, actual: Matrix4) {
+        for i in 0..4 {
+            for j in 0..4 {
+                assert!(
+                    (expected.get(i, j) - actual.get(i, j)).abs() < 1e-5,
+                    "Expected {:.4}, got {:.4}.",
+                    expected,
+                    actual
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_multiply_transpose() {
+        let a = Matrix4::new([
+            [1.0, 2.0, 3.0, 4.0],
+            [5.0, 6.0, 7.0, 8.0],
+            [9.0, 10.0, 11.0, 12.0],
+            [13.0, 14.0, 15.0, 16.0],
+        ]);
+        assert_eq!(a, a * Matrix4::identity());
+        assert_eq!(a, Matrix4::identity() * a);
+
+        let product = a * a.transpose();
+        assert_eq!(30.0, product.get(0, 0));
+        assert_eq!(70.0, product.get(0, 1));
+        assert_eq!(product, product.transpose(), "A A^T must be symmetric.");
+        assert_eq!(a, a.transpose().transpose());
+    }
+
+    #[test]
+    fn test_inverse() {
+        let m = Matrix4::translation(Vector3::new(1.0, -2.0, 3.0))
+            * Matrix4::rotation(Quaternion::from_axis_angle(
+                Vector3::new(1.0, 1.0, 0.0),
+                0.7,
+            ))
+            * Matrix4::scaling(Vector3::new(2.0, 0.5, 3.0));
+        let inverse = m.inverse().unwrap();
+        assert_close(Matrix4::identity(), m * inverse);
+        assert_close(Matrix4::identity(), inverse * m);
+
+        // A permutation needs pivoting to invert.
+        let swap = Matrix4::new([
+            [0.0, 1.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        assert_eq!(Some(swap), swap.inverse());
+
+        assert!(Matrix4::scaling(Vector3::new(1.0, 0.0, 1.0))
+            .inverse()
+            .is_none());
+    }
+
+    #[test]
+    fn test_transform_point_vector() {
+        let m = Matrix4::translation(Vector3::new(1.0, 2.0, 3.0))
+            * Matrix4::scaling(Vector3::new(2.0, 2.0, 2.0));
+        let p = Vector3::new(1.0, 0.0, -1.0);
+        assert_eq!(Vector3::new(3.0, 2.0, 1.0), m.transform_point(p));
+        assert_eq!(Vector3::new(2.0, 0.0, -2.0), m.transform_vector(p));
+    }
+}
@@ -0,0 +1,570 @@
+//! Loader for Wavefront OBJ meshes and their MTL material libraries.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::hittable::MaterialId;
+use crate::scene::{default_gamma, MaterialDesc, TextureDesc};
+use crate::shapes::Triangle;
+use crate::vector::Vector3;
+
+/// An error produced while loading an OBJ or MTL file.
+#[derive(Debug)]
+pub enum ObjError {
+    /// A file could not be read.
+    Io { path: PathBuf, error: io::Error },
+    /// A statement in `file` could not be understood.
+    Parse {
+        file: String,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            ObjError::Parse {
+                file,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", file, line, message),
+        }
+    }
+}
+
+impl Error for ObjError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ObjError::Io { error, .. } => Some(error),
+            ObjError::Parse { .. } => None,
+        }
+    }
+}
+
+/// A triangulated mesh loaded from an OBJ file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ObjMesh {
+    /// The faces of the mesh. Their `MaterialId`s are local, indexing into `material_names`.
+    pub triangles: Vec<Triangle>,
+    /// Names of the materials selected with `usemtl`, in order of first use. Faces that appear
+    /// before any `usemtl` refer to the empty name.
+    pub material_names: Vec<String>,
+    /// Materials defined by the mesh's material libraries.
+    pub materials: BTreeMap<String, MaterialDesc>,
+    /// Image textures used by `materials`, keyed by the names they use, which are the images'
+    /// paths.
+    pub textures: BTreeMap<String, TextureDesc>,
+    /// Material libraries named by `mtllib` statements.
+    pub material_libraries: Vec<String>,
+}
+
+/// Line-oriented parsing state shared by the OBJ and MTL parsers.
+struct Parser<'a> {
+    file: &'a str,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: impl Into<String>) -> ObjError {
+        ObjError::Parse {
+            file: self.file.to_string(),
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    fn number(&self, token: Option<&str>, what: &str) -> Result<f32, ObjError> {
+        let token = token.ok_or_else(|| self.error(format!("missing {}", what)))?;
+        token
+            .parse()
+            .map_err(|_| self.error(format!("invalid {} \"{}\"", what, token)))
+    }
+
+    fn vector(
+        &self,
+        tokens: &mut std::str::SplitWhitespace,
+        what: &str,
+    ) -> Result<Vector3, ObjError> {
+        Ok(Vector3::new(
+            self.number(tokens.next(), what)?,
+            self.number(tokens.next(), what)?,
+            self.number(tokens.next(), what)?,
+        ))
+    }
+
+    /// Parse a color, rejecting negative components, which would make light out of nothing.
+    fn color(&self, tokens: &mut std::str::SplitWhitespace) -> Result<Vector3, ObjError> {
+        let color = self.vector(tokens, "color component")?;
+        if color.x() < 0.0 || color.y() < 0.0 || color.z() < 0.0 {
+            return Err(self.error(format!(
+                "color components must be non-negative, got {}",
+                color
+            )));
+        }
+        Ok(color)
+    }
+
+    /// Resolve a 1-based, possibly negative (relative) OBJ index into a list of length `len`.
+    fn index(&self, token: &str, len: usize, what: &str) -> Result<usize, ObjError> {
+        let i: i64 = token
+            .parse()
+            .map_err(|_| self.error(format!("invalid {} index \"{}\"", what, token)))?;
+        let resolved = match i {
+            0 => None,
+            i if i > 0 => Some(i - 1),
+            i => Some(len as i64 + i),
+        };
+        match resolved {
+            Some(r) if r >= 0 && (r as usize) < len => Ok(r as usize),
+            _ => Err(self.error(format!(
+                "{} index {} is out of range ({} defined)",
+                what, i, len
+            ))),
+        }
+    }
+}
+
+/// Parse the OBJ source `source`. `file` names the source in error messages. Material libraries
+/// are recorded but not loaded; see `load_obj`.
+pub fn parse_obj(source: &str, file: &str) -> Result<ObjMesh, ObjError> {
+    let mut parser = Parser { file, line: 0 };
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut mesh = ObjMesh::default();
+    let mut current_material = None;
+
+    for (i, line) in source.lines().enumerate() {
+        parser.line = i + 1;
+        let line = line.split('#').next().unwrap();
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => positions.push(parser.vector(&mut tokens, "vertex coordinate")?),
+            Some("vn") => normals.push(parser.vector(&mut tokens, "normal coordinate")?),
+            Some("vt") => {
+                let u = parser.number(tokens.next(), "texture coordinate")?;
+                let v = match tokens.next() {
+                    Some(token) => parser.number(Some(token), "texture coordinate")?,
+                    None => 0.0,
+                };
+                uvs.push([u, v]);
+            }
+            Some("f") => {
+                let mut corners = Vec::new();
+                for token in tokens {
+                    let mut parts = token.split('/');
+                    let position =
+                        parser.index(parts.next().unwrap(), positions.len(), "vertex")?;
+                    let uv = match parts.next() {
+                        Some("") | None => None,
+                        Some(t) => Some(parser.index(t, uvs.len(), "texture coordinate")?),
+                    };
+                    let normal = match parts.next() {
+                        Some("") | None => None,
+                        Some(t) => Some(parser.index(t, normals.len(), "normal")?),
+                    };
+                    if parts.next().is_some() {
+                        return Err(parser.error(format!("malformed face vertex \"{}\"", token)));
+                    }
+                    corners.push((position, uv, normal));
+                }
+                if corners.len() < 3 {
+                    return Err(parser.error(format!(
+                        "face has {} vertices; at least 3 are required",
+                        corners.len()
+                    )));
+                }
+
+                let material = *current_material.get_or_insert_with(|| {
+                    mesh.material_names.push(String::new());
+                    mesh.material_names.len() - 1
+                });
+
+                // Triangulate polygons as a fan around the first vertex.
+                for k in 1..corners.len() - 1 {
+                    let face = [corners[0], corners[k], corners[k + 1]];
+                    let mut triangle = Triangle::new(
+                        positions[face[0].0],
+                        positions[face[1].0],
+                        positions[face[2].0],
+                        MaterialId(material),
+                    );
+                    if let [Some(a), Some(b), Some(c)] = face.map(|corner| corner.2) {
+                        triangle = triangle.with_normals([normals[a], normals[b], normals[c]]);
+                    }
+                    if let [Some(a), Some(b), Some(c)] = face.map(|corner| corner.1) {
+                        triangle = triangle.with_uvs([uvs[a], uvs[b], uvs[c]]);
+                    }
+                    mesh.triangles.push(triangle);
+                }
+            }
+            Some("usemtl") => {
+                let name = tokens
+                    .next()
+                    .ok_or_else(|| parser.error("usemtl requires a material name"))?;
+                let index = match mesh.material_names.iter().position(|n| n == name) {
+                    Some(index) => index,
+                    None => {
+                        mesh.material_names.push(name.to_string());
+                        mesh.material_names.len() - 1
+                    }
+                };
+                current_material = Some(index);
+            }
+            Some("mtllib") => mesh
+                .material_libraries
+                .extend(tokens.map(|t| t.to_string())),
+            // Groups, objects, smoothing groups, lines and so on do not affect rendering.
+            _ => {}
+        }
+    }
+
+    Ok(mesh)
+}
+
+/// Material properties accumulated from an MTL `newmtl` block.
+struct MtlProperties {
+    diffuse: Vector3,
+    /// Image multiplying `diffuse`, as named in the MTL file.
+    diffuse_map: Option<String>,
+    specular: Vector3,
+    emission: Vector3,
+    ior: f32,
+    shininess: f32,
+    dissolve: f32,
+    illum: u32,
+}
+
+impl Default for MtlProperties {
+    fn default() -> Self {
+        MtlProperties {
+            diffuse: Vector3::new(0.8, 0.8, 0.8),
+            diffuse_map: None,
+            specular: Vector3::zeros(),
+            emission: Vector3::zeros(),
+            ior: 1.5,
+            shininess: 0.0,
+            dissolve: 1.0,
+            illum: 2,
+        }
+    }
+}
+
+impl MtlProperties {
+    /// Pick the closest of our materials to these Phong-style properties.
+    fn to_desc(&self) -> MaterialDesc {
+        if self.emission.squared_norm() > 0.0 {
+            MaterialDesc::Emissive {
+                color: self.emission,
+                intensity: 1.0,
+                texture: None,
+            }
+        } else if matches!(self.illum, 4 | 6 | 7) || self.dissolve < 1.0 {
+            MaterialDesc::Dielectric { ior: self.ior }
+        } else if matches!(self.illum, 3 | 5) {
+            MaterialDesc::Metal {
+                albedo: self.specular,
+                // Map the Phong exponent onto a roughness: sharp highlights are barely fuzzy.
+                fuzz: (2.0 / (self.shininess + 2.0)).sqrt().clamp(0.0, 1.0),
+                texture: None,
+            }
+        } else {
+            MaterialDesc::Lambertian {
+                albedo: self.diffuse,
+                texture: self.diffuse_map.clone(),
+            }
+        }
+    }
+}
+
+/// Parse the MTL source `source` into material descriptions keyed by name. `file` names the
+/// source in error messages. Diffuse texture maps are named by their paths as written in the
+/// source; see `load_obj`.
+pub fn parse_mtl(source: &str, file: &str) -> Result<BTreeMap<String, MaterialDesc>, ObjError> {
+    let mut parser = Parser { file, line: 0 };
+    let mut materials = BTreeMap::new();
+    let mut current: Option<(String, MtlProperties)> = None;
+
+    for (i, line) in source.lines().enumerate() {
+        parser.line = i + 1;
+        let line = line.split('#').next().unwrap();
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+
+        if keyword == "newmtl" {
+            let name = tokens
+                .next()
+                .ok_or_else(|| parser.error("newmtl requires a material name"))?;
+            if let Some((name, properties)) = current.take() {
+                materials.insert(name, properties.to_desc());
+            }
+            current = Some((name.to_string(), MtlProperties::default()));
+            continue;
+        }
+
+        let properties = match current.as_mut() {
+            Some((_, properties)) => properties,
+            None => return Err(parser.error(format!("\"{}\" appears before newmtl", keyword))),
+        };
+        match keyword {
+            "Kd" => properties.diffuse = parser.color(&mut tokens)?,
+            "Ks" => properties.specular = parser.color(&mut tokens)?,
+            "Ke" => properties.emission = parser.color(&mut tokens)?,
+            "Ns" => {
+                properties.shininess = parser.number(tokens.next(), "shininess")?;
+                if properties.shininess < 0.0 {
+                    return Err(parser.error("shininess must be non-negative"));
+                }
+            }
+            "d" => properties.dissolve = parser.number(tokens.next(), "dissolve")?,
+            "Tr" => properties.dissolve = 1.0 - parser.number(tokens.next(), "transparency")?,
+            "Ni" => {
+                properties.ior = parser.number(tokens.next(), "index of refraction")?;
+                if properties.ior <= 0.0 {
+                    return Err(parser.error("index of refraction must be positive"));
+                }
+            }
+            "illum" => {
+                let token = tokens.next().unwrap_or("");
+                properties.illum = token.parse().map_err(|_| {
+                    parser.error(format!("invalid illumination model \"{}\"", token))
+                })?;
+            }
+            // Options such as `-s u v w` come before the file name.
+            "map_Kd" => {
+                let file = tokens
+                    .last()
+                    .ok_or_else(|| parser.error("map_Kd requires a file name"))?;
+                properties.diffuse_map = Some(file.to_string());
+            }
+            // Other texture maps and properties do not affect rendering.
+            _ => {}
+        }
+    }
+
+    if let Some((name, properties)) = current {
+        materials.insert(name, properties.to_desc());
+    }
+    Ok(materials)
+}
+
+fn read(path: &Path) -> Result<String, ObjError> {
+    fs::read_to_string(path).map_err(|error| ObjError::Io {
+        path: path.to_path_buf(),
+        error,
+    })
+}
+
+/// Load the OBJ file at `path` together with the material libraries it references, which are
+/// resolved relative to the OBJ file. The images their materials map are resolved relative to
+/// each library and listed in `ObjMesh::textures`, but not loaded.
+pub fn load_obj(path: impl AsRef<Path>) -> Result<ObjMesh, ObjError> {
+    let path = path.as_ref();
+    let mut mesh = parse_obj(&read(path)?, &path.display().to_string())?;
+
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    for library in &mesh.material_libraries {
+        let mtl_path = dir.join(library);
+        let mut materials = parse_mtl(&read(&mtl_path)?, &mtl_path.display().to_string())?;
+        let mtl_dir = mtl_path.parent().unwrap_or_else(|| Path::new(""));
+        for material in materials.values_mut() {
+            if let MaterialDesc::Lambertian {
+                texture: Some(map), ..
+            } = material
+            {
+                *map = mtl_dir.join(&*map).display().to_string();
+                let image = TextureDesc::Image {
+                    path: map.clone(),
+                    gamma: default_gamma(),
+                };
+                mesh.textures.insert(map.clone(), image);
+            }
+        }
+        mesh.materials.extend(materials);
+    }
+    Ok(mesh)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const QUAD: &str = "
+        # A unit quad in the xy-plane.
+        mtllib quad.mtl
+        v 0 0 0
+        v 1 0 0
+        v 1 1 0
+        v 0 1 0
+        vt 0 0
+        vt 1 0
+        vt 1 1
+        vt 0 1
+        vn 0 0 1
+        usemtl red
+        f 1/1/1 2/2/1 3/3/1 4/4/1
+        usemtl blue
+        f -4 -2 -1
+    ";
+
+    #[test]
+    fn test_parse_obj() {
+        let mesh = parse_obj(QUAD, "quad.obj").unwrap();
+        assert_eq!(3, mesh.triangles.len());
+        assert_eq!(vec!["red", "blue"], mesh.material_names);
+        assert_eq!(vec!["quad.mtl"], mesh.material_libraries);
+
+        let first = mesh.triangles[0];
+        assert_eq!(MaterialId(0), first.material);
+        assert_eq!(Some([Vector3::new(0.0, 0.0, 1.0); 3]), first.normals);
+        assert_eq!(Some([[0.0, 0.0], [1.0, 0.0], [1.0, 1.0]]), first.uvs);
+
+        // Relative indices, no normals or texture coordinates.
+        let last = mesh.triangles[2];
+        assert_eq!(MaterialId(1), last.material);
+        assert_eq!(
+            [
+                Vector3::zeros(),
+                Vector3::new(1.0, 1.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0)
+            ],
+            last.vertices
+        );
+        assert_eq!(None, last.normals);
+        assert_eq!(None, last.uvs);
+    }
+
+    #[test]
+    fn test_default_material() {
+        let mesh = parse_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n", "tri.obj").unwrap();
+        assert_eq!(vec![""], mesh.material_names);
+        assert_eq!(MaterialId(0), mesh.triangles[0].material);
+    }
+
+    #[test]
+    fn test_malformed_faces() {
+        let cases = [
+            ("v 0 0 0\nv 1 0 0\nf 1 2\n", 3, "face has 2 vertices"),
+            (
+                "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n",
+                4,
+                "vertex index 4 is out of range",
+            ),
+            (
+                "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 0 1 2\n",
+                4,
+                "vertex index 0 is out of range",
+            ),
+            (
+                "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 x\n",
+                4,
+                "invalid vertex index \"x\"",
+            ),
+            (
+                "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1//1 2 3\n",
+                4,
+                "normal index 1",
+            ),
+            (
+                "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvn 0 0 1\nf 1/1/1/1 2 3\n",
+                6,
+                "malformed face vertex",
+            ),
+            ("v 0 0\n", 1, "missing vertex coordinate"),
+        ];
+        for (source, line, message) in cases {
+            match parse_obj(source, "bad.obj") {
+                Err(ObjError::Parse {
+                    file,
+                    line: l,
+                    message: m,
+                }) => {
+                    assert_eq!("bad.obj", file);
+                    assert_eq!(line, l, "Wrong line for {:?}: {}.", source, m);
+                    assert!(
+                        m.contains(message),
+                        "Expected \"{}\", got \"{}\".",
+                        message,
+                        m
+                    );
+                }
+                other => panic!("Expected a parse error for {:?}, got {:?}.", source, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_mtl() {
+        let source = "
+            newmtl matte
+            Kd 0.5 0.25 0.125
+            newmtl chrome
+            illum 3
+            Ks 0.9 0.9 0.9
+            Ns 1000
+            newmtl glass
+            illum 7
+            Ni 1.33
+            newmtl lamp
+            Ke 4 4 4
+            newmtl wood
+            Kd 1 1 1
+            map_Kd -s 2 2 1 maps/wood.png
+        ";
+        let materials = parse_mtl(source, "test.mtl").unwrap();
+        assert_eq!(
+            Some(&MaterialDesc::Lambertian {
+                albedo: Vector3::new(0.5, 0.25, 0.125),
+                texture: None,
+            }),
+            materials.get("matte")
+        );
+        assert!(matches!(
+            materials.get("chrome"),
+            Some(MaterialDesc::Metal { fuzz, .. }) if *fuzz < 0.1
+        ));
+        assert_eq!(
+            Some(&MaterialDesc::Dielectric { ior: 1.33 }),
+            materials.get("glass")
+        );
+        assert!(matches!(
+            materials.get("lamp"),
+            Some(MaterialDesc::Emissive { .. })
+        ));
+        assert_eq!(
+            Some(&MaterialDesc::Lambertian {
+                albedo: Vector3::ones(),
+                texture: Some("maps/wood.png".to_string()),
+            }),
+            materials.get("wood")
+        );
+
+        let err = parse_mtl("Kd 1 1 1\n", "test.mtl").unwrap_err();
+        assert_eq!("test.mtl:1: \"Kd\" appears before newmtl", err.to_string());
+        for keyword in ["Kd", "Ks", "Ke"] {
+            let source = format!("newmtl bad\n{} 0.5 -0.1 0.5\n", keyword);
+            let err = parse_mtl(&source, "test.mtl").unwrap_err().to_string();
+            assert!(
+                err.starts_with("test.mtl:2: color components must be non-negative"),
+                "Got {}.",
+                err
+            );
+        }
+        let err = parse_mtl("newmtl bad\nmap_Kd\n", "test.mtl").unwrap_err();
+        assert_eq!("test.mtl:2: map_Kd requires a file name", err.to_string());
+        let err = parse_mtl("newmtl bad\nNs -5\n", "test.mtl").unwrap_err();
+        assert_eq!(
+            "test.mtl:2: shininess must be non-negative",
+            err.to_string()
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use std::fmt;
+use std::ops::{Mul, Neg};
+
+use crate::vector::{binop_ref_impl, unop_ref_impl, Vector3};
+
+/// A quaternion `w + xi + yj + zk`. Unit quaternions represent rotations.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quaternion {
+    w: f32,
+    x: f32,
+    y: f32,
+    z: f32,
+}
+
+impl Quaternion {
+    /// Create a new `Quaternion` with scalar part `w` and vector part `x`, `y`, `z`.
+    pub fn new(w: f32, x: f32, y: f32, z: f32) -> Self {
+        Quaternion { w, x, y, z }
+    }
+
+    /// Create a new identity `Quaternion`, representing no rotation.
+    pub fn identity() -> Self {
+        Quaternion::new(1.0, 0.0, 0.0, 0.0)
+    }
+
+    /// Create a new unit `Quaternion` rotating by `angle` radians counter-clockwise about `axis`,
+    /// which need not be normalized.
+    pub fn from_axis_angle(axis: Vector3, angle: f32) -> Self {
+        let axis = axis.normalized();
+        let (sin, cos) = (0.5 * angle).sin_cos();
+        Quaternion::new(cos, sin * axis.x(), sin * axis.y(), sin * axis.z())
+    }
+
+    /// Return the scalar part of this quaternion.
+    pub fn w(self) -> f32 {
+        self.w
+    }
+
+    /// Return the `i` component of this quaternion.
+    pub fn x(self) -> f32 {
+        self.x
+    }
+
+    /// Return the `j` component of this quaternion.
+    pub fn y(self) -> f32 {
+        self.y
+    }
+
+    /// Return the `k` component of this quaternion.
+    pub fn z(self) -> f32 {
+        self.z
+    }
+
+    /// Return the vector part of this quaternion.
+    pub fn vector(self) -> Vector3 {
+        Vector3::new(self.x, self.y, self.z)
+    }
+
+    /// Compute the four-dimensional dot product of this quaternion and `other`.
+    pub fn dot(self, other: Quaternion) -> f32 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    /// Compute the norm of this quaternion.
+    pub fn norm(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    /// Return a normalized copy of this quaternion.
+    pub fn normalized(self) -> Quaternion {
+        let norm = self.norm();
+        Quaternion::new(self.w / norm, self.x / norm, self.y / norm, self.z / norm)
+    }
+
+    /// Return the conjugate of this quaternion, which is the inverse rotation for unit
+    /// quaternions.
+    pub fn conjugate(self) -> Quaternion {
+        Quaternion::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    /// Rotate `v` by this unit quaternion.
+    pub fn rotate(self, v: Vector3) -> Vector3 {
+        // v' = v + 2w(q x v) + 2q x (q x v), with q the vector part.
+        let q = self.vector();
+        let t = 2.0 * q.cross(v);
+        v + self.w * t + q.cross(t)
+    }
+
+    /// Spherically interpolate from this unit quaternion (`t = 0`) to `other` (`t = 1`) along the
+    /// shortest arc.
+    pub fn slerp(self, other: Quaternion, t: f32) -> Quaternion {
+        let mut cos_theta = self.dot(other);
+        // q and -q are the same rotation; take the shorter way around.
+        let other = if cos_theta < 0.0 {
+            cos_theta = -cos_theta;
+            -other
+        } else {
+            other
+        };
+
+        let (a, b) = if cos_theta > 0.9995 {
+            // Nearly parallel: sin(theta) is too small to divide by, so interpolate linearly.
+            (1.0 - t, t)
+        } else {
+            let theta = cos_theta.acos();
+            let sin_theta = theta.sin();
+            (
+                ((1.0 - t) * theta).sin() / sin_theta,
+                (t * theta).sin() / sin_theta,
+            )
+        };
+
+        Quaternion::new(
+            a * self.w + b * other.w,
+            a * self.x + b * other.x,
+            a * self.y + b * other.y,
+            a * self.z + b * other.z,
+        )
+        .normalized()
+    }
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Quaternion::identity()
+    }
+}
+
+impl Mul<Quaternion> for Quaternion {
+    type Output = Quaternion;
+
+    /// The Hamilton product; `a * b` rotates by `b` and then by `a`.
+    fn mul(self, rhs: Quaternion) -> Self::Output {
+        Quaternion::new(
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        )
+    }
+}
+
+binop_ref_impl! { impl Mul<Quaternion> for Quaternion, mul -> Quaternion }
+
+impl Neg for Quaternion {
+    type Output = Quaternion;
+
+    fn neg(self) -> Self::Output {
+        Quaternion::new(-self.w, -self.x, -self.y, -self.z)
+    }
+}
+
+unop_ref_impl! { impl Neg for Quaternion, neg -> Quaternion }
+
+impl fmt::Display for Quaternion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let p = f.precision().unwrap_or(2);
+        write!(
+            f,
+            "[{:.*}; {:.*}, {:.*}, {:.*}]",
+            p, self.w, p, self.x, p, self.y, p, self.z
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matrix::Matrix4;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn assert_close(expected: Vector3, actual: Vector3) {
+        assert!(
+            (expected - actual).norm() < 1e-5,
+            "Expected {:.4}, got {:.4}.",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_axis_angle_rotation() {
+        let q = Quaternion::from_axis_angle(Vector3::new(0.0, 0.0, 2.0), FRAC_PI_2);
+        assert!((q.norm() - 1.0).abs() < 1e-6);
+
+        let x = Vector3::new(1.0, 0.0, 0.0);
+        assert_close(Vector3::new(0.0, 1.0, 0.0), q.rotate(x));
+        assert_close(x, q.conjugate().rotate(q.rotate(x)));
+        assert_close(Vector3::new(-1.0, 0.0, 0.0), (q * q).rotate(x));
+
+        // The rotation matrix agrees with rotating directly.
+        let q = Quaternion::from_axis_angle(Vector3::new(1.0, -2.0, 0.5), 1.3);
+        let v = Vector3::new(0.3, 0.7, -1.1);
+        assert_close(q.rotate(v), Matrix4::rotation(q).transform_vector(v));
+    }
+
+    #[test]
+    fn test_slerp() {
+        let axis = Vector3::new(0.0, 1.0, 0.0);
+        let a = Quaternion::from_axis_angle(axis, 0.0);
+        let b = Quaternion::from_axis_angle(axis, FRAC_PI_2);
+        let v = Vector3::new(1.0, 0.0, 0.0);
+
+        assert_close(a.rotate(v), a.slerp(b, 0.0).rotate(v));
+        assert_close(b.rotate(v), a.slerp(b, 1.0).rotate(v));
+        let halfway = Quaternion::from_axis_angle(axis, FRAC_PI_2 / 2.0);
+        assert_close(halfway.rotate(v), a.slerp(b, 0.5).rotate(v));
+
+        // -b is the same rotation as b, and slerp must still take the short arc.
+        assert_close(halfway.rotate(v), a.slerp(-b, 0.5).rotate(v));
+
+        // Nearly identical rotations fall back to linear interpolation.
+        let c = Quaternion::from_axis_angle(axis, 1e-4);
+        assert!((a.slerp(c, 0.5).norm() - 1.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,56 @@
+use crate::vector::Vector3;
+
+/// A half-line starting at `origin` and extending along `direction`, traced at the instant
+/// `time`. Moving objects are intersected where they are at that time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vector3,
+    pub direction: Vector3,
+    pub time: f32,
+}
+
+impl Ray {
+    /// Create a new `Ray` from `origin` in the (not necessarily normalized) `direction`, at time
+    /// zero.
+    pub fn new(origin: Vector3, direction: Vector3) -> Self {
+        Ray::with_time(origin, direction, 0.0)
+    }
+
+    /// Create a new `Ray` from `origin` in `direction` at `time`.
+    pub fn with_time(origin: Vector3, direction: Vector3, time: f32) -> Self {
+        Ray {
+            origin,
+            direction,
+            time,
+        }
+    }
+
+    /// Return the point along this ray at parameter `t`.
+    pub fn at(&self, t: f32) -> Vector3 {
+        self.origin + t * self.direction
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_at() {
+        let ray = Ray::new(Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 2.0, 0.0));
+        let expected = Vector3::new(1.0, 3.0, 0.0);
+        let point = ray.at(1.5);
+        assert_eq!(
+            expected, point,
+            "Ray::at() failed on {:?} at t = 1.5. Expected {}, got {}.",
+            ray, expected, point
+        );
+
+        let origin = ray.at(0.0);
+        assert_eq!(
+            ray.origin, origin,
+            "Ray::at() failed on {:?} at t = 0. Expected {}, got {}.",
+            ray, ray.origin, origin
+        );
+    }
+}
@@ -0,0 +1,483 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rayon::prelude::*;
+
+use crate::accumulator::{Accumulator, PixelStats};
+use crate::aov::Aov;
+use crate::camera::Camera;
+use crate::denoise::Guides;
+use crate::framebuffer::Framebuffer;
+use crate::hittable::TraversalStats;
+use crate::integrator::PathTracer;
+use crate::rng::Pcg32;
+use crate::scene::RenderSettings;
+use crate::vector::Color;
+use crate::world::World;
+
+/// Progress of a render, reported after every completed tile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub tiles_done: usize,
+    pub tiles_total: usize,
+}
+
+impl Progress {
+    /// Return the completed fraction of the render, in `[0, 1]`.
+    pub fn fraction(&self) -> f32 {
+        self.tiles_done as f32 / self.tiles_total.max(1) as f32
+    }
+}
+
+/// A rectangular block of pixels rendered as one unit of work.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Tile {
+    index: usize,
+    x0: usize,
+    y0: usize,
+    width: usize,
+    height: usize,
+}
+
+/// Renders a world by splitting the image into tiles and tracing them in parallel.
+///
+/// Each tile draws its random numbers from its own generator, seeded from the render seed, the
+/// pass and the tile index, so the output does not depend on the number of threads or on the
+/// order in which tiles complete.
+#[derive(Debug)]
+pub struct Renderer {
+    pub settings: RenderSettings,
+    /// Width and height of a tile, in pixels.
+    pub tile_size: usize,
+    /// Pool of worker threads, or `None` to use rayon's global pool with one thread per CPU.
+    pool: Option<rayon::ThreadPool>,
+}
+
+impl Renderer {
+    /// Create a new `Renderer` with the given settings, 16-pixel tiles, and one thread per CPU.
+    pub fn new(settings: RenderSettings) -> Self {
+        Renderer {
+            settings,
+            tile_size: 16,
+            pool: None,
+        }
+    }
+
+    /// Use a pool of `threads` worker threads, or return an error if it cannot be created.
+    pub fn with_threads(mut self, threads: usize) -> Result<Self, rayon::ThreadPoolBuildError> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()?;
+        self.pool = Some(pool);
+        Ok(self)
+    }
+
+    /// Return the number of worker threads.
+    pub fn threads(&self) -> usize {
+        match &self.pool {
+            Some(pool) => pool.current_num_threads(),
+            None => rayon::current_num_threads(),
+        }
+    }
+
+    /// Use tiles of `tile_size` by `tile_size` pixels.
+    pub fn with_tile_size(mut self, tile_size: usize) -> Self {
+        self.tile_size = tile_size.max(1);
+        self
+    }
+
+    fn tiles(&self) -> Vec<Tile> {
+        let width = self.settings.width as usize;
+        let height = self.settings.height as usize;
+        let size = self.tile_size;
+
+        let mut tiles = Vec::new();
+        for y0 in (0..height).step_by(size) {
+            for x0 in (0..width).step_by(size) {
+                tiles.push(Tile {
+                    index: tiles.len(),
+                    x0,
+                    y0,
+                    width: size.min(width - x0),
+                    height: size.min(height - y0),
+                });
+            }
+        }
+        tiles
+    }
+
+    /// Render `world` as seen by `camera`.
+    pub fn render(&self, world: &World, camera: &Camera) -> Framebuffer {
+        self.render_with_progress(world, camera, |_| {})
+    }
+
+    /// Render `world` as seen by `camera`, calling `progress` after every completed tile. The
+    /// callback may be called from any worker thread.
+    pub fn render_with_progress(
+        &self,
+        world: &World,
+        camera: &Camera,
+        progress: impl Fn(Progress) + Sync,
+    ) -> Framebuffer {
+        let mut accumulator = self.accumulator();
+        let samples = self.settings.samples_per_pixel.max(1);
+        self.render_pass_with_progress(world, camera, &mut accumulator, samples, progress);
+        accumulator.image()
+    }
+
+    /// Create an empty accumulator for progressive rendering with these settings.
+    pub fn accumulator(&self) -> Accumulator {
+        Accumulator::new(
+            self.settings.width as usize,
+            self.settings.height as usize,
+            self.settings.seed,
+        )
+    }
+
+    /// Add a pass of `samples` samples per pixel to `accumulator`, which must match the image
+    /// size of these settings.
+    ///
+    /// Each pass draws from its own random streams, chosen by the number of passes already in
+    /// `accumulator` and by the seed it records, so a render resumed from a saved accumulator
+    /// matches one that never stopped as long as every pass takes the same number of samples.
+    pub fn render_pass(
+        &self,
+        world: &World,
+        camera: &Camera,
+        accumulator: &mut Accumulator,
+        samples: u32,
+    ) {
+        self.render_pass_with_progress(world, camera, accumulator, samples, |_| {})
+    }
+
+    /// Add a pass to `accumulator` as `render_pass` does, calling `progress` after every
+    /// completed tile.
+    pub fn render_pass_with_progress(
+        &self,
+        world: &World,
+        camera: &Camera,
+        accumulator: &mut Accumulator,
+        samples: u32,
+        progress: impl Fn(Progress) + Sync,
+    ) {
+        let tiles = self.tiles();
+        let tiles_total = tiles.len();
+        let tiles_done = AtomicUsize::new(0);
+        let previous: &Accumulator = accumulator;
+
+        let trace_tile = |tile: &Tile| {
+            let pixels = self.render_tile(world, camera, tile, previous, samples);
+            let done = tiles_done.fetch_add(1, Ordering::SeqCst) + 1;
+            progress(Progress {
+                tiles_done: done,
+                tiles_total,
+            });
+            pixels
+        };
+
+        let rendered: Vec<Vec<PixelStats>> =
+            self.install(|| tiles.par_iter().map(trace_tile).collect());
+
+        let width = accumulator.width();
+        let mut pixels = accumulator.pixels().to_vec();
+        for (tile, stats) in tiles.iter().zip(rendered) {
+            for (i, stats) in stats.into_iter().enumerate() {
+                pixels[(tile.y0 + i / tile.width) * width + tile.x0 + i % tile.width] = stats;
+            }
+        }
+        accumulator.add_pass(pixels, samples);
+    }
+
+    /// Run `f` on this renderer's thread pool, so that any parallel work it does uses the
+    /// renderer's threads.
+    pub fn install<R: Send>(&self, f: impl FnOnce() -> R + Send) -> R {
+        match &self.pool {
+            Some(pool) => pool.install(f),
+            None => f(),
+        }
+    }
+
+    /// Trace up to `samples` more samples through every pixel of `tile`, adding them to the
+    /// statistics in `previous`, and return the new statistics in row-major order within the
+    /// tile. With adaptive sampling, pixels stop early once they converge.
+    fn render_tile(
+        &self,
+        world: &World,
+        camera: &Camera,
+        tile: &Tile,
+        previous: &Accumulator,
+        samples: u32,
+    ) -> Vec<PixelStats> {
+        let stream = (previous.passes() as u64) << 32 | tile.index as u64;
+        let mut rng = Pcg32::new(previous.seed(), stream);
+        let width = self.settings.width as f32;
+        let height = self.settings.height as f32;
+        let tracer = PathTracer::from_settings(&self.settings);
+        let threshold = self.settings.adaptive_threshold;
+        let min_samples = self.settings.min_samples.max(2);
+
+        let mut pixels = Vec::with_capacity(tile.width * tile.height);
+        for y in tile.y0..tile.y0 + tile.height {
+            for x in tile.x0..tile.x0 + tile.width {
+                let mut stats = previous.pixel(x, y);
+                for _ in 0..samples {
+                    if threshold > 0.0 && stats.count >= min_samples && stats.converged(threshold) {
+                        break;
+                    }
+                    let s = (x as f32 + rng.next_f32()) / width;
+                    let t = (y as f32 + rng.next_f32()) / height;
+                    let ray = camera.get_ray(s, t, rng.next_2d(), rng.next_f32());
+                    stats.add(tracer.radiance(world, &ray, &mut rng));
+                }
+                pixels.push(stats);
+            }
+        }
+        pixels
+    }
+
+    /// Render the albedo and shading normal of the first surface seen through every pixel,
+    /// averaged over a few samples so that edges are antialiased like the image. Pixels that
+    /// see nothing are black.
+    pub fn render_guides(&self, world: &World, camera: &Camera) -> Guides {
+        let mut images = self.render_aovs(world, camera, &[Aov::Albedo, Aov::Normal]);
+        let normal = images.pop().unwrap();
+        let albedo = images.pop().unwrap();
+        Guides { albedo, normal }
+    }
+
+    /// Render an image of raw values for each of `aovs`, in the same order, averaging a few
+    /// jittered camera rays per pixel where the AOV allows it.
+    pub fn render_aovs(&self, world: &World, camera: &Camera, aovs: &[Aov]) -> Vec<Framebuffer> {
+        const SAMPLES: u32 = 4;
+        let width = self.settings.width as usize;
+        let height = self.settings.height as usize;
+
+        let rows: Vec<Vec<Vec<Color>>> = self.install(|| {
+            (0..height)
+                .into_par_iter()
+                .map(|y| {
+                    // Keep clear of the streams used by render passes.
+                    let mut rng = Pcg32::new(self.settings.seed, u64::MAX - y as u64);
+                    (0..width)
+                        .map(|x| {
+                            let mut values = vec![Color::zeros(); aovs.len()];
+                            for sample in 0..SAMPLES {
+                                let s = (x as f32 + rng.next_f32()) / width as f32;
+                                let t = (y as f32 + rng.next_f32()) / height as f32;
+                                let ray = camera.get_ray(s, t, rng.next_2d(), rng.next_f32());
+                                let mut stats = TraversalStats::default();
+                                let hit = world.hit_counted(&ray, &mut stats);
+                                for (value, aov) in values.iter_mut().zip(aovs) {
+                                    if aov.filtered() {
+                                        *value += aov.value(world, &ray, hit.as_ref(), &stats)
+                                            / SAMPLES as f32;
+                                    } else if sample == 0 {
+                                        *value = aov.value(world, &ray, hit.as_ref(), &stats);
+                                    }
+                                }
+                            }
+                            values
+                        })
+                        .collect()
+                })
+                .collect()
+        });
+
+        (0..aovs.len())
+            .map(|i| Framebuffer::from_fn(width, height, |x, y| rows[y][x][i]))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::camera::CameraSettings;
+    use crate::hittable::{HittableList, MaterialId, Tagged};
+    use crate::material::{DiffuseLight, Lambertian, Material};
+    use crate::shapes::{Plane, Sphere};
+    use crate::vector::Vector3;
+    use std::sync::Mutex;
+
+    fn test_world() -> World {
+        let mut objects = HittableList::new();
+        let floor = Plane::new(Vector3::zeros(), Vector3::new(0.0, 1.0, 0.0), MaterialId(0));
+        objects.add(Tagged::new(floor, 0));
+        let ball = Sphere::new(Vector3::new(0.0, 1.0, 0.0), 1.0, MaterialId(0));
+        objects.add(Tagged::new(ball, 1));
+        let light = Sphere::new(Vector3::new(0.0, 6.0, 0.0), 3.0, MaterialId(1));
+        objects.add(Tagged::new(light, 2));
+        let materials: Vec<Box<dyn Material>> = vec![
+            Box::new(Lambertian::new(Color::new(0.7, 0.5, 0.3))),
+            Box::new(DiffuseLight::new(Color::new(4.0, 4.0, 4.0))),
+        ];
+        World::new(objects, materials)
+    }
+
+    fn test_settings() -> RenderSettings {
+        RenderSettings {
+            width: 37,
+            height: 23,
+            samples_per_pixel: 4,
+            max_depth: 4,
+            roulette_depth: 3,
+            seed: 1,
+            ..RenderSettings::default()
+        }
+    }
+
+    fn test_camera(settings: &RenderSettings) -> Camera {
+        let camera = CameraSettings::new(Vector3::new(0.0, 2.0, 6.0), Vector3::zeros(), 50.0);
+        Camera::new(&camera, settings.width as f32 / settings.height as f32)
+    }
+
+    #[test]
+    fn test_tiles_cover_image() {
+        let renderer = Renderer::new(test_settings()).with_tile_size(8);
+        let tiles = renderer.tiles();
+        assert_eq!(5 * 3, tiles.len());
+        let area: usize = tiles.iter().map(|t| t.width * t.height).sum();
+        assert_eq!(37 * 23, area);
+        let last = tiles.last().unwrap();
+        assert_eq!((32, 16, 5, 7), (last.x0, last.y0, last.width, last.height));
+    }
+
+    #[test]
+    fn test_deterministic_across_threads() {
+        let world = test_world();
+        let settings = test_settings();
+        let camera = test_camera(&settings);
+
+        let single = Renderer::new(settings.clone())
+            .with_threads(1)
+            .unwrap()
+            .render(&world, &camera);
+        let multi = Renderer::new(settings.clone())
+            .with_threads(4)
+            .unwrap()
+            .render(&world, &camera);
+        assert!(single == multi, "Render differs between 1 and 4 threads.");
+
+        let reseeded = Renderer::new(RenderSettings {
+            seed: 2,
+            ..settings
+        })
+        .with_threads(4)
+        .unwrap()
+        .render(&world, &camera);
+        assert!(single != reseeded, "Render did not depend on the seed.");
+
+        // Something in the image must be lit.
+        assert!(single.pixels().iter().any(|c| c.norm() > 0.0));
+    }
+
+    #[test]
+    fn test_resume() {
+        let world = test_world();
+        let settings = test_settings();
+        let camera = test_camera(&settings);
+        let renderer = Renderer::new(settings).with_threads(2).unwrap();
+
+        let mut straight = renderer.accumulator();
+        for _ in 0..3 {
+            renderer.render_pass(&world, &camera, &mut straight, 2);
+        }
+        assert_eq!((3, 6), (straight.passes(), straight.samples()));
+
+        // Stop after one pass, save, and pick up where we left off.
+        let mut partial = renderer.accumulator();
+        renderer.render_pass(&world, &camera, &mut partial, 2);
+        let mut bytes = Vec::new();
+        partial.write(&mut bytes).unwrap();
+        let mut resumed = Accumulator::read(&bytes[..]).unwrap();
+        for _ in 0..2 {
+            renderer.render_pass(&world, &camera, &mut resumed, 2);
+        }
+        assert!(straight == resumed, "Resumed render differs.");
+
+        // Every pass draws new samples.
+        let mut first = renderer.accumulator();
+        renderer.render_pass(&world, &camera, &mut first, 2);
+        assert!(first.image() != straight.image());
+    }
+
+    #[test]
+    fn test_adaptive_sampling() {
+        let world = test_world();
+        let settings = RenderSettings {
+            samples_per_pixel: 64,
+            adaptive_threshold: 0.05,
+            min_samples: 8,
+            ..test_settings()
+        };
+        let camera = test_camera(&settings);
+        let renderer = Renderer::new(settings).with_threads(2).unwrap();
+        let mut accumulator = renderer.accumulator();
+        renderer.render_pass(&world, &camera, &mut accumulator, 64);
+
+        // Pixels that see only the black background converge as soon as they may, while noisy
+        // ones keep sampling.
+        let counts: Vec<u32> = accumulator.pixels().iter().map(|p| p.count).collect();
+        assert_eq!(8, accumulator.pixel(0, 0).count);
+        assert!(counts.iter().all(|&count| (8..=64).contains(&count)));
+        assert!(counts.contains(&64), "No pixel used every sample.");
+
+        let guides = renderer.render_guides(&world, &camera);
+        assert_eq!(Color::zeros(), guides.normal.get(0, 0));
+        let center = guides.albedo.get(18, 14);
+        assert!(
+            (center - Color::new(0.7, 0.5, 0.3)).norm() < 1e-6,
+            "Expected the sphere's albedo, got {}.",
+            center
+        );
+    }
+
+    #[test]
+    fn test_aovs() {
+        let world = test_world();
+        let settings = test_settings();
+        let camera = test_camera(&settings);
+        let aovs = [Aov::ObjectId, Aov::Depth, Aov::Cost, Aov::Hits];
+        let images = Renderer::new(settings).render_aovs(&world, &camera, &aovs);
+        assert_eq!(4, images.len());
+        let [ids, depth, cost, hits] = &images[..] else {
+            unreachable!()
+        };
+
+        // The corner sees the background; the center sees the ball.
+        assert_eq!(Color::zeros(), ids.get(0, 0));
+        assert_eq!(Color::zeros(), depth.get(0, 0));
+        assert_eq!(Color::zeros(), hits.get(0, 0));
+        assert_eq!(Color::ones() * 2.0, ids.get(18, 7));
+        let distance = depth.get(18, 7).x();
+        assert!(
+            (4.0..6.0).contains(&distance),
+            "Expected the ball about 5 units away, got {}.",
+            distance
+        );
+        assert!(hits.get(18, 7).x() >= 1.0);
+        // A list tests every object against every ray.
+        assert!(cost.pixels().iter().all(|&c| c == Color::ones() * 3.0));
+    }
+
+    #[test]
+    fn test_progress() {
+        let world = test_world();
+        let settings = test_settings();
+        let camera = test_camera(&settings);
+        let reports = Mutex::new(Vec::new());
+
+        let renderer = Renderer::new(settings)
+            .with_tile_size(8)
+            .with_threads(3)
+            .unwrap();
+        assert_eq!(3, renderer.threads());
+        assert_eq!(3, renderer.install(rayon::current_num_threads));
+        renderer.render_with_progress(&world, &camera, |p| reports.lock().unwrap().push(p));
+
+        let mut reports = reports.into_inner().unwrap();
+        reports.sort_by_key(|p| p.tiles_done);
+        assert_eq!(15, reports.len());
+        assert!(reports.iter().all(|p| p.tiles_total == 15));
+        assert_eq!(1.0, reports.last().unwrap().fraction());
+    }
+}
@@ -0,0 +1,184 @@
+/// A small, fast, deterministic random number generator: O'Neill's PCG32 (XSH-RR variant).
+///
+/// Generators built with the same `seed` and `stream` always produce the same sequence, and
+/// different streams are statistically independent, which keeps renders reproducible.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pcg32 {
+    state: u64,
+    inc: u64,
+}
+
+const MULTIPLIER: u64 = 6364136223846793005;
+
+impl Pcg32 {
+    /// Create a new generator from a `seed` and a `stream` selector.
+    pub fn new(seed: u64, stream: u64) -> Self {
+        let mut rng = Pcg32 {
+            state: 0,
+            inc: (stream << 1) | 1,
+        };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    /// Return the next uniformly distributed `u32`.
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULTIPLIER).wrapping_add(self.inc);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    /// Return the next uniformly distributed `u64`, made of two consecutive `u32`s.
+    pub fn next_u64(&mut self) -> u64 {
+        let high = self.next_u32() as u64;
+        high << 32 | self.next_u32() as u64
+    }
+
+    /// Return the next uniformly distributed `u32` in `[0, bound)`. `bound` must be positive.
+    pub fn next_bounded(&mut self, bound: u32) -> u32 {
+        // Reject the lowest 2^32 mod bound values, so that every remainder is equally likely.
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let x = self.next_u32();
+            if x >= threshold {
+                return x % bound;
+            }
+        }
+    }
+
+    /// Return the next uniformly distributed `f32` in `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        // Use the top 24 bits so that every value is exactly representable.
+        (self.next_u32() >> 8) as f32 * (1.0 / (1u32 << 24) as f32)
+    }
+
+    /// Return the next uniformly distributed `f32` in `[low, high)`.
+    pub fn next_range(&mut self, low: f32, high: f32) -> f32 {
+        low + (high - low) * self.next_f32()
+    }
+
+    /// Return a uniformly distributed pair of `f32`s in `[0, 1)`.
+    pub fn next_2d(&mut self) -> [f32; 2] {
+        [self.next_f32(), self.next_f32()]
+    }
+
+    /// Skip ahead `delta` outputs in logarithmic time, as if `next_u32` had been called `delta`
+    /// times. Skipping `2^64 - n` goes back `n` outputs.
+    pub fn advance(&mut self, delta: u64) {
+        // Brown, "Random Number Generation with Arbitrary Strides": compose the affine step
+        // state * MULTIPLIER + inc with itself by repeated squaring.
+        let (mut mul, mut add) = (MULTIPLIER, self.inc);
+        let (mut acc_mul, mut acc_add) = (1u64, 0u64);
+        let mut delta = delta;
+        while delta > 0 {
+            if delta & 1 == 1 {
+                acc_mul = acc_mul.wrapping_mul(mul);
+                acc_add = acc_add.wrapping_mul(mul).wrapping_add(add);
+            }
+            add = mul.wrapping_add(1).wrapping_mul(add);
+            mul = mul.wrapping_mul(mul);
+            delta >>= 1;
+        }
+        self.state = acc_mul.wrapping_mul(self.state).wrapping_add(acc_add);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sampling::chi_squared;
+
+    #[test]
+    fn test_reference_sequence() {
+        // Output of the reference pcg32-demo with seed 42 and stream 54.
+        let expected = [
+            0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e,
+        ];
+        let mut rng = Pcg32::new(42, 54);
+        for &e in &expected {
+            let x = rng.next_u32();
+            assert_eq!(e, x, "Pcg32 diverged: expected {:#x}, got {:#x}.", e, x);
+        }
+    }
+
+    #[test]
+    fn test_f32_range() {
+        let mut rng = Pcg32::new(1, 2);
+        let mut sum = 0.0;
+        for _ in 0..10000 {
+            let x = rng.next_f32();
+            assert!((0.0..1.0).contains(&x), "next_f32() returned {}.", x);
+            sum += x;
+        }
+        let mean = sum / 10000.0;
+        assert!((mean - 0.5).abs() < 0.02, "Mean {} is far from 0.5.", mean);
+
+        for _ in 0..1000 {
+            let x = rng.next_range(-2.0, 3.0);
+            assert!((-2.0..3.0).contains(&x), "next_range() returned {}.", x);
+        }
+    }
+
+    #[test]
+    fn test_uniformity() {
+        // With 15 degrees of freedom, chi-squared exceeds 37.7 with probability 0.001.
+        let mut rng = Pcg32::new(11, 3);
+        let mut counts = [0; 16];
+        for _ in 0..64000 {
+            counts[(rng.next_f32() * 16.0) as usize] += 1;
+        }
+        let statistic = chi_squared(&counts);
+        assert!(
+            statistic < 37.7,
+            "next_f32() gave chi-squared {}.",
+            statistic
+        );
+
+        // A bound that does not divide 2^32 must not favor small values; 6 bins leave 5
+        // degrees of freedom, for which the 0.001 threshold is 20.5.
+        let mut counts = [0; 6];
+        for _ in 0..60000 {
+            counts[rng.next_bounded(6) as usize] += 1;
+        }
+        let statistic = chi_squared(&counts);
+        assert!(
+            statistic < 20.5,
+            "next_bounded() gave chi-squared {}.",
+            statistic
+        );
+        assert_eq!(0, rng.next_bounded(1));
+
+        // Consecutive pairs are independent: 4 by 4 cells leave 15 degrees of freedom.
+        let mut counts = [0; 16];
+        for _ in 0..64000 {
+            let [a, b] = rng.next_2d();
+            counts[(a * 4.0) as usize * 4 + (b * 4.0) as usize] += 1;
+        }
+        let statistic = chi_squared(&counts);
+        assert!(
+            statistic < 37.7,
+            "next_2d() gave chi-squared {}.",
+            statistic
+        );
+    }
+
+    #[test]
+    fn test_advance() {
+        let mut stepped = Pcg32::new(42, 54);
+        let mut skipped = stepped.clone();
+        for _ in 0..1000 {
+            stepped.next_u32();
+        }
+        skipped.advance(1000);
+        assert_eq!(stepped, skipped);
+
+        // Going back is skipping forward almost a whole period.
+        let expected = stepped.next_u64();
+        stepped.advance(2u64.wrapping_neg());
+        assert_eq!(expected, stepped.next_u64());
+    }
+}
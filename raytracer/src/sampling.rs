@@ -0,0 +1,542 @@
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+use crate::rng::Pcg32;
+use crate::vector::Vector3;
+
+/// The largest `f32` below one, which keeps samples in `[0, 1)` despite rounding.
+const ONE_MINUS_EPSILON: f32 = 1.0 - f32::EPSILON / 2.0;
+
+/// An orthonormal basis, used to map directions sampled around the `z` axis onto a surface.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Onb {
+    pub u: Vector3,
+    pub v: Vector3,
+    pub w: Vector3,
+}
+
+impl Onb {
+    /// Create a new right-handed `Onb` whose `w` axis is the unit vector `w`.
+    pub fn from_w(w: Vector3) -> Self {
+        // Duff et al., "Building an Orthonormal Basis, Revisited".
+        let sign = 1f32.copysign(w.z());
+        let a = -1.0 / (sign + w.z());
+        let b = w.x() * w.y() * a;
+        let u = Vector3::new(1.0 + sign * w.x() * w.x() * a, sign * b, -sign * w.x());
+        let v = Vector3::new(b, sign + w.y() * w.y() * a, -w.y());
+        Onb { u, v, w }
+    }
+
+    /// Map `local`, expressed in this basis, to world space.
+    pub fn to_world(&self, local: Vector3) -> Vector3 {
+        local.x() * self.u + local.y() * self.v + local.z() * self.w
+    }
+
+    /// Express the world-space vector `world` in this basis.
+    pub fn to_local(&self, world: Vector3) -> Vector3 {
+        Vector3::new(world.dot(self.u), world.dot(self.v), world.dot(self.w))
+    }
+}
+
+/// Map a uniform sample in `[0, 1)^2` to a uniform point on the unit disk in the `xy` plane,
+/// using Shirley and Chiu's concentric mapping so that stratified samples stay well distributed.
+pub fn concentric_disk([a, b]: [f32; 2]) -> Vector3 {
+    let a = 2.0 * a - 1.0;
+    let b = 2.0 * b - 1.0;
+    if a == 0.0 && b == 0.0 {
+        return Vector3::zeros();
+    }
+
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, FRAC_PI_4 * (b / a))
+    } else {
+        (b, FRAC_PI_2 - FRAC_PI_4 * (a / b))
+    };
+    Vector3::new(r * theta.cos(), r * theta.sin(), 0.0)
+}
+
+/// Map a uniform sample in `[0, 1)^3` to a uniform point inside the unit ball.
+pub fn uniform_ball([a, b, c]: [f32; 3]) -> Vector3 {
+    // The fraction of the ball's volume within radius r is r^3.
+    uniform_sphere([a, b]) * c.cbrt()
+}
+
+/// Map a uniform sample in `[0, 1)^2` to a uniform direction in the hemisphere around `+z`. Its
+/// density in solid angle is `1 / (2 pi)`.
+pub fn uniform_hemisphere([a, b]: [f32; 2]) -> Vector3 {
+    let z = 1.0 - a;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * b;
+    Vector3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Map a uniform sample in `[0, 1)^2` to a cosine-weighted direction in the hemisphere around
+/// `+z`. Its density in solid angle is `cos(theta) / pi`.
+pub fn cosine_hemisphere(sample: [f32; 2]) -> Vector3 {
+    // Malley's method: project a uniform disk sample up onto the hemisphere.
+    let d = concentric_disk(sample);
+    let z = (1.0 - d.squared_norm()).max(0.0).sqrt();
+    Vector3::new(d.x(), d.y(), z)
+}
+
+/// Map a uniform sample in `[0, 1)^2` to a uniform direction on the unit sphere. Its density in
+/// solid angle is `1 / (4 pi)`.
+pub fn uniform_sphere([a, b]: [f32; 2]) -> Vector3 {
+    let z = 1.0 - 2.0 * a;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * b;
+    Vector3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Map a uniform sample in `[0, 1)^2` to a uniform direction in the cone around `+z` whose
+/// half-angle has cosine `cos_max`. Its density in solid angle is `1 / (2 pi (1 - cos_max))`.
+pub fn uniform_cone(cos_max: f32, [a, b]: [f32; 2]) -> Vector3 {
+    let z = 1.0 - a * (1.0 - cos_max);
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * b;
+    Vector3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Map a uniform sample in `[0, 1)^2` to uniformly distributed barycentric coordinates `(u, v)`
+/// on a triangle, weighting the second and third vertices.
+pub fn uniform_triangle([a, b]: [f32; 2]) -> [f32; 2] {
+    let s = a.sqrt();
+    [s * (1.0 - b), s * b]
+}
+
+/// Return `n` samples in `[0, 1)`, one jittered uniformly within each of `n` equal strata, in
+/// order.
+pub fn stratified_1d(n: usize, rng: &mut Pcg32) -> Vec<f32> {
+    (0..n)
+        .map(|i| ((i as f32 + rng.next_f32()) / n as f32).min(ONE_MINUS_EPSILON))
+        .collect()
+}
+
+/// Return `nx * ny` samples in `[0, 1)^2`, one jittered uniformly within each cell of an `nx` by
+/// `ny` grid, with the first coordinate varying fastest.
+pub fn stratified_2d(nx: usize, ny: usize, rng: &mut Pcg32) -> Vec<[f32; 2]> {
+    let mut samples = Vec::with_capacity(nx * ny);
+    for y in 0..ny {
+        for x in 0..nx {
+            let [a, b] = rng.next_2d();
+            samples.push([
+                ((x as f32 + a) / nx as f32).min(ONE_MINUS_EPSILON),
+                ((y as f32 + b) / ny as f32).min(ONE_MINUS_EPSILON),
+            ]);
+        }
+    }
+    samples
+}
+
+/// The bases of the dimensions of the Halton sequence.
+const PRIMES: [u32; 16] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53];
+
+/// Return the digits of `index` in `base`, mirrored about the radix point: the van der Corput
+/// sequence in that base.
+pub fn radical_inverse(base: u32, index: u64) -> f32 {
+    let base = base as u64;
+    let (mut index, mut reversed, mut scale) = (index, 0u64, 1.0f64);
+    while index > 0 {
+        reversed = reversed * base + index % base;
+        scale /= base as f64;
+        index /= base;
+    }
+    ((reversed as f64 * scale) as f32).min(ONE_MINUS_EPSILON)
+}
+
+/// Return coordinate `dimension` of point `index` of the Halton sequence, a low-discrepancy
+/// sequence whose successive points fill `[0, 1)^d` evenly. Supports up to 16 dimensions; the
+/// later ones need many points before they look uniform together.
+pub fn halton(dimension: usize, index: u64) -> f32 {
+    radical_inverse(PRIMES[dimension], index)
+}
+
+/// Return point `index` of the two-dimensional Sobol sequence, XORed with `scramble`. Every
+/// aligned block of `2^m` points has one point in each cell of every grid of `2^m` equal
+/// rectangles, and scrambling with random bits keeps that while decorrelating sequences.
+pub fn sobol_2d(index: u32, scramble: [u32; 2]) -> [f32; 2] {
+    // The first dimension reverses the bits of the index; the second multiplies them by the
+    // generator matrix whose columns are successive `v ^= v >> 1`.
+    let mut v = 1u32 << 31;
+    let mut second = 0;
+    let mut bits = index;
+    while bits != 0 {
+        if bits & 1 == 1 {
+            second ^= v;
+        }
+        bits >>= 1;
+        v ^= v >> 1;
+    }
+    let to_f32 = |x: u32| (x >> 8) as f32 * (1.0 / (1u32 << 24) as f32);
+    [
+        to_f32(index.reverse_bits() ^ scramble[0]),
+        to_f32(second ^ scramble[1]),
+    ]
+}
+
+/// A piecewise-constant density over `[0, 1)`, proportional to a function given by its values on
+/// equal steps.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Distribution1D {
+    func: Vec<f32>,
+    cdf: Vec<f32>,
+    integral: f32,
+}
+
+impl Distribution1D {
+    /// Create a new `Distribution1D` proportional to `func`, which must be non-empty and
+    /// non-negative. A function that is zero everywhere gives a uniform density.
+    pub fn new(func: Vec<f32>) -> Self {
+        let n = func.len();
+        let mut cdf = Vec::with_capacity(n + 1);
+        cdf.push(0.0);
+        for (i, f) in func.iter().enumerate() {
+            cdf.push(cdf[i] + f / n as f32);
+        }
+        let integral = cdf[n];
+        for (i, c) in cdf.iter_mut().enumerate() {
+            *c = if integral > 0.0 {
+                *c / integral
+            } else {
+                i as f32 / n as f32
+            };
+        }
+        Distribution1D {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    /// Return the number of steps.
+    pub fn len(&self) -> usize {
+        self.func.len()
+    }
+
+    /// Return whether there are no steps, which `new` does not allow.
+    pub fn is_empty(&self) -> bool {
+        self.func.is_empty()
+    }
+
+    /// Return the average value of the function.
+    pub fn integral(&self) -> f32 {
+        self.integral
+    }
+
+    /// Return the index of the step containing `x`.
+    fn offset(&self, x: f32) -> usize {
+        ((x * self.len() as f32) as usize).min(self.len() - 1)
+    }
+
+    /// Map a uniform sample in `[0, 1)` to a point in `[0, 1)` distributed with this density.
+    /// Returns the point and the index of its step.
+    pub fn sample(&self, u: f32) -> (f32, usize) {
+        let i = (self.cdf.partition_point(|&c| c <= u) - 1).min(self.len() - 1);
+        let width = self.cdf[i + 1] - self.cdf[i];
+        let within = if width > 0.0 {
+            (u - self.cdf[i]) / width
+        } else {
+            0.0
+        };
+        let x = (i as f32 + within) / self.len() as f32;
+        (x.min(ONE_MINUS_EPSILON), i)
+    }
+
+    /// Return the density at `x`.
+    pub fn pdf(&self, x: f32) -> f32 {
+        if self.integral > 0.0 {
+            self.func[self.offset(x)] / self.integral
+        } else {
+            1.0
+        }
+    }
+}
+
+/// A piecewise-constant density over `[0, 1)^2`, proportional to a function given by its values
+/// on a grid of equal cells. Samples pick a row by its total first, then a cell within it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// Create a new `Distribution2D` proportional to `func`, which holds `width` non-negative
+    /// values for each row, with the first coordinate varying fastest.
+    pub fn new(func: &[f32], width: usize) -> Self {
+        let rows: Vec<Distribution1D> = func
+            .chunks_exact(width)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(rows.iter().map(|row| row.integral()).collect());
+        Distribution2D { rows, marginal }
+    }
+
+    /// Map a uniform sample in `[0, 1)^2` to a point in `[0, 1)^2` distributed with this
+    /// density.
+    pub fn sample(&self, [a, b]: [f32; 2]) -> [f32; 2] {
+        let (v, row) = self.marginal.sample(b);
+        let (u, _) = self.rows[row].sample(a);
+        [u, v]
+    }
+
+    /// Return the density at `[u, v]`.
+    pub fn pdf(&self, [u, v]: [f32; 2]) -> f32 {
+        let row = self.marginal.offset(v);
+        self.marginal.pdf(v) * self.rows[row].pdf(u)
+    }
+}
+
+/// Veach's power heuristic (with exponent 2) for weighting a sample drawn with density `pdf`
+/// against one other strategy with density `other_pdf`.
+pub fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b == 0.0 {
+        0.0
+    } else {
+        a / (a + b)
+    }
+}
+
+/// Return Pearson's chi-squared statistic for `counts` against equally likely bins.
+#[cfg(test)]
+pub(crate) fn chi_squared(counts: &[u32]) -> f32 {
+    let total: u32 = counts.iter().sum();
+    let expected = total as f32 / counts.len() as f32;
+    counts
+        .iter()
+        .map(|&c| (c as f32 - expected).powi(2) / expected)
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Return the index of the octant containing `p`.
+    fn octant(p: Vector3) -> usize {
+        (p.x() > 0.0) as usize | ((p.y() > 0.0) as usize) << 1 | ((p.z() > 0.0) as usize) << 2
+    }
+
+    #[test]
+    fn test_onb() {
+        for w in [
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            Vector3::unit(1.0, -2.0, 0.5),
+        ] {
+            let onb = Onb::from_w(w);
+            assert!(onb.u.dot(onb.v).abs() < 1e-6 && onb.u.dot(onb.w).abs() < 1e-6);
+            assert!((onb.u.norm() - 1.0).abs() < 1e-6 && (onb.v.norm() - 1.0).abs() < 1e-6);
+            assert!(
+                onb.u.cross(onb.v).approx_eq(onb.w, 1e-6),
+                "Basis is not right-handed."
+            );
+            assert!(onb.to_world(Vector3::new(0.0, 0.0, 1.0)).approx_eq(w, 1e-6));
+        }
+    }
+
+    #[test]
+    fn test_sphere_and_ball() {
+        // Points fall equally into the eight octants; with 7 degrees of freedom, chi-squared
+        // exceeds 24.3 with probability 0.001.
+        let mut rng = Pcg32::new(1, 7);
+        let n = 40000;
+        let mut octants = [0; 8];
+        let mut z_squared = 0.0;
+        for _ in 0..n {
+            let d = uniform_sphere(rng.next_2d());
+            assert!((d.norm() - 1.0).abs() < 1e-5);
+            octants[octant(d)] += 1;
+            z_squared += d.z() * d.z() / n as f32;
+        }
+        let statistic = chi_squared(&octants);
+        assert!(statistic < 24.3, "Octants gave chi-squared {}.", statistic);
+        assert!(
+            (z_squared - 1.0 / 3.0).abs() < 0.01,
+            "E[z^2] {} is far from 1/3.",
+            z_squared
+        );
+
+        // An eighth of the ball lies within half its radius.
+        let mut octants = [0; 8];
+        let mut inner = 0;
+        for _ in 0..n {
+            let p = uniform_ball([rng.next_f32(), rng.next_f32(), rng.next_f32()]);
+            assert!(p.norm() <= 1.0 + 1e-6);
+            octants[octant(p)] += 1;
+            inner += (p.norm() < 0.5) as u32;
+        }
+        let statistic = chi_squared(&octants);
+        assert!(statistic < 24.3, "Octants gave chi-squared {}.", statistic);
+        let inner = inner as f32 / n as f32;
+        assert!(
+            (inner - 0.125).abs() < 0.01,
+            "{} of points were inner.",
+            inner
+        );
+    }
+
+    #[test]
+    fn test_disk_and_hemisphere() {
+        for a in 0..10 {
+            for b in 0..10 {
+                let p = concentric_disk([a as f32 / 10.0, b as f32 / 10.0]);
+                assert!(p.norm() <= 1.0 + 1e-6, "{} is outside the unit disk.", p);
+                assert_eq!(0.0, p.z());
+            }
+        }
+        assert_eq!(Vector3::zeros(), concentric_disk([0.5, 0.5]));
+
+        // Four rings of equal area, each split into four quadrants, hold equally many points;
+        // with 15 degrees of freedom, chi-squared exceeds 37.7 with probability 0.001.
+        let mut rng = Pcg32::new(2, 7);
+        let mut cells = [0; 16];
+        for _ in 0..32000 {
+            let p = concentric_disk(rng.next_2d());
+            let ring = ((p.squared_norm() * 4.0) as usize).min(3);
+            cells[ring * 4 + octant(p) % 4] += 1;
+        }
+        let statistic = chi_squared(&cells);
+        assert!(
+            statistic < 37.7,
+            "Disk cells gave chi-squared {}.",
+            statistic
+        );
+
+        // E[cos(theta)] over the uniform hemisphere is 1/2.
+        let n = 20000;
+        let mut octants = [0; 8];
+        let mut mean = 0.0;
+        for _ in 0..n {
+            let d = uniform_hemisphere(rng.next_2d());
+            assert!(d.z() >= 0.0 && (d.norm() - 1.0).abs() < 1e-5);
+            octants[octant(d)] += 1;
+            mean += d.z() / n as f32;
+        }
+        assert!(octants[..4].iter().all(|&c| c == 0));
+        assert!((mean - 0.5).abs() < 0.01, "Mean cosine {}.", mean);
+    }
+
+    #[test]
+    fn test_stratified() {
+        let mut rng = Pcg32::new(6, 0);
+        let samples = stratified_1d(8, &mut rng);
+        for (i, x) in samples.iter().enumerate() {
+            assert_eq!(i, (x * 8.0) as usize, "Sample {} left its stratum.", x);
+        }
+        let samples = stratified_2d(4, 3, &mut rng);
+        assert_eq!(12, samples.len());
+        for (i, [a, b]) in samples.iter().enumerate() {
+            assert_eq!((i % 4, i / 4), ((a * 4.0) as usize, (b * 3.0) as usize));
+        }
+    }
+
+    #[test]
+    fn test_low_discrepancy() {
+        let base_2: Vec<f32> = (0..4).map(|i| halton(0, i)).collect();
+        assert_eq!(vec![0.0, 0.5, 0.25, 0.75], base_2);
+        assert!((halton(1, 5) - 7.0 / 9.0).abs() < 1e-6);
+        assert!((radical_inverse(10, 123) - 0.321).abs() < 1e-6);
+
+        // Every aligned block of 16 Sobol points has one point in each cell of every grid of
+        // 16 equal cells, scrambled or not.
+        let mut rng = Pcg32::new(8, 0);
+        for scramble in [[0, 0], [rng.next_u32(), rng.next_u32()]] {
+            for block in 0..4 {
+                for m in 0..=4 {
+                    let (nx, ny) = (1 << m, 1 << (4 - m));
+                    let mut cells = [0; 16];
+                    for i in 0..16 {
+                        let [a, b] = sobol_2d(block * 16 + i, scramble);
+                        cells[(a * nx as f32) as usize + nx * (b * ny as f32) as usize] += 1;
+                    }
+                    assert!(
+                        cells.iter().all(|&c| c == 1),
+                        "Sobol block {} with {:?} missed a cell of the {} by {} grid.",
+                        block,
+                        scramble,
+                        nx,
+                        ny
+                    );
+                }
+            }
+        }
+
+        // The first 18 Halton points in bases 2 and 3 have one point in each cell of a 2 by 9
+        // grid, since 18 = 2 * 9.
+        let mut cells = [0; 18];
+        for i in 0..18 {
+            cells[(halton(0, i) * 2.0) as usize * 9 + (halton(1, i) * 9.0) as usize] += 1;
+        }
+        assert!(cells.iter().all(|&c| c == 1), "Got {:?}.", cells);
+    }
+
+    #[test]
+    fn test_cosine_hemisphere() {
+        // E[cos(theta)] under a cosine-weighted density is 2/3.
+        let mut rng = Pcg32::new(3, 0);
+        let n = 20000;
+        let mut mean = 0.0;
+        for _ in 0..n {
+            let d = cosine_hemisphere(rng.next_2d());
+            assert!(d.z() >= 0.0 && (d.norm() - 1.0).abs() < 1e-5);
+            mean += d.z() / n as f32;
+        }
+        assert!((mean - 2.0 / 3.0).abs() < 0.01, "Mean cosine {}.", mean);
+    }
+
+    #[test]
+    fn test_distributions() {
+        let d = Distribution1D::new(vec![1.0, 0.0, 3.0, 0.0]);
+        assert_eq!(1.0, d.integral());
+        assert_eq!(0.0, d.pdf(0.3));
+        assert_eq!(3.0, d.pdf(0.6));
+        // A quarter of the mass lies in the first step, and the rest in the third.
+        assert_eq!((0.125, 0), d.sample(0.125));
+        assert_eq!((0.5, 2), d.sample(0.25));
+        assert_eq!((0.625, 2), d.sample(0.625));
+        let flat = Distribution1D::new(vec![0.0; 4]);
+        assert_eq!((0.3, 1), flat.sample(0.3));
+        assert_eq!(1.0, flat.pdf(0.3));
+
+        // Samples land in each cell in proportion to its value, and the density agrees.
+        let func = [0.0, 1.0, 2.0, 3.0, 0.0, 2.0];
+        let d = Distribution2D::new(&func, 3);
+        let mut rng = Pcg32::new(5, 0);
+        let n = 80000;
+        let mut counts = [0; 6];
+        for _ in 0..n {
+            let [u, v] = d.sample(rng.next_2d());
+            assert!((0.0..1.0).contains(&u) && (0.0..1.0).contains(&v));
+            let cell = (u * 3.0) as usize + 3 * (v * 2.0) as usize;
+            counts[cell] += 1;
+            assert!((d.pdf([u, v]) - func[cell] * 6.0 / 8.0).abs() < 1e-5);
+        }
+        for (count, f) in counts.iter().zip(func) {
+            let expected = f / 8.0;
+            let got = *count as f32 / n as f32;
+            assert!(
+                (got - expected).abs() < 0.01,
+                "Expected {}, got {}.",
+                expected,
+                got
+            );
+        }
+    }
+
+    #[test]
+    fn test_cone_and_triangle() {
+        let mut rng = Pcg32::new(4, 0);
+        for _ in 0..1000 {
+            let d = uniform_cone(0.9, rng.next_2d());
+            assert!(d.z() >= 0.9 - 1e-6 && (d.norm() - 1.0).abs() < 1e-5);
+
+            let [u, v] = uniform_triangle(rng.next_2d());
+            assert!(u >= 0.0 && v >= 0.0 && u + v <= 1.0 + 1e-6);
+        }
+
+        assert_eq!(0.5, power_heuristic(1.0, 1.0));
+        assert_eq!(1.0, power_heuristic(1.0, 0.0));
+        assert_eq!(0.0, power_heuristic(0.0, 0.0));
+    }
+}
@@ -0,0 +1,2271 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::aabb::Aabb;
+use crate::animation::{Animation, MotionKey, Moving};
+use crate::aov::Aov;
+use crate::bvh::Bvh;
+use crate::camera::{Camera, CameraSettings};
+use crate::csg::{Csg, CsgOp};
+use crate::environment::{Environment, EnvironmentMap};
+use crate::framebuffer::{Framebuffer, ImageError};
+use crate::graph::Node;
+use crate::hittable::{Hittable, MaterialId, Tagged};
+use crate::light::{AreaLight, DirectionalLight, Light, PointLight, SpotLight};
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Medium, Metal};
+use crate::obj::load_obj;
+use crate::shapes::{Cone, Cuboid, Cylinder, Disk, Plane, Quadric, Rect, Sphere, Torus};
+use crate::texture::{
+    Checker, ImageTexture, NoisePattern, NoiseTexture, Scaled, SolidColor, Texture,
+};
+use crate::transform::{Instance, Transform};
+use crate::vector::Vector3;
+use crate::volume::{DensityGrid, PhaseFunction, Volume};
+use crate::world::World;
+
+/// An error produced while loading, validating, or saving a scene description.
+#[derive(Debug)]
+pub enum SceneError {
+    /// The scene file could not be read or written.
+    Io(std::io::Error),
+    /// The JSON was malformed or did not match the scene schema.
+    Parse {
+        path: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// The JSON was well-formed but described an invalid scene.
+    Invalid { path: String, message: String },
+}
+
+impl SceneError {
+    fn invalid(path: impl Into<String>, message: impl Into<String>) -> Self {
+        SceneError::Invalid {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Return the JSON path of the offending value, if the error refers to one.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            SceneError::Io(_) => None,
+            SceneError::Parse { path, .. } | SceneError::Invalid { path, .. } => Some(path),
+        }
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "{}", err),
+            SceneError::Parse {
+                path,
+                line,
+                column,
+                message,
+            } => write!(
+                f,
+                "{} (line {}, column {}): {}",
+                path, line, column, message
+            ),
+            SceneError::Invalid { path, message } => write!(f, "{}: {}", path, message),
+        }
+    }
+}
+
+impl Error for SceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for SceneError {
+    fn from(err: std::io::Error) -> Self {
+        SceneError::Io(err)
+    }
+}
+
+fn default_one() -> f32 {
+    1.0
+}
+
+fn default_white() -> Vector3 {
+    Vector3::ones()
+}
+
+/// Image and sampling parameters for rendering a scene.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderSettings {
+    pub width: u32,
+    pub height: u32,
+    pub samples_per_pixel: u32,
+    pub max_depth: u32,
+    /// Path depth after which Russian roulette may terminate paths early.
+    pub roulette_depth: u32,
+    /// Seed for all random sampling; renders with the same seed are identical.
+    pub seed: u64,
+    /// Relative standard error at which a pixel stops taking samples, or zero to always take
+    /// `samples_per_pixel`.
+    pub adaptive_threshold: f32,
+    /// Samples every pixel takes before adaptive sampling may stop it.
+    pub min_samples: u32,
+    /// Debug images to render alongside the beauty pass.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub aovs: Vec<Aov>,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            width: 400,
+            height: 225,
+            samples_per_pixel: 16,
+            max_depth: 8,
+            roulette_depth: 3,
+            seed: 0,
+            adaptive_threshold: 0.0,
+            min_samples: 16,
+            aovs: Vec::new(),
+        }
+    }
+}
+
+/// A material description. Objects refer to materials by their key in `Scene::materials`.
+///
+/// Materials with a color may also name a `texture` from `Scene::textures`, which is multiplied
+/// by the color.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum MaterialDesc {
+    Lambertian {
+        #[serde(default = "default_white")]
+        albedo: Vector3,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        texture: Option<String>,
+    },
+    Metal {
+        #[serde(default = "default_white")]
+        albedo: Vector3,
+        #[serde(default)]
+        fuzz: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        texture: Option<String>,
+    },
+    Dielectric {
+        ior: f32,
+    },
+    Emissive {
+        #[serde(default = "default_white")]
+        color: Vector3,
+        #[serde(default = "default_one")]
+        intensity: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        texture: Option<String>,
+    },
+    /// The scattering inside a `volume` object, which keeps `color` of the light it stops.
+    Medium {
+        #[serde(default = "default_white")]
+        color: Vector3,
+        #[serde(default)]
+        phase: PhaseFunction,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        texture: Option<String>,
+    },
+}
+
+impl MaterialDesc {
+    /// Build the material this description refers to, looking up named textures in `textures`.
+    ///
+    /// Panics if a texture is missing from `textures`.
+    pub fn build(&self, textures: &BTreeMap<String, Arc<dyn Texture>>) -> Box<dyn Material> {
+        let texture = |name: &Option<String>, color| resolve_texture(textures, name, color);
+        match self {
+            MaterialDesc::Lambertian { albedo, texture: t } => {
+                Box::new(Lambertian::textured(texture(t, *albedo)))
+            }
+            MaterialDesc::Metal {
+                albedo,
+                fuzz,
+                texture: t,
+            } => Box::new(Metal::textured(texture(t, *albedo), *fuzz)),
+            MaterialDesc::Dielectric { ior } => Box::new(Dielectric::new(*ior)),
+            MaterialDesc::Emissive {
+                color,
+                intensity,
+                texture: t,
+            } => Box::new(DiffuseLight::textured(texture(t, *intensity * *color))),
+            MaterialDesc::Medium {
+                color,
+                phase,
+                texture: t,
+            } => Box::new(Medium::textured(texture(t, *color), *phase)),
+        }
+    }
+
+    /// Return the radiance this material emits, or `None` if it is not emissive.
+    ///
+    /// Panics if a texture is missing from `textures`.
+    pub fn emission(
+        &self,
+        textures: &BTreeMap<String, Arc<dyn Texture>>,
+    ) -> Option<Arc<dyn Texture>> {
+        match self {
+            MaterialDesc::Emissive {
+                color,
+                intensity,
+                texture,
+            } => Some(resolve_texture(textures, texture, *intensity * *color)),
+            _ => None,
+        }
+    }
+
+    /// Return the name of the texture this material uses, if any.
+    pub fn texture(&self) -> Option<&str> {
+        match self {
+            MaterialDesc::Lambertian { texture, .. }
+            | MaterialDesc::Metal { texture, .. }
+            | MaterialDesc::Emissive { texture, .. }
+            | MaterialDesc::Medium { texture, .. } => texture.as_deref(),
+            MaterialDesc::Dielectric { .. } => None,
+        }
+    }
+
+    fn texture_mut(&mut self) -> Option<&mut String> {
+        match self {
+            MaterialDesc::Lambertian { texture, .. }
+            | MaterialDesc::Metal { texture, .. }
+            | MaterialDesc::Emissive { texture, .. }
+            | MaterialDesc::Medium { texture, .. } => texture.as_mut(),
+            MaterialDesc::Dielectric { .. } => None,
+        }
+    }
+}
+
+/// Return the texture named `name` multiplied by `color`, or a solid `color` if there is none.
+fn resolve_texture(
+    textures: &BTreeMap<String, Arc<dyn Texture>>,
+    name: &Option<String>,
+    color: Vector3,
+) -> Arc<dyn Texture> {
+    match name {
+        Some(name) if color == Vector3::ones() => textures[name].clone(),
+        Some(name) => Arc::new(Scaled::new(textures[name].clone(), color)),
+        None => Arc::new(SolidColor::new(color)),
+    }
+}
+
+/// A texture description. Materials refer to textures by their key in `Scene::textures`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TextureDesc {
+    Solid {
+        color: Vector3,
+    },
+    /// A 3D checkerboard of cubes with side `1 / scale`.
+    Checker {
+        even: Vector3,
+        odd: Vector3,
+        #[serde(default = "default_one")]
+        scale: f32,
+    },
+    /// Perlin noise, turbulence or marble, multiplying `color`.
+    Noise {
+        #[serde(default)]
+        pattern: NoisePattern,
+        #[serde(default = "default_one")]
+        scale: f32,
+        #[serde(default = "default_white")]
+        color: Vector3,
+        #[serde(default)]
+        seed: u64,
+    },
+    /// A PNG, PPM, HDR or PFM image mapped by texture coordinates. Relative paths are resolved
+    /// against the scene file's directory.
+    Image {
+        path: String,
+        /// Gamma to decode PNG and PPM images with. HDR and PFM images are linear already.
+        #[serde(default = "default_gamma")]
+        gamma: f32,
+    },
+}
+
+/// Prefix of the keys that textures mapped by mesh materials are built under, which scene
+/// texture names may not start with.
+const MTL_TEXTURE_PREFIX: &str = "mtl:";
+
+pub(crate) fn default_gamma() -> f32 {
+    2.2
+}
+
+impl TextureDesc {
+    /// Build the texture this description refers to, resolving image paths against
+    /// `base_dir`.
+    pub fn build(&self, base_dir: Option<&Path>) -> Result<Arc<dyn Texture>, ImageError> {
+        Ok(match self {
+            TextureDesc::Solid { color } => Arc::new(SolidColor::new(*color)),
+            TextureDesc::Checker { even, odd, scale } => {
+                Arc::new(Checker::from_colors(*even, *odd, *scale))
+            }
+            TextureDesc::Noise {
+                pattern,
+                scale,
+                color,
+                seed,
+            } => Arc::new(NoiseTexture::new(*pattern, *scale, *color, *seed)),
+            TextureDesc::Image { path, gamma } => {
+                let path = match base_dir {
+                    Some(dir) => dir.join(path),
+                    None => PathBuf::from(path),
+                };
+                Arc::new(ImageTexture::load(path, *gamma)?)
+            }
+        })
+    }
+}
+
+/// A light source description. Each light's power is `intensity * color`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum LightDesc {
+    Point {
+        position: Vector3,
+        color: Vector3,
+        #[serde(default = "default_one")]
+        intensity: f32,
+    },
+    /// A distant light, such as the sun, shining along `direction`.
+    Directional {
+        direction: Vector3,
+        color: Vector3,
+        #[serde(default = "default_one")]
+        intensity: f32,
+    },
+    /// A point light shining along `direction` in a cone of half-angle `angle` degrees, fading
+    /// out over the outer `falloff` fraction of the cone.
+    Spot {
+        position: Vector3,
+        direction: Vector3,
+        color: Vector3,
+        #[serde(default = "default_one")]
+        intensity: f32,
+        angle: f32,
+        #[serde(default)]
+        falloff: f32,
+    },
+    /// A one-sided rectangular area light spanning `corner + a * u + b * v` for `a`, `b` in
+    /// `[0, 1]`, emitting on the side `u x v` points toward.
+    Rect {
+        corner: Vector3,
+        u: Vector3,
+        v: Vector3,
+        color: Vector3,
+        #[serde(default = "default_one")]
+        intensity: f32,
+    },
+    /// A spherical area light.
+    Sphere {
+        center: Vector3,
+        radius: f32,
+        color: Vector3,
+        #[serde(default = "default_one")]
+        intensity: f32,
+    },
+}
+
+impl LightDesc {
+    /// Return the color and intensity of this light.
+    fn power(&self) -> (Vector3, f32) {
+        match self {
+            LightDesc::Point {
+                color, intensity, ..
+            }
+            | LightDesc::Directional {
+                color, intensity, ..
+            }
+            | LightDesc::Spot {
+                color, intensity, ..
+            }
+            | LightDesc::Rect {
+                color, intensity, ..
+            }
+            | LightDesc::Sphere {
+                color, intensity, ..
+            } => (*color, *intensity),
+        }
+    }
+}
+
+fn default_zenith() -> Vector3 {
+    Vector3::new(0.5, 0.7, 1.0)
+}
+
+/// A description of the light arriving along rays that leave the scene, scaled by `intensity`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum EnvironmentDesc {
+    /// The same `color` from every direction.
+    Constant {
+        #[serde(default = "default_white")]
+        color: Vector3,
+        #[serde(default = "default_one")]
+        intensity: f32,
+    },
+    /// A sky blending with height from `horizon`, at and below the horizon, to `zenith` straight
+    /// up.
+    Gradient {
+        #[serde(default = "default_white")]
+        horizon: Vector3,
+        #[serde(default = "default_zenith")]
+        zenith: Vector3,
+        #[serde(default = "default_one")]
+        intensity: f32,
+    },
+    /// An equirectangular HDR or PFM image of linear radiance, whose center looks along `-z`,
+    /// turned `rotation` degrees about the `y` axis toward `+x`. Relative paths are resolved
+    /// against the scene file's directory.
+    Map {
+        path: String,
+        #[serde(default = "default_one")]
+        intensity: f32,
+        #[serde(default)]
+        rotation: f32,
+    },
+}
+
+impl EnvironmentDesc {
+    /// Build the environment this description refers to, resolving image paths against
+    /// `base_dir`.
+    pub fn build(&self, base_dir: Option<&Path>) -> Result<Environment, ImageError> {
+        Ok(match self {
+            EnvironmentDesc::Constant { color, intensity } => {
+                Environment::Constant(*intensity * *color)
+            }
+            EnvironmentDesc::Gradient {
+                horizon,
+                zenith,
+                intensity,
+            } => Environment::Gradient {
+                horizon: *intensity * *horizon,
+                zenith: *intensity * *zenith,
+            },
+            EnvironmentDesc::Map {
+                path,
+                intensity,
+                rotation,
+            } => {
+                let path = match base_dir {
+                    Some(dir) => dir.join(path),
+                    None => PathBuf::from(path),
+                };
+                let mut image = Framebuffer::load(path)?;
+                for pixel in image.pixels_mut() {
+                    *pixel *= *intensity;
+                }
+                Environment::Map(EnvironmentMap::new(image, rotation.to_radians()))
+            }
+        })
+    }
+}
+
+/// A geometric object description.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ObjectDesc {
+    Sphere {
+        center: Vector3,
+        radius: f32,
+        material: String,
+    },
+    Plane {
+        point: Vector3,
+        normal: Vector3,
+        material: String,
+    },
+    /// An axis-aligned box.
+    #[serde(rename = "box")]
+    Cuboid {
+        min: Vector3,
+        max: Vector3,
+        material: String,
+    },
+    /// A cylinder whose flat caps are centered on `base` and `top`.
+    Cylinder {
+        base: Vector3,
+        top: Vector3,
+        radius: f32,
+        material: String,
+    },
+    /// A cone with its base disk centered on `base` and its tip at `apex`.
+    Cone {
+        base: Vector3,
+        apex: Vector3,
+        radius: f32,
+        material: String,
+    },
+    Disk {
+        center: Vector3,
+        normal: Vector3,
+        radius: f32,
+        material: String,
+    },
+    /// A ring whose hole runs along `axis`, with a tube of `minor_radius` around a circle of
+    /// `major_radius`.
+    Torus {
+        center: Vector3,
+        axis: Vector3,
+        major_radius: f32,
+        minor_radius: f32,
+        material: String,
+    },
+    /// The solid where `a x^2 + b y^2 + c z^2 + d xy + e yz + f xz + g x + h y + i z + j` is at
+    /// most zero, with `coefficients` `[a, b, ..., j]`, clipped to the box from `min` to `max`.
+    Quadric {
+        coefficients: [f32; 10],
+        min: Vector3,
+        max: Vector3,
+        material: String,
+    },
+    /// A triangle mesh loaded from a Wavefront OBJ file. Faces use the materials from the OBJ's
+    /// material libraries, or scene materials of the same name; `material` is used for faces whose
+    /// material is not defined by either.
+    Mesh {
+        path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        material: Option<String>,
+    },
+    /// A boolean combination of two closed objects. Emissive surfaces inside a CSG object glow
+    /// but are not sampled as lights.
+    Csg {
+        op: CsgOp,
+        left: Box<ObjectDesc>,
+        right: Box<ObjectDesc>,
+    },
+    /// A participating medium, such as fog or smoke, filling the closed `boundary`, whose own
+    /// material is not used. Rays scatter in it with a probability per unit length of
+    /// `density`, times the value of `grid` where there is one, and are shaded with `material`,
+    /// which should be a `medium`. The grid spans the bounding box of the boundary.
+    Volume {
+        boundary: Box<ObjectDesc>,
+        density: f32,
+        material: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        grid: Option<DensityGrid>,
+    },
+    /// An object that moves over time, offset from where it is described by `keys` interpolated
+    /// linearly in time. Emissive surfaces of moving objects glow but are not sampled as lights.
+    Moving {
+        keys: Vec<MotionKey>,
+        object: Box<ObjectDesc>,
+    },
+}
+
+/// A complete scene description, as stored in a JSON scene file.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scene {
+    pub camera: CameraSettings,
+    #[serde(default)]
+    pub render: RenderSettings,
+    /// Textures keyed by name. Names starting with `mtl:` are reserved for the texture maps of
+    /// mesh materials.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub textures: BTreeMap<String, TextureDesc>,
+    #[serde(default)]
+    pub materials: BTreeMap<String, MaterialDesc>,
+    #[serde(default)]
+    pub lights: Vec<LightDesc>,
+    #[serde(default)]
+    pub objects: Vec<ObjectDesc>,
+    /// Objects shared by name among the nodes that place copies of them. Each is built once,
+    /// however many nodes place it, and its emissive surfaces glow but are not sampled as lights.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub assets: BTreeMap<String, ObjectDesc>,
+    /// Roots of the scene graph. The assets its nodes place are rendered alongside `objects`, and
+    /// numbered after the objects and lights, depth first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nodes: Vec<Node>,
+    /// Light from beyond the scene, which is black if there is none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<EnvironmentDesc>,
+    /// Frames to render as an animation, if the scene is animated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animation: Option<Animation>,
+    /// Directory that relative mesh and image paths are resolved against. Set by `Scene::load`.
+    #[serde(skip)]
+    pub base_dir: Option<PathBuf>,
+}
+
+impl Scene {
+    /// Parse and validate a scene from a JSON string.
+    pub fn from_json(json: &str) -> Result<Self, SceneError> {
+        let mut de = serde_json::Deserializer::from_str(json);
+        let scene: Scene = serde_path_to_error::deserialize(&mut de).map_err(|err| {
+            let path = err.path().to_string();
+            let inner = err.into_inner();
+            SceneError::Parse {
+                path,
+                line: inner.line(),
+                column: inner.column(),
+                message: inner.to_string(),
+            }
+        })?;
+        de.end().map_err(|err| SceneError::Parse {
+            path: ".".to_string(),
+            line: err.line(),
+            column: err.column(),
+            message: err.to_string(),
+        })?;
+
+        scene.validate()?;
+        Ok(scene)
+    }
+
+    /// Load and validate a scene from the JSON file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        let mut scene = Scene::from_json(&fs::read_to_string(path)?)?;
+        scene.base_dir = path.parent().map(Path::to_path_buf);
+        Ok(scene)
+    }
+
+    /// Serialize this scene to pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Scene serialization cannot fail.")
+    }
+
+    /// Write this scene as JSON to the file at `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SceneError> {
+        fs::write(path, self.to_json())?;
+        Ok(())
+    }
+
+    /// Build the camera described by this scene, matching the aspect ratio of the rendered image
+    /// unless the camera overrides it. For animations, this is the camera of the first frame.
+    pub fn camera(&self) -> Camera {
+        self.frame_camera(0)
+    }
+
+    /// Return the number of frames to render: one, unless the scene is animated.
+    pub fn frames(&self) -> u32 {
+        self.animation
+            .as_ref()
+            .map_or(1, |animation| animation.frames)
+    }
+
+    /// Build the camera for `frame` of the animation, with its shutter open from the frame's
+    /// start time plus `shutter_open` to its start plus `shutter_close`. Camera keyframes are
+    /// evaluated at both ends of that interval, so a fast camera move blurs the frame.
+    pub fn frame_camera(&self, frame: u32) -> Camera {
+        let aspect_ratio = self.render.width as f32 / self.render.height as f32;
+        let start = self
+            .animation
+            .as_ref()
+            .map_or(0.0, |animation| animation.frame_time(frame));
+        let shutter = (
+            start + self.camera.shutter_open,
+            start + self.camera.shutter_close,
+        );
+        let settings = |time| match &self.animation {
+            Some(animation) => animation.camera_at(&self.camera, time),
+            None => self.camera.clone(),
+        };
+        Camera::moving(
+            &settings(shutter.0),
+            &settings(shutter.1),
+            aspect_ratio,
+            shutter,
+        )
+    }
+
+    /// Return the node named `name` and the transform from its space into the world, if there is
+    /// such a node.
+    pub fn node(&self, name: &str) -> Option<(&Node, Transform)> {
+        let mut found = None;
+        for (i, root) in self.nodes.iter().enumerate() {
+            let path = format!("nodes[{}]", i);
+            // Stop the walk once the node is found.
+            let stopped = root.walk(&path, Transform::identity(), &mut |_, node, transform| {
+                if node.name.as_deref() == Some(name) {
+                    found = Some((node, transform));
+                    return Err(());
+                }
+                Ok(())
+            });
+            if stopped.is_err() {
+                break;
+            }
+        }
+        found
+    }
+
+    /// Return the handle of the material named `name`, if it exists.
+    pub fn material_id(&self, name: &str) -> Option<MaterialId> {
+        self.materials
+            .keys()
+            .position(|k| k == name)
+            .map(MaterialId)
+    }
+
+    /// Check that this scene is renderable, returning an error naming the first invalid value.
+    pub fn validate(&self) -> Result<(), SceneError> {
+        validate_camera("camera", &self.camera)?;
+        if self.camera.shutter_close < self.camera.shutter_open {
+            return Err(SceneError::invalid(
+                "camera.shutter_close",
+                format!(
+                    "must not precede shutter_open {}, got {}",
+                    self.camera.shutter_open, self.camera.shutter_close
+                ),
+            ));
+        }
+
+        let render = &self.render;
+        if render.width == 0 {
+            return Err(SceneError::invalid("render.width", "must be positive"));
+        }
+        if render.height == 0 {
+            return Err(SceneError::invalid("render.height", "must be positive"));
+        }
+        if render.samples_per_pixel == 0 {
+            return Err(SceneError::invalid(
+                "render.samples_per_pixel",
+                "must be positive",
+            ));
+        }
+        check_non_negative("render", "adaptive_threshold", render.adaptive_threshold)?;
+
+        for (name, texture) in &self.textures {
+            let path = format!("textures.{}", name);
+            if name.starts_with(MTL_TEXTURE_PREFIX) {
+                return Err(SceneError::invalid(
+                    path,
+                    format!(
+                        "names starting with \"{}\" are reserved for MTL texture maps",
+                        MTL_TEXTURE_PREFIX
+                    ),
+                ));
+            }
+            match texture {
+                TextureDesc::Solid { color } => check_color(&path, "color", *color)?,
+                TextureDesc::Checker { even, odd, scale } => {
+                    check_color(&path, "even", *even)?;
+                    check_color(&path, "odd", *odd)?;
+                    check_positive(&path, "scale", *scale)?;
+                }
+                TextureDesc::Noise { scale, color, .. } => {
+                    check_positive(&path, "scale", *scale)?;
+                    check_color(&path, "color", *color)?;
+                }
+                TextureDesc::Image { path: image, gamma } => {
+                    if image.is_empty() {
+                        return Err(SceneError::invalid(
+                            format!("{}.path", path),
+                            "must not be empty",
+                        ));
+                    }
+                    check_positive(&path, "gamma", *gamma)?;
+                }
+            }
+        }
+
+        for (name, material) in &self.materials {
+            let path = format!("materials.{}", name);
+            if let Some(texture) = material.texture() {
+                if !self.textures.contains_key(texture) {
+                    return Err(SceneError::invalid(
+                        format!("{}.texture", path),
+                        format!("unknown texture \"{}\"", texture),
+                    ));
+                }
+            }
+            match material {
+                MaterialDesc::Lambertian { albedo, .. } => check_color(&path, "albedo", *albedo)?,
+                MaterialDesc::Metal { albedo, fuzz, .. } => {
+                    check_color(&path, "albedo", *albedo)?;
+                    if !(0.0..=1.0).contains(fuzz) {
+                        return Err(SceneError::invalid(
+                            format!("{}.fuzz", path),
+                            format!("must be in [0, 1], got {}", fuzz),
+                        ));
+                    }
+                }
+                MaterialDesc::Dielectric { ior } => check_positive(&path, "ior", *ior)?,
+                MaterialDesc::Emissive {
+                    color, intensity, ..
+                } => {
+                    check_color(&path, "color", *color)?;
+                    check_non_negative(&path, "intensity", *intensity)?;
+                }
+                MaterialDesc::Medium { color, phase, .. } => {
+                    check_color(&path, "color", *color)?;
+                    if let PhaseFunction::HenyeyGreenstein { g } = phase {
+                        if g.is_nan() || g.abs() >= 1.0 {
+                            return Err(SceneError::invalid(
+                                format!("{}.phase.g", path),
+                                format!("must be in (-1, 1), got {}", g),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        match &self.environment {
+            None => {}
+            Some(EnvironmentDesc::Constant { color, intensity }) => {
+                check_color("environment", "color", *color)?;
+                check_non_negative("environment", "intensity", *intensity)?;
+            }
+            Some(EnvironmentDesc::Gradient {
+                horizon,
+                zenith,
+                intensity,
+            }) => {
+                check_color("environment", "horizon", *horizon)?;
+                check_color("environment", "zenith", *zenith)?;
+                check_non_negative("environment", "intensity", *intensity)?;
+            }
+            Some(EnvironmentDesc::Map {
+                path, intensity, ..
+            }) => {
+                if path.is_empty() {
+                    return Err(SceneError::invalid("environment.path", "must not be empty"));
+                }
+                check_non_negative("environment", "intensity", *intensity)?;
+            }
+        }
+
+        for (i, light) in self.lights.iter().enumerate() {
+            let path = format!("lights[{}]", i);
+            let (color, intensity) = light.power();
+            check_color(&path, "color", color)?;
+            check_non_negative(&path, "intensity", intensity)?;
+            match light {
+                LightDesc::Point { .. } => {}
+                LightDesc::Directional { direction, .. } => {
+                    check_nonzero(&path, "direction", *direction)?;
+                }
+                LightDesc::Spot {
+                    direction,
+                    angle,
+                    falloff,
+                    ..
+                } => {
+                    check_nonzero(&path, "direction", *direction)?;
+                    if !(*angle > 0.0 && *angle <= 180.0) {
+                        return Err(SceneError::invalid(
+                            format!("{}.angle", path),
+                            format!("must be in (0, 180], got {}", angle),
+                        ));
+                    }
+                    if !(0.0..=1.0).contains(falloff) {
+                        return Err(SceneError::invalid(
+                            format!("{}.falloff", path),
+                            format!("must be in [0, 1], got {}", falloff),
+                        ));
+                    }
+                }
+                LightDesc::Rect { u, v, .. } => {
+                    if u.cross(*v).squared_norm() == 0.0 {
+                        return Err(SceneError::invalid(
+                            format!("{}.v", path),
+                            "must not be zero or parallel to u",
+                        ));
+                    }
+                }
+                LightDesc::Sphere { radius, .. } => check_positive(&path, "radius", *radius)?,
+            }
+        }
+
+        for (i, object) in self.objects.iter().enumerate() {
+            self.validate_object(&format!("objects[{}]", i), object)?;
+        }
+        for (name, asset) in &self.assets {
+            self.validate_object(&format!("assets.{}", name), asset)?;
+        }
+        let mut names = BTreeSet::new();
+        for (i, root) in self.nodes.iter().enumerate() {
+            root.walk(
+                &format!("nodes[{}]", i),
+                Transform::identity(),
+                &mut |path, node, _| self.validate_node(path, node, &mut names),
+            )?;
+        }
+
+        if let Some(animation) = &self.animation {
+            if animation.frames == 0 {
+                return Err(SceneError::invalid("animation.frames", "must be positive"));
+            }
+            check_positive("animation", "frame_rate", animation.frame_rate)?;
+            for (i, keyframe) in animation.camera.iter().enumerate() {
+                let path = format!("animation.camera[{}]", i);
+                if i > 0 && keyframe.frame <= animation.camera[i - 1].frame {
+                    return Err(SceneError::invalid(
+                        format!("{}.frame", path),
+                        format!(
+                            "must come after the previous keyframe's frame {}, got {}",
+                            animation.camera[i - 1].frame,
+                            keyframe.frame
+                        ),
+                    ));
+                }
+                validate_camera(&path, &keyframe.apply(&self.camera))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validate `object`, found at `path` in the scene.
+    fn validate_object(&self, path: &str, object: &ObjectDesc) -> Result<(), SceneError> {
+        let material = match object {
+            ObjectDesc::Sphere {
+                radius, material, ..
+            } => {
+                check_positive(path, "radius", *radius)?;
+                material
+            }
+            ObjectDesc::Plane {
+                normal, material, ..
+            } => {
+                check_nonzero(path, "normal", *normal)?;
+                material
+            }
+            ObjectDesc::Cuboid { min, max, material }
+            | ObjectDesc::Quadric {
+                min, max, material, ..
+            } => {
+                check_ordered(path, *min, *max)?;
+                material
+            }
+            ObjectDesc::Cylinder {
+                base,
+                top: end,
+                radius,
+                material,
+            }
+            | ObjectDesc::Cone {
+                base,
+                apex: end,
+                radius,
+                material,
+            } => {
+                let field = match object {
+                    ObjectDesc::Cylinder { .. } => "top",
+                    _ => "apex",
+                };
+                if end == base {
+                    return Err(SceneError::invalid(
+                        format!("{}.{}", path, field),
+                        "must differ from base",
+                    ));
+                }
+                check_positive(path, "radius", *radius)?;
+                material
+            }
+            ObjectDesc::Disk {
+                normal,
+                radius,
+                material,
+                ..
+            } => {
+                check_nonzero(path, "normal", *normal)?;
+                check_positive(path, "radius", *radius)?;
+                material
+            }
+            ObjectDesc::Torus {
+                axis,
+                major_radius,
+                minor_radius,
+                material,
+                ..
+            } => {
+                check_nonzero(path, "axis", *axis)?;
+                check_positive(path, "major_radius", *major_radius)?;
+                check_positive(path, "minor_radius", *minor_radius)?;
+                material
+            }
+            ObjectDesc::Mesh {
+                path: mesh_path,
+                material,
+            } => {
+                if mesh_path.is_empty() {
+                    return Err(SceneError::invalid(
+                        format!("{}.path", path),
+                        "must not be empty",
+                    ));
+                }
+                match material {
+                    Some(material) => material,
+                    None => return Ok(()),
+                }
+            }
+            ObjectDesc::Csg { left, right, .. } => {
+                self.validate_object(&format!("{}.left", path), left)?;
+                return self.validate_object(&format!("{}.right", path), right);
+            }
+            ObjectDesc::Volume {
+                boundary,
+                density,
+                material,
+                grid,
+            } => {
+                if let ObjectDesc::Plane { .. } = **boundary {
+                    return Err(SceneError::invalid(
+                        format!("{}.boundary", path),
+                        "must be a closed object, not a plane",
+                    ));
+                }
+                self.validate_object(&format!("{}.boundary", path), boundary)?;
+                check_non_negative(path, "density", *density)?;
+                if let Some(grid) = grid {
+                    validate_grid(&format!("{}.grid", path), grid)?;
+                }
+                material
+            }
+            ObjectDesc::Moving { keys, object } => {
+                if keys.is_empty() {
+                    return Err(SceneError::invalid(
+                        format!("{}.keys", path),
+                        "must not be empty",
+                    ));
+                }
+                for (i, pair) in keys.windows(2).enumerate() {
+                    if pair[1].time <= pair[0].time {
+                        return Err(SceneError::invalid(
+                            format!("{}.keys[{}].time", path, i + 1),
+                            format!(
+                                "must come after the previous key's time {}, got {}",
+                                pair[0].time, pair[1].time
+                            ),
+                        ));
+                    }
+                }
+                return self.validate_object(&format!("{}.object", path), object);
+            }
+        };
+        if !self.materials.contains_key(material) {
+            return Err(SceneError::invalid(
+                format!("{}.material", path),
+                format!("unknown material \"{}\"", material),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Validate `node`, found at `path` in the scene graph, adding its name to the `names` of the
+    /// nodes before it.
+    fn validate_node<'a>(
+        &self,
+        path: &str,
+        node: &'a Node,
+        names: &mut BTreeSet<&'a str>,
+    ) -> Result<(), SceneError> {
+        if let Some(name) = &node.name {
+            if !names.insert(name) {
+                return Err(SceneError::invalid(
+                    format!("{}.name", path),
+                    format!("duplicate node name \"{}\"", name),
+                ));
+            }
+        }
+        let transform = &node.transform;
+        if let Some(rotate) = &transform.rotate {
+            check_nonzero(&format!("{}.transform.rotate", path), "axis", rotate.axis)?;
+        }
+        let scale = transform.scale;
+        if scale.x() == 0.0 || scale.y() == 0.0 || scale.z() == 0.0 {
+            return Err(SceneError::invalid(
+                format!("{}.transform.scale", path),
+                format!("must have no zero component, got {}", scale),
+            ));
+        }
+        if let Some(asset) = &node.asset {
+            if !self.assets.contains_key(asset) {
+                return Err(SceneError::invalid(
+                    format!("{}.asset", path),
+                    format!("unknown asset \"{}\"", asset),
+                ));
+            }
+        }
+        if let Some(material) = &node.material {
+            if node.asset.is_none() {
+                return Err(SceneError::invalid(
+                    format!("{}.material", path),
+                    "requires an asset to apply to",
+                ));
+            }
+            if !self.materials.contains_key(material) {
+                return Err(SceneError::invalid(
+                    format!("{}.material", path),
+                    format!("unknown material \"{}\"", material),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the geometry, materials and lights of this scene, loading any meshes and images it
+    /// refers to. Emissive objects become area lights. The scene must have been validated.
+    pub fn build_world(&self) -> Result<World, SceneError> {
+        let mut textures = BTreeMap::new();
+        for (name, texture) in &self.textures {
+            let texture = texture.build(self.base_dir.as_deref()).map_err(|err| {
+                SceneError::invalid(format!("textures.{}.path", name), err.to_string())
+            })?;
+            textures.insert(name.clone(), texture);
+        }
+
+        let mut materials: Vec<MaterialDesc> = self.materials.values().cloned().collect();
+        let mut objects: Vec<Box<dyn Hittable>> = Vec::new();
+        let mut lights: Vec<Box<dyn Light>> = Vec::new();
+
+        for (i, object) in self.objects.iter().enumerate() {
+            let path = format!("objects[{}]", i);
+            let mut built = Built {
+                textures: &mut textures,
+                materials: &mut materials,
+                lights: Some(&mut lights),
+            };
+            objects.extend(self.build_object(&path, object, i, &mut built)?);
+        }
+
+        // Area lights are numbered after the objects.
+        for (j, light) in self.lights.iter().enumerate() {
+            let id = self.objects.len() + j;
+            let (color, intensity) = light.power();
+            let power = intensity * color;
+            // Area lights are also visible geometry, with a material of their own.
+            let mut emissive = || {
+                materials.push(MaterialDesc::Emissive {
+                    color,
+                    intensity,
+                    texture: None,
+                });
+                MaterialId(materials.len() - 1)
+            };
+            let emit: Arc<dyn Texture> = Arc::new(SolidColor::new(power));
+            match light {
+                LightDesc::Point { position, .. } => {
+                    lights.push(Box::new(PointLight::new(*position, power)));
+                }
+                LightDesc::Directional { direction, .. } => {
+                    lights.push(Box::new(DirectionalLight::new(*direction, power)));
+                }
+                LightDesc::Spot {
+                    position,
+                    direction,
+                    angle,
+                    falloff,
+                    ..
+                } => lights.push(Box::new(SpotLight::new(
+                    *position,
+                    *direction,
+                    power,
+                    angle.to_radians(),
+                    *falloff,
+                ))),
+                LightDesc::Rect { corner, u, v, .. } => {
+                    let rect = Rect::new(*corner, *u, *v, emissive());
+                    lights.push(Box::new(AreaLight::new(Box::new(rect), emit)));
+                    objects.push(Box::new(Tagged::new(rect, id)));
+                }
+                LightDesc::Sphere { center, radius, .. } => {
+                    let sphere = Sphere::new(*center, *radius, emissive());
+                    lights.push(Box::new(AreaLight::new(Box::new(sphere), emit)));
+                    objects.push(Box::new(Tagged::new(sphere, id)));
+                }
+            }
+        }
+
+        // Node ids follow the area lights. Each asset is built the first time a node places it,
+        // and shared by the instances of every node that does.
+        let mut assets: BTreeMap<&str, Arc<dyn Hittable>> = BTreeMap::new();
+        let mut id = self.objects.len() + self.lights.len();
+        for (i, root) in self.nodes.iter().enumerate() {
+            root.walk(
+                &format!("nodes[{}]", i),
+                Transform::identity(),
+                &mut |path, node, transform| -> Result<(), SceneError> {
+                    let node_id = id;
+                    id += 1;
+                    let name = match &node.asset {
+                        Some(name) => name,
+                        None => return Ok(()),
+                    };
+                    let asset = match assets.get(name.as_str()) {
+                        Some(asset) => asset.clone(),
+                        None => {
+                            let mut built = Built {
+                                textures: &mut textures,
+                                materials: &mut materials,
+                                lights: None,
+                            };
+                            let path = format!("assets.{}", name);
+                            let asset =
+                                self.build_unlit(&path, &self.assets[name], 0, &mut built)?;
+                            let asset: Arc<dyn Hittable> = Arc::from(asset);
+                            assets.insert(name, asset.clone());
+                            asset
+                        }
+                    };
+                    let mut instance = Instance::new(asset, transform);
+                    if let Some(material) = &node.material {
+                        let material = self.material_id(material).ok_or_else(|| {
+                            SceneError::invalid(
+                                format!("{}.material", path),
+                                format!("unknown material \"{}\"", material),
+                            )
+                        })?;
+                        instance = instance.with_material(material);
+                    }
+                    // The tag replaces the one the asset was built with.
+                    objects.push(Box::new(Tagged::new(instance, node_id)));
+                    Ok(())
+                },
+            )?;
+        }
+
+        let materials = materials.iter().map(|m| m.build(&textures)).collect();
+        let mut world = World::new(Bvh::new(objects), materials).with_lights(lights);
+        if let Some(environment) = &self.environment {
+            let environment = environment
+                .build(self.base_dir.as_deref())
+                .map_err(|err| SceneError::invalid("environment.path", err.to_string()))?;
+            world = world.with_environment(environment);
+        }
+        Ok(world)
+    }
+
+    /// Build the hittables for `object`, found at `path` in the scene, tagging them with `id`.
+    fn build_object(
+        &self,
+        path: &str,
+        object: &ObjectDesc,
+        id: usize,
+        built: &mut Built,
+    ) -> Result<Vec<Box<dyn Hittable>>, SceneError> {
+        let lookup = |name: &str| {
+            self.material_id(name).ok_or_else(|| {
+                SceneError::invalid(
+                    format!("{}.material", path),
+                    format!("unknown material \"{}\"", name),
+                )
+            })
+        };
+        let shape: Box<dyn Hittable> = match object {
+            ObjectDesc::Sphere {
+                center,
+                radius,
+                material,
+            } => {
+                let sphere = Sphere::new(*center, *radius, lookup(material)?);
+                built.add_light(sphere.material, || Box::new(sphere));
+                Box::new(Tagged::new(sphere, id))
+            }
+            ObjectDesc::Plane {
+                point,
+                normal,
+                material,
+            } => Box::new(Tagged::new(
+                Plane::new(*point, *normal, lookup(material)?),
+                id,
+            )),
+            ObjectDesc::Cuboid { min, max, material } => {
+                Box::new(Tagged::new(Cuboid::new(*min, *max, lookup(material)?), id))
+            }
+            ObjectDesc::Cylinder {
+                base,
+                top,
+                radius,
+                material,
+            } => Box::new(Tagged::new(
+                Cylinder::new(*base, *top, *radius, lookup(material)?),
+                id,
+            )),
+            ObjectDesc::Cone {
+                base,
+                apex,
+                radius,
+                material,
+            } => Box::new(Tagged::new(
+                Cone::new(*base, *apex, *radius, lookup(material)?),
+                id,
+            )),
+            ObjectDesc::Disk {
+                center,
+                normal,
+                radius,
+                material,
+            } => {
+                let disk = Disk::new(*center, *normal, *radius, lookup(material)?);
+                built.add_light(disk.material, || Box::new(disk));
+                Box::new(Tagged::new(disk, id))
+            }
+            ObjectDesc::Torus {
+                center,
+                axis,
+                major_radius,
+                minor_radius,
+                material,
+            } => Box::new(Tagged::new(
+                Torus::new(
+                    *center,
+                    *axis,
+                    *major_radius,
+                    *minor_radius,
+                    lookup(material)?,
+                ),
+                id,
+            )),
+            ObjectDesc::Quadric {
+                coefficients,
+                min,
+                max,
+                material,
+            } => Box::new(Tagged::new(
+                Quadric::new(*coefficients, Aabb::new(*min, *max), lookup(material)?),
+                id,
+            )),
+            ObjectDesc::Mesh {
+                path: mesh_file,
+                material,
+            } => {
+                let mesh_path = match &self.base_dir {
+                    Some(dir) => dir.join(mesh_file),
+                    None => PathBuf::from(mesh_file),
+                };
+                let mesh = load_obj(mesh_path).map_err(|err| {
+                    SceneError::invalid(format!("{}.path", path), err.to_string())
+                })?;
+                let fallback = material.as_deref().map(lookup).transpose()?;
+
+                // Map the mesh's local material indices onto scene material handles.
+                let mut remap = Vec::with_capacity(mesh.material_names.len());
+                for name in &mesh.material_names {
+                    let material = if let Some(desc) = mesh.materials.get(name) {
+                        let mut desc = desc.clone();
+                        if let Some(texture) = desc.texture_mut() {
+                            // Keep mapped images apart from scene textures of the same name.
+                            let key = format!("{}{}", MTL_TEXTURE_PREFIX, texture);
+                            if !built.textures.contains_key(&key) {
+                                let image =
+                                    mesh.textures[&*texture].build(None).map_err(|err| {
+                                        SceneError::invalid(
+                                            format!("{}.path", path),
+                                            format!("{}: {}", texture, err),
+                                        )
+                                    })?;
+                                built.textures.insert(key.clone(), image);
+                            }
+                            *texture = key;
+                        }
+                        built.materials.push(desc);
+                        MaterialId(built.materials.len() - 1)
+                    } else if let Some(material) = self.material_id(name).or(fallback) {
+                        material
+                    } else {
+                        return Err(SceneError::invalid(
+                            format!("{}.material", path),
+                            format!(
+                                "mesh material \"{}\" is not defined; a fallback material is required",
+                                name
+                            ),
+                        ));
+                    };
+                    remap.push(material);
+                }
+
+                let mut triangles: Vec<Box<dyn Hittable>> = Vec::new();
+                for mut triangle in mesh.triangles {
+                    triangle.material = remap[triangle.material.0];
+                    built.add_light(triangle.material, || Box::new(triangle));
+                    triangles.push(Box::new(Tagged::new(triangle, id)));
+                }
+                return Ok(triangles);
+            }
+            ObjectDesc::Csg { op, left, right } => {
+                // Parts of a CSG operand's surface may be carved away, so emissive operands
+                // light the scene only where rays happen to hit them.
+                let left = self.build_unlit(&format!("{}.left", path), left, id, built)?;
+                let right = self.build_unlit(&format!("{}.right", path), right, id, built)?;
+                Box::new(Csg {
+                    op: *op,
+                    left,
+                    right,
+                })
+            }
+            ObjectDesc::Volume {
+                boundary,
+                density,
+                material,
+                grid,
+            } => {
+                let boundary_path = format!("{}.boundary", path);
+                let boundary = self.build_unlit(&boundary_path, boundary, id, built)?;
+                let volume = Volume::new(boundary, *density, lookup(material)?);
+                let volume = match grid {
+                    None => volume,
+                    Some(grid) => volume.with_grid(grid.clone()).ok_or_else(|| {
+                        SceneError::invalid(boundary_path, "must be bounded to hold a grid")
+                    })?,
+                };
+                Box::new(Tagged::new(volume, id))
+            }
+            ObjectDesc::Moving { keys, object } => {
+                // Lights are sampled where they are at time zero, so moving emissive surfaces
+                // light the scene only where rays happen to hit them.
+                let object = self.build_unlit(&format!("{}.object", path), object, id, built)?;
+                Box::new(Moving {
+                    object,
+                    keys: keys.clone(),
+                })
+            }
+        };
+        Ok(vec![shape])
+    }
+
+    /// Build `object`, found at `path` in the scene, as a single hittable tagged with `id`,
+    /// without sampling any of its surfaces as lights.
+    fn build_unlit(
+        &self,
+        path: &str,
+        object: &ObjectDesc,
+        id: usize,
+        built: &mut Built,
+    ) -> Result<Box<dyn Hittable>, SceneError> {
+        let mut inner = Built {
+            textures: &mut *built.textures,
+            materials: &mut *built.materials,
+            lights: None,
+        };
+        let mut parts = self.build_object(path, object, id, &mut inner)?;
+        Ok(if parts.len() == 1 {
+            parts.pop().unwrap()
+        } else {
+            Box::new(Bvh::new(parts))
+        })
+    }
+}
+
+/// State shared while building the objects of a scene.
+struct Built<'a> {
+    /// Scene textures, followed by any that mesh materials map.
+    textures: &'a mut BTreeMap<String, Arc<dyn Texture>>,
+    /// Scene materials, followed by any defined by meshes.
+    materials: &'a mut Vec<MaterialDesc>,
+    /// Lights to sample emissive surfaces with, or `None` where they should not be sampled.
+    lights: Option<&'a mut Vec<Box<dyn Light>>>,
+}
+
+impl Built<'_> {
+    /// If `material` is emissive, add an area light for the shape made by `shape`.
+    fn add_light(&mut self, material: MaterialId, shape: impl FnOnce() -> Box<dyn Hittable>) {
+        if let Some(lights) = &mut self.lights {
+            if let Some(emit) = self.materials[material.0].emission(self.textures) {
+                lights.push(Box::new(AreaLight::new(shape(), emit)));
+            }
+        }
+    }
+}
+
+/// Check the camera settings found at `path` in the scene.
+fn validate_camera(path: &str, camera: &CameraSettings) -> Result<(), SceneError> {
+    if camera.look_from == camera.look_at {
+        return Err(SceneError::invalid(
+            format!("{}.look_at", path),
+            format!("must differ from {}.look_from", path),
+        ));
+    }
+    if camera.up.squared_norm() == 0.0 {
+        return Err(SceneError::invalid(
+            format!("{}.up", path),
+            "must be non-zero",
+        ));
+    }
+    let forward = camera.look_at - camera.look_from;
+    if forward.cross(camera.up).squared_norm() == 0.0 {
+        return Err(SceneError::invalid(
+            format!("{}.up", path),
+            "must not be parallel to the viewing direction",
+        ));
+    }
+    if !(camera.vfov > 0.0 && camera.vfov < 180.0) {
+        return Err(SceneError::invalid(
+            format!("{}.vfov", path),
+            format!("must be in (0, 180), got {}", camera.vfov),
+        ));
+    }
+    if let Some(aspect_ratio) = camera.aspect_ratio {
+        check_positive(path, "aspect_ratio", aspect_ratio)?;
+    }
+    check_non_negative(path, "aperture", camera.aperture)?;
+    if let Some(focus_distance) = camera.focus_distance {
+        check_positive(path, "focus_distance", focus_distance)?;
+    }
+    Ok(())
+}
+
+/// Check the density grid found at `path` in the scene.
+fn validate_grid(path: &str, grid: &DensityGrid) -> Result<(), SceneError> {
+    if grid.resolution.contains(&0) {
+        return Err(SceneError::invalid(
+            format!("{}.resolution", path),
+            "must be positive along every axis",
+        ));
+    }
+    let count: usize = grid.resolution.iter().product();
+    if grid.values.len() != count {
+        return Err(SceneError::invalid(
+            format!("{}.values", path),
+            format!(
+                "expected {} values for resolution {:?}, got {}",
+                count,
+                grid.resolution,
+                grid.values.len()
+            ),
+        ));
+    }
+    if let Some(i) = grid
+        .values
+        .iter()
+        .position(|v| !(*v >= 0.0 && v.is_finite()))
+    {
+        return Err(SceneError::invalid(
+            format!("{}.values[{}]", path, i),
+            format!("must be non-negative, got {}", grid.values[i]),
+        ));
+    }
+    Ok(())
+}
+
+fn check_color(path: &str, field: &str, color: Vector3) -> Result<(), SceneError> {
+    if color.x() < 0.0 || color.y() < 0.0 || color.z() < 0.0 {
+        return Err(SceneError::invalid(
+            format!("{}.{}", path, field),
+            format!("color components must be non-negative, got {}", color),
+        ));
+    }
+    Ok(())
+}
+
+fn check_positive(path: &str, field: &str, value: f32) -> Result<(), SceneError> {
+    if value.is_nan() || value <= 0.0 {
+        return Err(SceneError::invalid(
+            format!("{}.{}", path, field),
+            format!("must be positive, got {}", value),
+        ));
+    }
+    Ok(())
+}
+
+fn check_non_negative(path: &str, field: &str, value: f32) -> Result<(), SceneError> {
+    if value.is_nan() || value < 0.0 {
+        return Err(SceneError::invalid(
+            format!("{}.{}", path, field),
+            format!("must be non-negative, got {}", value),
+        ));
+    }
+    Ok(())
+}
+
+fn check_ordered(path: &str, min: Vector3, max: Vector3) -> Result<(), SceneError> {
+    if !(min.x() < max.x() && min.y() < max.y() && min.z() < max.z()) {
+        return Err(SceneError::invalid(
+            format!("{}.max", path),
+            format!("must exceed min {} in every component, got {}", min, max),
+        ));
+    }
+    Ok(())
+}
+
+fn check_nonzero(path: &str, field: &str, v: Vector3) -> Result<(), SceneError> {
+    if v.squared_norm() == 0.0 {
+        return Err(SceneError::invalid(
+            format!("{}.{}", path, field),
+            "must be a non-zero vector",
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ray::Ray;
+
+    const SCENE: &str = r#"{
+        "camera": {
+            "look_from": {"x": 0, "y": 1, "z": 3},
+            "look_at": {"x": 0, "y": 0, "z": 0},
+            "vfov": 40,
+            "aperture": 0.1
+        },
+        "render": {"width": 64, "height": 48},
+        "materials": {
+            "ground": {"type": "lambertian", "albedo": {"x": 0.5, "y": 0.5, "z": 0.5}},
+            "glass": {"type": "dielectric", "ior": 1.5}
+        },
+        "lights": [
+            {"type": "point", "position": {"x": 0, "y": 5, "z": 0}, "color": {"x": 1, "y": 1, "z": 1}}
+        ],
+        "objects": [
+            {"type": "plane", "point": {"x": 0, "y": 0, "z": 0}, "normal": {"x": 0, "y": 1, "z": 0}, "material": "ground"},
+            {"type": "sphere", "center": {"x": 0, "y": 1, "z": 0}, "radius": 1, "material": "glass"}
+        ]
+    }"#;
+
+    #[test]
+    fn test_load() {
+        let scene = Scene::from_json(SCENE).unwrap();
+        assert_eq!(64, scene.render.width);
+        assert_eq!(16, scene.render.samples_per_pixel, "Expected default spp.");
+        assert_eq!(Vector3::new(0.0, 1.0, 0.0), scene.camera.up);
+        assert_eq!(None, scene.camera.focus_distance);
+
+        let ray = scene.camera().get_ray(0.5, 0.5, [0.5, 0.5], 0.5);
+        assert_eq!(scene.camera.look_from, ray.origin);
+        assert_eq!(2, scene.objects.len());
+        assert_eq!(Some(MaterialId(1)), scene.material_id("ground"));
+        assert_eq!(Some(MaterialId(0)), scene.material_id("glass"));
+
+        let world = scene.build_world().unwrap();
+        let ray = Ray::new(Vector3::new(0.0, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let hit = world.hit(&ray).unwrap();
+        assert_eq!(
+            MaterialId(0),
+            hit.material,
+            "Expected to hit the sphere first."
+        );
+        assert_eq!(Some(1), hit.object, "Expected the sphere's object index.");
+        let emitted = world.material(hit.material).emitted(&hit);
+        assert_eq!(Vector3::zeros(), emitted, "Glass should not emit light.");
+    }
+
+    #[test]
+    fn test_mesh_materials() {
+        let dir = std::env::temp_dir().join(format!("raytracer-scene-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("tris.obj"),
+            "mtllib tris.mtl\nv -1 0.5 -1\nv 1 0.5 -1\nv 0 0.5 1\nv -1 2 -1\nv 1 2 -1\nv 0 2 1\n\
+             usemtl shiny\nf 1 3 2\nusemtl ground\nf 4 6 5\n",
+        )
+        .unwrap();
+        fs::write(dir.join("tris.mtl"), "newmtl shiny\nillum 3\nKs 1 1 1\n").unwrap();
+
+        let json = SCENE.replace(
+            r#"{"type": "sphere", "center": {"x": 0, "y": 1, "z": 0}, "radius": 1, "material": "glass"}"#,
+            r#"{"type": "mesh", "path": "tris.obj"}"#,
+        );
+        fs::write(dir.join("scene.json"), json).unwrap();
+
+        let scene = Scene::load(dir.join("scene.json")).unwrap();
+        let world = scene.build_world().unwrap();
+
+        // The upper triangle uses the scene's "ground" material.
+        let down = Ray::new(Vector3::new(0.0, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let hit = world.hit(&down).unwrap();
+        assert_eq!(scene.material_id("ground"), Some(hit.material));
+
+        // The lower triangle uses the metal from the MTL file, appended after scene materials.
+        let up = Ray::new(Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let hit = world.hit(&up).unwrap();
+        assert_eq!(MaterialId(2), hit.material);
+
+        // Diffuse maps are loaded relative to the MTL file.
+        fs::create_dir_all(dir.join("maps")).unwrap();
+        fs::write(dir.join("maps/red.ppm"), "P3\n1 1\n255\n255 0 0\n").unwrap();
+        fs::write(
+            dir.join("tris.mtl"),
+            "newmtl shiny\nKd 1 1 1\nmap_Kd -s 1 1 1 maps/red.ppm\n",
+        )
+        .unwrap();
+        let world = scene.build_world().unwrap();
+        let hit = world.hit(&up).unwrap();
+        let mut rng = crate::rng::Pcg32::new(0, 0);
+        let scatter = world
+            .material(hit.material)
+            .scatter(&up, &hit, &mut rng)
+            .unwrap();
+        assert_eq!(Vector3::new(1.0, 0.0, 0.0), scatter.attenuation);
+
+        // A scene texture named like the map's path does not replace it.
+        let key = dir.join("maps/red.ppm").display().to_string();
+        let json = SCENE
+            .replace(
+                r#"{"type": "sphere", "center": {"x": 0, "y": 1, "z": 0}, "radius": 1, "material": "glass"}"#,
+                r#"{"type": "mesh", "path": "tris.obj"}"#,
+            )
+            .replace(
+                r#""materials": {"#,
+                &format!(
+                    r#""textures": {{{}: {{"type": "solid", "color": {{"x": 0, "y": 0, "z": 1}}}}}},
+                    "materials": {{"#,
+                    serde_json::to_string(&key).unwrap()
+                ),
+            )
+            .replace(
+                r#""ground": {"type": "lambertian", "albedo": {"x": 0.5, "y": 0.5, "z": 0.5}}"#,
+                &format!(
+                    r#""ground": {{"type": "lambertian", "texture": {}}}"#,
+                    serde_json::to_string(&key).unwrap()
+                ),
+            );
+        let mut colliding = Scene::from_json(&json).unwrap();
+        colliding.base_dir = Some(dir.clone());
+        let world = colliding.build_world().unwrap();
+        let attenuation = |ray: &Ray| {
+            let hit = world.hit(ray).unwrap();
+            let mut rng = crate::rng::Pcg32::new(0, 0);
+            let scatter = world.material(hit.material).scatter(ray, &hit, &mut rng);
+            scatter.unwrap().attenuation
+        };
+        assert_eq!(Vector3::new(1.0, 0.0, 0.0), attenuation(&up));
+        assert_eq!(Vector3::new(0.0, 0.0, 1.0), attenuation(&down));
+
+        let reserved = json.replace(
+            &format!("{{{}:", serde_json::to_string(&key).unwrap()),
+            r#"{"mtl:red":"#,
+        );
+        let err = Scene::from_json(&reserved).unwrap_err();
+        assert_eq!(Some("textures.mtl:red"), err.path(), "Got {}.", err);
+
+        fs::remove_file(dir.join("maps/red.ppm")).unwrap();
+        let err = scene.build_world().err().unwrap();
+        assert_eq!(Some("objects[1].path"), err.path(), "Got {}.", err);
+        assert!(err.to_string().contains("red.ppm"), "Got {}.", err);
+
+        // Undefined mesh materials without a fallback are an error.
+        fs::write(dir.join("tris.mtl"), "").unwrap();
+        let json = SCENE.replace(
+            r#"{"type": "sphere", "center": {"x": 0, "y": 1, "z": 0}, "radius": 1, "material": "glass"}"#,
+            r#"{"type": "mesh", "path": "tris.obj"}"#,
+        );
+        let mut scene = Scene::from_json(&json).unwrap();
+        scene.base_dir = Some(dir.clone());
+        let err = scene.build_world().err().unwrap();
+        assert_eq!(Some("objects[1].material"), err.path(), "Got {}.", err);
+        assert!(err.to_string().contains("\"shiny\""), "Got {}.", err);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_textures() {
+        let dir = std::env::temp_dir().join(format!("raytracer-textures-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("red.ppm"), "P3\n1 1\n255\n255 0 0\n").unwrap();
+
+        let json = SCENE.replace(
+            r#""materials": {"#,
+            r#""textures": {
+                "tiles": {"type": "checker", "even": {"x": 1, "y": 1, "z": 1}, "odd": {"x": 0, "y": 0, "z": 0}},
+                "marble": {"type": "noise", "pattern": "marble", "scale": 4},
+                "red": {"type": "image", "path": "red.ppm"}
+            },
+            "materials": {
+                "stone": {"type": "lambertian", "texture": "marble"},
+                "painted": {"type": "lambertian", "albedo": {"x": 0.5, "y": 0.5, "z": 0.5}, "texture": "red"},"#,
+        );
+        let json = json.replace(r#""material": "ground""#, r#""material": "painted""#);
+        fs::write(dir.join("scene.json"), &json).unwrap();
+
+        let scene = Scene::load(dir.join("scene.json")).unwrap();
+        assert_eq!(3, scene.textures.len());
+        let reloaded = Scene::from_json(&scene.to_json()).unwrap();
+        assert_eq!(scene.textures, reloaded.textures);
+        assert_eq!(scene.materials, reloaded.materials);
+
+        // The ground plane is the red image, tinted by the albedo.
+        let world = scene.build_world().unwrap();
+        let ray = Ray::new(Vector3::new(3.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let hit = world.hit(&ray).unwrap();
+        let mut rng = crate::rng::Pcg32::new(0, 0);
+        let scatter = world
+            .material(hit.material)
+            .scatter(&ray, &hit, &mut rng)
+            .unwrap();
+        assert_eq!(Vector3::new(0.5, 0.0, 0.0), scatter.attenuation);
+
+        let err = Scene::from_json(&json.replace(r#""texture": "red""#, r#""texture": "blue""#))
+            .unwrap_err();
+        assert_eq!(
+            Some("materials.painted.texture"),
+            err.path(),
+            "Got {}.",
+            err
+        );
+
+        let mut scene = Scene::from_json(&json.replace("red.ppm", "missing.ppm")).unwrap();
+        scene.base_dir = Some(dir.clone());
+        let err = scene.build_world().err().unwrap();
+        assert_eq!(Some("textures.red.path"), err.path(), "Got {}.", err);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_environment() {
+        let dir =
+            std::env::temp_dir().join(format!("raytracer-environment-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let sky = Framebuffer::from_fn(4, 2, |x, _| Vector3::new(x as f32, 1.0, 0.5));
+        sky.save(dir.join("sky.pfm"), &Default::default()).unwrap();
+
+        let json = SCENE.replace(
+            r#""objects": ["#,
+            r#""environment": {"type": "map", "path": "sky.pfm", "intensity": 2, "rotation": 90},
+            "objects": ["#,
+        );
+        fs::write(dir.join("scene.json"), &json).unwrap();
+        let scene = Scene::load(dir.join("scene.json")).unwrap();
+        let reloaded = Scene::from_json(&scene.to_json()).unwrap();
+        assert_eq!(scene.environment, reloaded.environment);
+        // Turned a quarter turn, the center of the map lies along +x.
+        let world = scene.build_world().unwrap();
+        assert_eq!(
+            Vector3::new(4.0, 2.0, 1.0),
+            world.background(Vector3::new(1.0, 0.1, 0.0))
+        );
+
+        let mut scene = Scene::from_json(&json.replace("sky.pfm", "missing.hdr")).unwrap();
+        scene.base_dir = Some(dir.clone());
+        let err = scene.build_world().err().unwrap();
+        assert_eq!(Some("environment.path"), err.path(), "Got {}.", err);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let json = SCENE.replace(
+            r#""objects": ["#,
+            r#""environment": {"type": "gradient", "intensity": 2},
+            "objects": ["#,
+        );
+        let world = Scene::from_json(&json).unwrap().build_world().unwrap();
+        assert_eq!(
+            Vector3::new(1.0, 1.4, 2.0),
+            world.background(Vector3::new(0.0, 1.0, 0.0))
+        );
+        let bad = json.replace(r#""intensity": 2"#, r#""intensity": -2"#);
+        let err = Scene::from_json(&bad).unwrap_err();
+        assert_eq!(Some("environment.intensity"), err.path(), "Got {}.", err);
+
+        // Without an environment, the background is black.
+        let world = Scene::from_json(SCENE).unwrap().build_world().unwrap();
+        assert_eq!(
+            Vector3::zeros(),
+            world.background(Vector3::new(0.0, 1.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn test_lights() {
+        let json = SCENE.replace(
+            r#""lights": ["#,
+            r#""lights": [
+            {"type": "spot", "position": {"x": 0, "y": 4, "z": 0}, "direction": {"x": 0, "y": -1, "z": 0}, "color": {"x": 1, "y": 1, "z": 1}, "angle": 30, "falloff": 0.2},
+            {"type": "directional", "direction": {"x": 1, "y": -1, "z": 0}, "color": {"x": 1, "y": 1, "z": 1}},
+            {"type": "rect", "corner": {"x": -1, "y": 3, "z": -1}, "u": {"x": 2, "y": 0, "z": 0}, "v": {"x": 0, "y": 0, "z": 2}, "color": {"x": 1, "y": 1, "z": 1}, "intensity": 4},
+            {"type": "sphere", "center": {"x": 5, "y": 5, "z": 5}, "radius": 0.5, "color": {"x": 1, "y": 1, "z": 1}},"#,
+        );
+        let scene = Scene::from_json(&json).unwrap();
+        assert_eq!(5, scene.lights.len());
+        assert_eq!(scene, Scene::from_json(&scene.to_json()).unwrap());
+
+        // The area lights are visible geometry, emitting from their front face.
+        let world = scene.build_world().unwrap();
+        let up = Ray::new(Vector3::new(0.0, 2.5, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let hit = world.hit(&up).unwrap();
+        assert_eq!(3.0, up.at(hit.t).y());
+        assert_eq!(
+            Vector3::ones() * 4.0,
+            world.material(hit.material).emitted(&hit)
+        );
+
+        // Beside the glass sphere, the sphere shadows the directional light but not the others.
+        let point = Vector3::new(2.0, 0.01, 0.0);
+        let mut rng = crate::rng::Pcg32::new(0, 0);
+        let mut visible = 0;
+        for _ in 0..100 {
+            let sample = world.sample_light(point, &mut rng).unwrap();
+            if world.visible(point, sample.direction, sample.distance, 0.0) {
+                visible += 1;
+            }
+        }
+        assert!(
+            visible > 0 && visible < 100,
+            "Got {} visible samples.",
+            visible
+        );
+
+        let err = Scene::from_json(&json.replace(r#""angle": 30"#, r#""angle": 0"#)).unwrap_err();
+        assert_eq!(Some("lights[0].angle"), err.path(), "Got {}.", err);
+        let err =
+            Scene::from_json(&json.replace(r#""intensity": 4"#, r#""intensity": -4"#)).unwrap_err();
+        assert_eq!(Some("lights[2].intensity"), err.path(), "Got {}.", err);
+    }
+
+    #[test]
+    fn test_shapes_and_csg() {
+        let json = SCENE.replace(
+            r#""objects": ["#,
+            r#""objects": [
+            {"type": "csg", "op": "difference",
+                "left": {"type": "box", "min": {"x": -1, "y": 2, "z": -1}, "max": {"x": 1, "y": 4, "z": 1}, "material": "ground"},
+                "right": {"type": "cylinder", "base": {"x": 0, "y": 1, "z": 0}, "top": {"x": 0, "y": 5, "z": 0}, "radius": 0.5, "material": "glass"}},
+            {"type": "cone", "base": {"x": 5, "y": 0, "z": 0}, "apex": {"x": 5, "y": 2, "z": 0}, "radius": 1, "material": "ground"},
+            {"type": "disk", "center": {"x": -5, "y": 1, "z": 0}, "normal": {"x": 0, "y": 1, "z": 0}, "radius": 1, "material": "ground"},
+            {"type": "torus", "center": {"x": 0, "y": 1, "z": 5}, "axis": {"x": 0, "y": 1, "z": 0}, "major_radius": 1, "minor_radius": 0.25, "material": "ground"},
+            {"type": "quadric", "coefficients": [1, 0, 1, 0, 0, 0, 0, 0, 16, 63], "min": {"x": -1, "y": 0, "z": -9}, "max": {"x": 1, "y": 1, "z": -7}, "material": "ground"},"#,
+        );
+        let scene = Scene::from_json(&json).unwrap();
+        assert_eq!(
+            scene,
+            Scene::from_json(&scene.to_json()).unwrap(),
+            "Shapes did not survive a round trip."
+        );
+        let world = scene.build_world().unwrap();
+
+        // Down through the hole drilled in the box, onto the glass sphere below.
+        let down =
+            |x: f32, z: f32| Ray::new(Vector3::new(x, 10.0, z), Vector3::new(0.0, -1.0, 0.0));
+        let hit = world.hit(&down(0.0, 0.0)).unwrap();
+        assert_eq!((Some(6), MaterialId(0)), (hit.object, hit.material));
+        let hit = world.hit(&down(0.75, 0.0)).unwrap();
+        assert_eq!(Some(0), hit.object);
+        assert!(
+            (hit.t - 6.0).abs() < 1e-5,
+            "Expected the box top, got {:?}.",
+            hit
+        );
+        for (x, z, object) in [(5.0, 0.0, 1), (-5.0, 0.0, 2), (1.0, 5.0, 3), (0.0, -8.0, 4)] {
+            let hit = world.hit(&down(x, z)).unwrap();
+            assert_eq!(Some(object), hit.object, "Got {:?}.", hit);
+        }
+
+        let bad = json.replace(r#""radius": 0.5"#, r#""radius": -0.5"#);
+        let err = Scene::from_json(&bad).unwrap_err();
+        assert_eq!(Some("objects[0].right.radius"), err.path(), "Got {}.", err);
+        let bad = json.replace(r#""z": -7}"#, r#""z": -9}"#);
+        let err = Scene::from_json(&bad).unwrap_err();
+        assert_eq!(Some("objects[4].max"), err.path(), "Got {}.", err);
+    }
+
+    #[test]
+    fn test_volumes() {
+        let json = SCENE
+            .replace(
+                r#""materials": {"#,
+                r#""materials": {
+                "fog": {"type": "medium", "color": {"x": 0.9, "y": 0.9, "z": 0.9}, "phase": {"type": "henyey_greenstein", "g": 0.5}},"#,
+            )
+            .replace(
+                r#""objects": ["#,
+                r#""objects": [
+                {"type": "volume", "density": 1000, "material": "fog",
+                    "boundary": {"type": "box", "min": {"x": 4, "y": 2, "z": -1}, "max": {"x": 6, "y": 4, "z": 1}, "material": "ground"}},
+                {"type": "volume", "density": 100, "material": "fog",
+                    "grid": {"resolution": [2, 2, 2], "values": [0, 0, 0, 0, 0, 0, 0, 0]},
+                    "boundary": {"type": "sphere", "center": {"x": -5, "y": 1, "z": 0}, "radius": 1, "material": "ground"}},"#,
+            );
+        let scene = Scene::from_json(&json).unwrap();
+        assert_eq!(
+            scene,
+            Scene::from_json(&scene.to_json()).unwrap(),
+            "Volumes did not survive a round trip."
+        );
+        let world = scene.build_world().unwrap();
+
+        // The dense fog stops a ray just inside its top, and the empty grid lets it through.
+        let down = |x: f32| Ray::new(Vector3::new(x, 10.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let hit = world.hit(&down(5.0)).unwrap();
+        assert_eq!(Some(0), hit.object);
+        assert!(
+            hit.t > 6.0 && hit.t < 6.1,
+            "Expected a hit just inside the fog, got {:?}.",
+            hit
+        );
+        assert_eq!(Some(2), world.hit(&down(-5.0)).unwrap().object);
+
+        let bad = json.replace(r#""g": 0.5"#, r#""g": 1.5"#);
+        let err = Scene::from_json(&bad).unwrap_err();
+        assert_eq!(Some("materials.fog.phase.g"), err.path(), "Got {}.", err);
+        let bad = json.replace(r#""values": [0, "#, r#""values": ["#);
+        let err = Scene::from_json(&bad).unwrap_err();
+        assert_eq!(Some("objects[1].grid.values"), err.path(), "Got {}.", err);
+        let bad = json.replace(r#""density": 1000"#, r#""density": -1"#);
+        let err = Scene::from_json(&bad).unwrap_err();
+        assert_eq!(Some("objects[0].density"), err.path(), "Got {}.", err);
+        let bad = json.replace(
+            r#""boundary": {"type": "box", "min": {"x": 4, "y": 2, "z": -1}, "max": {"x": 6, "y": 4, "z": 1}, "material": "ground"}"#,
+            r#""boundary": {"type": "plane", "point": {"x": 0, "y": 0, "z": 0}, "normal": {"x": 0, "y": 1, "z": 0}, "material": "ground"}"#,
+        );
+        let err = Scene::from_json(&bad).unwrap_err();
+        assert_eq!(Some("objects[0].boundary"), err.path(), "Got {}.", err);
+    }
+
+    #[test]
+    fn test_animation() {
+        let still = SCENE
+            .replace(r#""aperture": 0.1"#, r#""aperture": 0.1, "shutter_close": 0.25"#)
+            .replace(
+                r#""objects": ["#,
+                r#""objects": [
+                {"type": "moving", "keys": [{"time": 0, "offset": {"x": 0, "y": 0, "z": 0}}, {"time": 1, "offset": {"x": 0, "y": 0, "z": -10}}],
+                    "object": {"type": "sphere", "center": {"x": 5, "y": 1, "z": 0}, "radius": 1, "material": "ground"}},"#,
+            );
+        let scene = Scene::from_json(&still).unwrap();
+        assert_eq!(1, scene.frames());
+        assert_eq!((0.0, 0.25), scene.camera().shutter());
+
+        // The sphere leaves the spot it starts from.
+        let world = scene.build_world().unwrap();
+        let down = |time| {
+            Ray::with_time(
+                Vector3::new(5.0, 10.0, 0.0),
+                Vector3::new(0.0, -1.0, 0.0),
+                time,
+            )
+        };
+        assert_eq!(Some(0), world.hit(&down(0.0)).unwrap().object);
+        assert_eq!(Some(1), world.hit(&down(0.5)).unwrap().object);
+
+        let json = still.replace(
+            r#""objects": ["#,
+            r#""animation": {"frames": 10, "frame_rate": 4, "camera": [
+                {"frame": 0},
+                {"frame": 8, "look_from": {"x": 8, "y": 1, "z": 3}}
+            ]},
+            "objects": ["#,
+        );
+        let scene = Scene::from_json(&json).unwrap();
+        assert_eq!(
+            scene,
+            Scene::from_json(&scene.to_json()).unwrap(),
+            "Animation did not survive a round trip."
+        );
+        assert_eq!(10, scene.frames());
+        let camera = scene.frame_camera(4);
+        assert_eq!((1.0, 1.25), camera.shutter());
+        // Halfway through the shutter of frame 4, the camera is at frame 4.5.
+        let ray = camera.get_ray(0.5, 0.5, [0.5, 0.5], 0.5);
+        assert_eq!(1.125, ray.time);
+        assert!(
+            ray.origin.approx_eq(Vector3::new(4.5, 1.0, 3.0), 1e-5),
+            "Got {}.",
+            ray.origin
+        );
+        let ray = scene.frame_camera(9).get_ray(0.5, 0.5, [0.5, 0.5], 0.0);
+        assert_eq!(Vector3::new(8.0, 1.0, 3.0), ray.origin);
+
+        let bad = json.replace(r#""frame": 8"#, r#""frame": 0"#);
+        let err = Scene::from_json(&bad).unwrap_err();
+        assert_eq!(
+            Some("animation.camera[1].frame"),
+            err.path(),
+            "Got {}.",
+            err
+        );
+        let bad = json.replace(r#""x": 8, "y": 1, "z": 3"#, r#""x": 0, "y": 0, "z": 0"#);
+        let err = Scene::from_json(&bad).unwrap_err();
+        assert_eq!(
+            Some("animation.camera[1].look_at"),
+            err.path(),
+            "Got {}.",
+            err
+        );
+        let bad = json.replace(r#""time": 1"#, r#""time": 0"#);
+        let err = Scene::from_json(&bad).unwrap_err();
+        assert_eq!(Some("objects[0].keys[1].time"), err.path(), "Got {}.", err);
+        let bad = json.replace(r#""shutter_close": 0.25"#, r#""shutter_open": 0.1"#);
+        let err = Scene::from_json(&bad).unwrap_err();
+        assert_eq!(Some("camera.shutter_close"), err.path(), "Got {}.", err);
+    }
+
+    #[test]
+    fn test_scene_graph() {
+        let json = SCENE.replace(
+            r#""objects": ["#,
+            r#""assets": {
+                "ball": {"type": "sphere", "center": {"x": 0, "y": 0, "z": 0}, "radius": 0.5, "material": "ground"}
+            },
+            "nodes": [
+                {"name": "row", "transform": {"translate": {"x": 10, "y": 3, "z": 0}, "rotate": {"axis": {"x": 0, "y": 1, "z": 0}, "angle": 180}}, "children": [
+                    {"name": "left", "asset": "ball", "transform": {"translate": {"x": -2, "y": 0, "z": 0}}},
+                    {"name": "right", "asset": "ball", "material": "glass", "transform": {"translate": {"x": 2, "y": 0, "z": 0}, "scale": {"x": 1, "y": 4, "z": 1}}}
+                ]}
+            ],
+            "objects": ["#,
+        );
+        let scene = Scene::from_json(&json).unwrap();
+        let (node, transform) = scene.node("right").unwrap();
+        assert_eq!(Some("ball"), node.asset.as_deref());
+        let center = transform.point(Vector3::zeros());
+        assert!(
+            (center - Vector3::new(8.0, 3.0, 0.0)).norm() < 1e-5,
+            "Got {}.",
+            center
+        );
+        assert!(scene.node("row").is_some() && scene.node("ball").is_none());
+
+        // The turned row puts "left" on the right. Nodes are numbered after the two objects and
+        // the light, depth first.
+        let world = scene.build_world().unwrap();
+        let down = Vector3::new(0.0, -1.0, 0.0);
+        let hit = world
+            .hit(&Ray::new(Vector3::new(12.0, 10.0, 0.0), down))
+            .unwrap();
+        assert!((hit.point.y() - 3.5).abs() < 1e-4, "Got {}.", hit.point);
+        assert_eq!((MaterialId(1), Some(4)), (hit.material, hit.object));
+        // "right" is stretched upwards and shaded with its own material.
+        let hit = world
+            .hit(&Ray::new(Vector3::new(8.0, 10.0, 0.0), down))
+            .unwrap();
+        assert!((hit.point.y() - 5.0).abs() < 1e-4, "Got {}.", hit.point);
+        assert_eq!((MaterialId(0), Some(5)), (hit.material, hit.object));
+
+        let reloaded = Scene::from_json(&scene.to_json()).unwrap();
+        assert_eq!(scene, reloaded);
+
+        let cases = [
+            (
+                r#""asset": "ball", "transform""#,
+                r#""asset": "cube", "transform""#,
+                "nodes[0].children[0].asset",
+            ),
+            (
+                r#""name": "right""#,
+                r#""name": "left""#,
+                "nodes[0].children[1].name",
+            ),
+            (
+                r#""y": 4"#,
+                r#""y": 0"#,
+                "nodes[0].children[1].transform.scale",
+            ),
+            (
+                r#""axis": {"x": 0, "y": 1"#,
+                r#""axis": {"x": 0, "y": 0"#,
+                "nodes[0].transform.rotate.axis",
+            ),
+            (
+                r#""name": "row","#,
+                r#""name": "row", "material": "glass","#,
+                "nodes[0].material",
+            ),
+            (
+                r#""radius": 0.5, "material": "ground""#,
+                r#""radius": 0.5, "material": "clay""#,
+                "assets.ball.material",
+            ),
+        ];
+        for (from, to, path) in cases {
+            assert_eq!(1, json.matches(from).count(), "{} is ambiguous.", from);
+            let err = Scene::from_json(&json.replace(from, to)).unwrap_err();
+            assert_eq!(Some(path), err.path(), "Got {}.", err);
+        }
+    }
+
+    #[test]
+    fn test_load_example() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/spheres.json");
+        let scene = Scene::load(path).unwrap();
+        assert_eq!(5, scene.objects.len());
+
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/parts.json");
+        let scene = Scene::load(path).unwrap();
+        assert_eq!(6, scene.objects.len());
+        scene.build_world().unwrap();
+
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/motion.json");
+        let scene = Scene::load(path).unwrap();
+        assert_eq!(24, scene.frames());
+        scene.build_world().unwrap();
+
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/smoke.json");
+        let scene = Scene::load(path).unwrap();
+        assert_eq!(5, scene.objects.len());
+        scene.build_world().unwrap();
+
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/environment.json");
+        let scene = Scene::load(path).unwrap();
+        assert!(scene.lights.is_empty());
+        let world = scene.build_world().unwrap();
+        assert!(world.background(Vector3::new(0.0, 1.0, 0.0)).x() > 0.0);
+
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/graph.json");
+        let scene = Scene::load(path).unwrap();
+        assert_eq!(3, scene.assets.len());
+        assert_eq!(5, scene.node("temple").unwrap().0.children.len());
+        scene.build_world().unwrap();
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let scene = Scene::from_json(SCENE).unwrap();
+        assert!(!scene.to_json().contains("aovs"));
+        let json = SCENE.replace(
+            r#""height": 48}"#,
+            r#""height": 48, "aovs": ["depth", "object_id"]}"#,
+        );
+        let scene = Scene::from_json(&json).unwrap();
+        assert_eq!(vec![Aov::Depth, Aov::ObjectId], scene.render.aovs);
+        let json = scene.to_json();
+        let reloaded = Scene::from_json(&json).unwrap();
+        assert_eq!(
+            scene, reloaded,
+            "Scene did not survive a round trip:\n{}",
+            json
+        );
+    }
+
+    #[test]
+    fn test_parse_error_path() {
+        let json = SCENE.replace(r#""radius": 1"#, r#""radius": "big""#);
+        let err = Scene::from_json(&json).unwrap_err();
+        assert!(
+            matches!(err, SceneError::Parse { .. }),
+            "Expected a parse error, got {:?}.",
+            err
+        );
+        assert!(
+            err.path().unwrap().starts_with("objects[1]"),
+            "Expected error path under objects[1], got {}.",
+            err
+        );
+
+        let json = SCENE.replace(r#""width": 64"#, r#""width": -1"#);
+        let err = Scene::from_json(&json).unwrap_err();
+        assert_eq!(Some("render.width"), err.path(), "Got {}.", err);
+    }
+
+    #[test]
+    fn test_validation_error_path() {
+        let json = SCENE.replace(r#""material": "glass""#, r#""material": "steel""#);
+        let err = Scene::from_json(&json).unwrap_err();
+        assert_eq!(Some("objects[1].material"), err.path(), "Got {}.", err);
+
+        let json = SCENE.replace(r#""ior": 1.5"#, r#""ior": 0"#);
+        let err = Scene::from_json(&json).unwrap_err();
+        assert_eq!(Some("materials.glass.ior"), err.path(), "Got {}.", err);
+
+        let json = SCENE.replace(r#""radius": 1"#, r#""radius": 0"#);
+        let err = Scene::from_json(&json).unwrap_err();
+        assert_eq!(Some("objects[1].radius"), err.path(), "Got {}.", err);
+
+        let json = SCENE.replace(r#""aperture": 0.1"#, r#""aperture": -1"#);
+        let err = Scene::from_json(&json).unwrap_err();
+        assert_eq!(Some("camera.aperture"), err.path(), "Got {}.", err);
+    }
+}
@@ -0,0 +1,141 @@
+use std::f32::consts::PI;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, MaterialId};
+use crate::ray::Ray;
+use crate::sampling::Onb;
+use crate::vector::Vector3;
+
+/// A solid cone tapering from a disk of `radius` around `base` to a point at `apex`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cone {
+    pub base: Vector3,
+    pub apex: Vector3,
+    pub radius: f32,
+    pub material: MaterialId,
+}
+
+impl Cone {
+    /// Create a new `Cone` with its base disk of `radius` centered on `base` and its tip at
+    /// `apex`.
+    pub fn new(base: Vector3, apex: Vector3, radius: f32, material: MaterialId) -> Self {
+        Cone {
+            base,
+            apex,
+            radius,
+            material,
+        }
+    }
+}
+
+impl Hittable for Cone {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        // Intersect in a frame with the axis along +z from the base. The direction is not
+        // renormalized, so t carries over.
+        let axis = self.apex - self.base;
+        let height = axis.norm();
+        let onb = Onb::from_w(axis / height);
+        let o = onb.to_local(ray.origin - self.base);
+        let d = onb.to_local(ray.direction);
+
+        // The side, where x^2 + y^2 = (k (h - z))^2 with k = r / h, between base and apex.
+        let k2 = (self.radius / height).powi(2);
+        let h = height - o.z();
+        let a = d.x() * d.x() + d.y() * d.y() - k2 * d.z() * d.z();
+        let b = 2.0 * (o.x() * d.x() + o.y() * d.y() + k2 * h * d.z());
+        let c = o.x() * o.x() + o.y() * o.y() - k2 * h * h;
+        let side_hits = super::solve_quadratic(a, b, c)
+            .into_iter()
+            .flat_map(|(t0, t1)| [t0, t1])
+            .filter(|&t| (0.0..=height).contains(&(o.z() + t * d.z())))
+            .map(|t| (t, false));
+
+        // The base, where z = 0, inside the radius.
+        let base_hit = (d.z() != 0.0)
+            .then(|| -o.z() / d.z())
+            .filter(|&t| {
+                let p = o + t * d;
+                p.x() * p.x() + p.y() * p.y() <= self.radius * self.radius
+            })
+            .map(|t| (t, true));
+
+        let (t, on_base) = super::nearest(side_hits.chain(base_hit), t_min, t_max)?;
+        let p = o + t * d;
+        let u = (p.y().atan2(p.x()) + PI) / (2.0 * PI);
+        let (normal, v) = if on_base {
+            (
+                Vector3::new(0.0, 0.0, -1.0),
+                (p.x() * p.x() + p.y() * p.y()).sqrt() / self.radius,
+            )
+        } else {
+            // The gradient of x^2 + y^2 - k^2 (h - z)^2.
+            let normal = Vector3::new(p.x(), p.y(), k2 * (height - p.z()));
+            (normal.normalized(), p.z() / height)
+        };
+        Some(HitRecord::new(ray, t, onb.to_world(normal), self.material).with_uv(u, v))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let n = (self.apex - self.base).normalized();
+        let extent = Vector3::new(
+            (1.0 - n.x() * n.x()).max(0.0).sqrt(),
+            (1.0 - n.y() * n.y()).max(0.0).sqrt(),
+            (1.0 - n.z() * n.z()).max(0.0).sqrt(),
+        ) * self.radius;
+        Some(Aabb::from_points([
+            self.base - extent,
+            self.base + extent,
+            self.apex,
+        ]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hit() {
+        let cone = Cone::new(
+            Vector3::zeros(),
+            Vector3::new(0.0, 2.0, 0.0),
+            1.0,
+            MaterialId(0),
+        );
+        assert_eq!(
+            Aabb::new(Vector3::new(-1.0, 0.0, -1.0), Vector3::new(1.0, 2.0, 1.0)),
+            cone.bounding_box().unwrap()
+        );
+
+        // Halfway up, the radius is one half.
+        let ray = Ray::new(Vector3::new(-5.0, 1.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        let hit = cone.hit(&ray, 0.0, f32::INFINITY).unwrap();
+        assert!(
+            (hit.t - 4.5).abs() < 1e-5,
+            "Expected t = 4.5, got {}.",
+            hit.t
+        );
+        let expected = Vector3::new(-2.0, 1.0, 0.0).normalized();
+        assert!(
+            (hit.normal - expected).norm() < 1e-5,
+            "Expected normal {}, got {}.",
+            expected,
+            hit.normal
+        );
+
+        // Up into the base.
+        let up = Ray::new(Vector3::new(0.5, -1.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let hit = cone.hit(&up, 0.0, f32::INFINITY).unwrap();
+        assert!((hit.t - 1.0).abs() < 1e-5);
+        assert!((hit.normal - Vector3::new(0.0, -1.0, 0.0)).norm() < 1e-5);
+        assert!(hit.front_face);
+        // Then out through the side, at height one half.
+        let hit = cone.hit(&up, 1.5, f32::INFINITY).unwrap();
+        assert!((hit.t - 2.0).abs() < 1e-5, "Expected t = 2, got {}.", hit.t);
+        assert!(!hit.front_face);
+
+        // The mirror-image nappe above the apex is not part of the cone.
+        let above = Ray::new(Vector3::new(-5.0, 3.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert!(cone.hit(&above, 0.0, f32::INFINITY).is_none());
+    }
+}
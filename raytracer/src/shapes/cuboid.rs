@@ -0,0 +1,99 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, MaterialId};
+use crate::ray::Ray;
+use crate::vector::Vector3;
+
+/// An axis-aligned box spanning `min` to `max`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cuboid {
+    pub min: Vector3,
+    pub max: Vector3,
+    pub material: MaterialId,
+}
+
+impl Cuboid {
+    /// Create a new `Cuboid` with opposite corners `a` and `b`.
+    pub fn new(a: Vector3, b: Vector3, material: MaterialId) -> Self {
+        Cuboid {
+            min: a.cwise(b, f32::min),
+            max: a.cwise(b, f32::max),
+            material,
+        }
+    }
+}
+
+/// Where a ray crosses the boundary of a box: the ray parameter and the box's outward normal.
+pub(super) type Crossing = (f32, Vector3);
+
+/// Return where `ray` enters and leaves the box spanning `min` to `max`, or `None` if it misses.
+/// The crossings may lie behind the ray origin.
+pub(super) fn slabs(min: Vector3, max: Vector3, ray: &Ray) -> Option<(Crossing, Crossing)> {
+    let mut enter = (f32::NEG_INFINITY, Vector3::zeros());
+    let mut exit = (f32::INFINITY, Vector3::zeros());
+    for axis in 0..3 {
+        let inv_d = 1.0 / ray.direction[axis];
+        let origin = ray.origin[axis];
+        let mut t0 = (min[axis] - origin) * inv_d;
+        let mut t1 = (max[axis] - origin) * inv_d;
+        // The normal of the face crossed first along the ray points back against it.
+        let mut normal = Vector3::zeros();
+        normal[axis] = -1.0;
+        if inv_d < 0.0 {
+            std::mem::swap(&mut t0, &mut t1);
+            normal[axis] = 1.0;
+        }
+        if t0 > enter.0 {
+            enter = (t0, normal);
+        }
+        if t1 < exit.0 {
+            exit = (t1, -normal);
+        }
+    }
+    (enter.0 <= exit.0).then_some((enter, exit))
+}
+
+impl Hittable for Cuboid {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (enter, exit) = slabs(self.min, self.max, ray)?;
+        let (t, normal) = super::nearest([enter, exit], t_min, t_max)?;
+
+        // Texture coordinates run across each face along the other two axes.
+        let axis = (0..3).find(|&axis| normal[axis] != 0.0).unwrap();
+        let local = (ray.at(t) - self.min).cwise_div(self.max - self.min);
+        let (u, v) = (local[(axis + 1) % 3], local[(axis + 2) % 3]);
+        Some(HitRecord::new(ray, t, normal, self.material).with_uv(u, v))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(self.min, self.max))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hit() {
+        let cuboid = Cuboid::new(Vector3::ones(), -Vector3::ones(), MaterialId(0));
+        assert_eq!(-Vector3::ones(), cuboid.min);
+
+        let ray = Ray::new(Vector3::new(0.5, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit = cuboid.hit(&ray, 0.0, f32::INFINITY).unwrap();
+        assert_eq!(4.0, hit.t);
+        assert_eq!(Vector3::new(0.0, 0.0, 1.0), hit.normal);
+        assert!(hit.front_face, "Expected a front-face hit, got {:?}.", hit);
+        assert_eq!((0.75, 0.5), (hit.u, hit.v));
+
+        // From inside, the ray hits the far face from behind.
+        let inside = Ray::new(Vector3::zeros(), Vector3::new(1.0, 0.0, 0.0));
+        let hit = cuboid.hit(&inside, 0.0, f32::INFINITY).unwrap();
+        assert_eq!(1.0, hit.t);
+        assert!(!hit.front_face, "Expected a back-face hit, got {:?}.", hit);
+        assert_eq!(Vector3::new(-1.0, 0.0, 0.0), hit.normal);
+
+        let miss = Ray::new(Vector3::new(2.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!(cuboid.hit(&miss, 0.0, f32::INFINITY).is_none());
+        assert!(cuboid.hit(&ray, 0.0, 3.0).is_none());
+    }
+}
@@ -0,0 +1,144 @@
+use std::f32::consts::PI;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, MaterialId};
+use crate::ray::Ray;
+use crate::sampling::Onb;
+use crate::vector::Vector3;
+
+/// A solid cylinder of `radius` around the segment from `base` to `top`, closed by flat caps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cylinder {
+    pub base: Vector3,
+    pub top: Vector3,
+    pub radius: f32,
+    pub material: MaterialId,
+}
+
+impl Cylinder {
+    /// Create a new `Cylinder` with `radius` whose caps are centered on `base` and `top`.
+    pub fn new(base: Vector3, top: Vector3, radius: f32, material: MaterialId) -> Self {
+        Cylinder {
+            base,
+            top,
+            radius,
+            material,
+        }
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        // Intersect in a frame with the axis along +z from the base. The direction is not
+        // renormalized, so t carries over.
+        let axis = self.top - self.base;
+        let height = axis.norm();
+        let onb = Onb::from_w(axis / height);
+        let o = onb.to_local(ray.origin - self.base);
+        let d = onb.to_local(ray.direction);
+        let r2 = self.radius * self.radius;
+
+        // The side, where x^2 + y^2 = r^2, between the caps.
+        let a = d.x() * d.x() + d.y() * d.y();
+        let b = 2.0 * (o.x() * d.x() + o.y() * d.y());
+        let c = o.x() * o.x() + o.y() * o.y() - r2;
+        let side = if a > 0.0 {
+            super::solve_quadratic(a, b, c)
+        } else {
+            None
+        };
+        let on_side = |t: f32| (0.0..=height).contains(&(o.z() + t * d.z()));
+        let side_hits = side
+            .into_iter()
+            .flat_map(|(t0, t1)| [t0, t1])
+            .filter(|&t| on_side(t))
+            .map(|t| (t, None));
+
+        // The caps, where z = 0 or z = height, inside the radius.
+        let cap_hits = [(0.0, -1.0), (height, 1.0)]
+            .into_iter()
+            .filter(|_| d.z() != 0.0)
+            .map(|(z, normal)| ((z - o.z()) / d.z(), Some(normal)))
+            .filter(|&(t, _)| {
+                let p = o + t * d;
+                p.x() * p.x() + p.y() * p.y() <= r2
+            });
+
+        let (t, cap) = super::nearest(side_hits.chain(cap_hits), t_min, t_max)?;
+        let p = o + t * d;
+        let angle = (p.y().atan2(p.x()) + PI) / (2.0 * PI);
+        let (normal, u, v) = match cap {
+            // Caps are textured with the angle and distance from the center.
+            Some(z) => (
+                Vector3::new(0.0, 0.0, z),
+                angle,
+                (p.x() * p.x() + p.y() * p.y()).sqrt() / self.radius,
+            ),
+            None => (
+                Vector3::new(p.x(), p.y(), 0.0) / self.radius,
+                angle,
+                p.z() / height,
+            ),
+        };
+        Some(HitRecord::new(ray, t, onb.to_world(normal), self.material).with_uv(u, v))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // Each cap's disk extends by the radius times the sine of an axis's angle to the
+        // cylinder's axis.
+        let n = (self.top - self.base).normalized();
+        let extent = Vector3::new(
+            (1.0 - n.x() * n.x()).max(0.0).sqrt(),
+            (1.0 - n.y() * n.y()).max(0.0).sqrt(),
+            (1.0 - n.z() * n.z()).max(0.0).sqrt(),
+        ) * self.radius;
+        Some(Aabb::from_points([
+            self.base - extent,
+            self.base + extent,
+            self.top - extent,
+            self.top + extent,
+        ]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hit() {
+        let cylinder = Cylinder::new(
+            Vector3::zeros(),
+            Vector3::new(0.0, 2.0, 0.0),
+            1.0,
+            MaterialId(0),
+        );
+        assert_eq!(
+            Aabb::new(Vector3::new(-1.0, 0.0, -1.0), Vector3::new(1.0, 2.0, 1.0)),
+            cylinder.bounding_box().unwrap()
+        );
+
+        // Through the side.
+        let ray = Ray::new(Vector3::new(-5.0, 1.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        let hit = cylinder.hit(&ray, 0.0, f32::INFINITY).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-5, "Expected t = 4, got {}.", hit.t);
+        assert!((hit.normal - Vector3::new(-1.0, 0.0, 0.0)).norm() < 1e-5);
+        assert!((hit.v - 0.5).abs() < 1e-5);
+
+        // Down onto the top cap, and out through the base from inside.
+        let down = Ray::new(Vector3::new(0.5, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let hit = cylinder.hit(&down, 0.0, f32::INFINITY).unwrap();
+        assert!((hit.t - 3.0).abs() < 1e-5);
+        assert!((hit.normal - Vector3::new(0.0, 1.0, 0.0)).norm() < 1e-5);
+        assert!(hit.front_face);
+        let hit = cylinder.hit(&down, 3.5, f32::INFINITY).unwrap();
+        assert!((hit.t - 5.0).abs() < 1e-5);
+        assert!(!hit.front_face);
+
+        // Past the end of the side, and parallel to the axis outside the radius.
+        let above = Ray::new(Vector3::new(-5.0, 2.5, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert!(cylinder.hit(&above, 0.0, f32::INFINITY).is_none());
+        let beside = Ray::new(Vector3::new(2.0, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        assert!(cylinder.hit(&beside, 0.0, f32::INFINITY).is_none());
+    }
+}
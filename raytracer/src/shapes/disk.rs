@@ -0,0 +1,128 @@
+use std::f32::consts::PI;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, MaterialId};
+use crate::ray::Ray;
+use crate::rng::Pcg32;
+use crate::sampling::{concentric_disk, Onb};
+use crate::vector::Vector3;
+
+/// A flat disk of `radius` around `center`, facing along the unit `normal`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Disk {
+    pub center: Vector3,
+    pub normal: Vector3,
+    pub radius: f32,
+    pub material: MaterialId,
+}
+
+impl Disk {
+    /// Create a new `Disk` at `center` perpendicular to `normal`. The normal is normalized.
+    pub fn new(center: Vector3, normal: Vector3, radius: f32, material: MaterialId) -> Self {
+        Disk {
+            center,
+            normal: normal.normalized(),
+            radius,
+            material,
+        }
+    }
+
+    /// Return the area of this disk.
+    pub fn area(&self) -> f32 {
+        PI * self.radius * self.radius
+    }
+}
+
+impl Hittable for Disk {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let denom = self.normal.dot(ray.direction);
+        if denom.abs() < 1e-12 {
+            // The ray is parallel to the disk.
+            return None;
+        }
+
+        let t = (self.center - ray.origin).dot(self.normal) / denom;
+        if t <= t_min || t >= t_max {
+            return None;
+        }
+        let local = Onb::from_w(self.normal).to_local(ray.at(t) - self.center);
+        let r = (local.x() * local.x() + local.y() * local.y()).sqrt();
+        if r > self.radius {
+            return None;
+        }
+
+        // Texture coordinates are polar: the angle around the normal, then the distance out.
+        let u = (local.y().atan2(local.x()) + PI) / (2.0 * PI);
+        let v = r / self.radius;
+        Some(HitRecord::new(ray, t, self.normal, self.material).with_uv(u, v))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // Each axis extends by the radius times the sine of its angle to the normal.
+        let n = self.normal;
+        let extent = Vector3::new(
+            (1.0 - n.x() * n.x()).max(0.0).sqrt(),
+            (1.0 - n.y() * n.y()).max(0.0).sqrt(),
+            (1.0 - n.z() * n.z()).max(0.0).sqrt(),
+        ) * self.radius;
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+
+    fn sample_direction(&self, origin: Vector3, rng: &mut Pcg32) -> Option<Vector3> {
+        let offset =
+            Onb::from_w(self.normal).to_world(concentric_disk(rng.next_2d()) * self.radius);
+        let direction = self.center + offset - origin;
+        if self.normal.dot(direction).abs() < 1e-12 {
+            // Seen edge-on.
+            return None;
+        }
+        Some(direction)
+    }
+
+    fn pdf_direction(&self, origin: Vector3, direction: Vector3) -> f32 {
+        let hit = match self.hit(&Ray::new(origin, direction), 0.0, f32::INFINITY) {
+            Some(hit) => hit,
+            None => return 0.0,
+        };
+        // Convert the uniform density over the area to solid angle.
+        let distance_squared = (hit.t * direction).squared_norm();
+        let cosine = self.normal.dot(direction.normalized()).abs();
+        distance_squared / (cosine * self.area())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hit_and_sample() {
+        let disk = Disk::new(
+            Vector3::new(0.0, 2.0, 0.0),
+            Vector3::new(0.0, -3.0, 0.0),
+            1.0,
+            MaterialId(0),
+        );
+        let bounds = disk.bounding_box().unwrap();
+        assert_eq!(Vector3::new(1.0, 2.0, 1.0), bounds.max);
+
+        let up = Ray::new(Vector3::new(0.5, 0.0, 0.5), Vector3::new(0.0, 1.0, 0.0));
+        let hit = disk.hit(&up, 0.0, f32::INFINITY).unwrap();
+        assert_eq!(2.0, hit.t);
+        assert!(hit.front_face, "Expected a front-face hit, got {:?}.", hit);
+        assert!((hit.v - 0.5f32.sqrt()).abs() < 1e-6);
+        let outside = Ray::new(Vector3::new(0.8, 0.0, 0.8), Vector3::new(0.0, 1.0, 0.0));
+        assert!(disk.hit(&outside, 0.0, f32::INFINITY).is_none());
+
+        // Directly below, the density is distance^2 / area.
+        let origin = Vector3::zeros();
+        let pdf = disk.pdf_direction(origin, Vector3::new(0.0, 1.0, 0.0));
+        assert!((pdf - 4.0 / PI).abs() < 1e-5, "Got pdf {}.", pdf);
+        let mut rng = Pcg32::new(0, 0);
+        for _ in 0..100 {
+            let direction = disk.sample_direction(origin, &mut rng).unwrap();
+            let hit = disk.hit(&Ray::new(origin, direction), 0.0, f32::INFINITY);
+            assert!(hit.is_some(), "Sampled {} misses the disk.", direction);
+        }
+    }
+}
@@ -0,0 +1,53 @@
+mod cone;
+mod cuboid;
+mod cylinder;
+mod disk;
+mod plane;
+mod quadric;
+mod rect;
+mod sphere;
+mod torus;
+mod triangle;
+
+pub use cone::Cone;
+pub use cuboid::Cuboid;
+pub use cylinder::Cylinder;
+pub use disk::Disk;
+pub use plane::Plane;
+pub use quadric::Quadric;
+pub use rect::Rect;
+pub use sphere::Sphere;
+pub use torus::Torus;
+pub use triangle::Triangle;
+
+/// Return the real roots of `a t^2 + b t + c` in ascending order, or `None` if there are none.
+/// A vanishing `a` leaves a linear equation, whose single root is returned twice.
+fn solve_quadratic(a: f32, b: f32, c: f32) -> Option<(f32, f32)> {
+    if a.abs() < 1e-12 {
+        if b == 0.0 {
+            return None;
+        }
+        let t = -c / b;
+        return Some((t, t));
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    // Avoid cancellation by computing the larger-magnitude root first.
+    let q = -0.5 * (b + discriminant.sqrt().copysign(b));
+    let (r0, r1) = if q == 0.0 { (0.0, 0.0) } else { (q / a, c / q) };
+    Some((r0.min(r1), r0.max(r1)))
+}
+
+/// Return the candidate `(t, value)` with the smallest `t` in `(t_min, t_max)`.
+fn nearest<T>(
+    candidates: impl IntoIterator<Item = (f32, T)>,
+    t_min: f32,
+    t_max: f32,
+) -> Option<(f32, T)> {
+    candidates
+        .into_iter()
+        .filter(|(t, _)| *t > t_min && *t < t_max)
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+}
@@ -0,0 +1,86 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, MaterialId};
+use crate::ray::Ray;
+use crate::vector::Vector3;
+
+/// An infinite plane through `point` with unit `normal`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plane {
+    pub point: Vector3,
+    pub normal: Vector3,
+    pub material: MaterialId,
+}
+
+impl Plane {
+    /// Create a new `Plane` through `point` perpendicular to `normal`. The normal is normalized.
+    pub fn new(point: Vector3, normal: Vector3, material: MaterialId) -> Self {
+        Plane {
+            point,
+            normal: normal.normalized(),
+            material,
+        }
+    }
+}
+
+impl Hittable for Plane {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let denom = self.normal.dot(ray.direction);
+        if denom.abs() < 1e-8 {
+            // The ray is parallel to the plane.
+            return None;
+        }
+
+        let t = (self.point - ray.origin).dot(self.normal) / denom;
+        if t <= t_min || t >= t_max {
+            return None;
+        }
+
+        // Texture coordinates tile the plane in world units along an arbitrary tangent frame.
+        let helper = if self.normal.x().abs() > 0.9 {
+            Vector3::new(0.0, 1.0, 0.0)
+        } else {
+            Vector3::new(1.0, 0.0, 0.0)
+        };
+        let tangent = self.normal.cross(helper).normalized();
+        let bitangent = self.normal.cross(tangent);
+        let offset = ray.at(t) - self.point;
+        let (u, v) = (offset.dot(tangent), offset.dot(bitangent));
+
+        Some(HitRecord::new(ray, t, self.normal, self.material).with_uv(u, v))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hit() {
+        let plane = Plane::new(Vector3::zeros(), Vector3::new(0.0, 1.0, 0.0), MaterialId(0));
+        let ray = Ray::new(Vector3::new(0.0, 2.0, 0.0), Vector3::new(1.0, -1.0, 0.0));
+
+        let hit = plane.hit(&ray, 0.0, f32::INFINITY).unwrap();
+        let expected = Vector3::new(2.0, 0.0, 0.0);
+        assert_eq!(
+            expected, hit.point,
+            "Plane::hit() failed. Expected point {}, got {}.",
+            expected, hit.point
+        );
+        assert!(hit.front_face, "Expected a front-face hit, got {:?}.", hit);
+    }
+
+    #[test]
+    fn test_parallel_and_behind() {
+        let plane = Plane::new(Vector3::zeros(), Vector3::new(0.0, 1.0, 0.0), MaterialId(0));
+
+        let parallel = Ray::new(Vector3::new(0.0, 1.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert!(plane.hit(&parallel, 0.0, f32::INFINITY).is_none());
+
+        let away = Ray::new(Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        assert!(plane.hit(&away, 0.0, f32::INFINITY).is_none());
+    }
+}
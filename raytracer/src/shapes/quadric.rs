@@ -0,0 +1,183 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, MaterialId};
+use crate::ray::Ray;
+use crate::vector::Vector3;
+
+/// The solid region where the quadratic
+///
+/// `a x^2 + b y^2 + c z^2 + d xy + e yz + f xz + g x + h y + i z + j`
+///
+/// is at most zero, clipped to the box `bounds`. The `coefficients` are `[a, b, ..., j]`.
+///
+/// Quadrics cover ellipsoids, paraboloids, hyperboloids, and elliptic cylinders and cones; the
+/// bounds keep the unbounded ones finite, and become flat faces wherever they cut the solid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quadric {
+    pub coefficients: [f32; 10],
+    pub bounds: Aabb,
+    pub material: MaterialId,
+}
+
+impl Quadric {
+    /// Create a new `Quadric` with `coefficients`, clipped to `bounds`.
+    pub fn new(coefficients: [f32; 10], bounds: Aabb, material: MaterialId) -> Self {
+        Quadric {
+            coefficients,
+            bounds,
+            material,
+        }
+    }
+
+    /// Return the value of the quadratic at `p`, which is negative inside the solid.
+    pub fn eval(&self, p: Vector3) -> f32 {
+        let [a, b, c, d, e, f, g, h, i, j] = self.coefficients;
+        let (x, y, z) = (p.x(), p.y(), p.z());
+        a * x * x
+            + b * y * y
+            + c * z * z
+            + d * x * y
+            + e * y * z
+            + f * x * z
+            + g * x
+            + h * y
+            + i * z
+            + j
+    }
+
+    /// Return the gradient of the quadratic at `p`, which points out of the solid.
+    pub fn gradient(&self, p: Vector3) -> Vector3 {
+        let [a, b, c, d, e, f, g, h, i, _] = self.coefficients;
+        let (x, y, z) = (p.x(), p.y(), p.z());
+        Vector3::new(
+            2.0 * a * x + d * y + f * z + g,
+            2.0 * b * y + d * x + e * z + h,
+            2.0 * c * z + e * y + f * x + i,
+        )
+    }
+
+    /// Return the intervals of the ray parameter over which `ray` is inside the unclipped
+    /// solid, which may be unbounded.
+    fn inside(&self, ray: &Ray) -> Vec<(f32, f32)> {
+        let [a, b, c, d, e, f, g, h, i, _] = self.coefficients;
+        let (o, v) = (ray.origin, ray.direction);
+        // Substitute o + t v: the quadratic part of v, twice the bilinear form of o and v plus
+        // the linear part of v, and the value at o.
+        let qa = a * v.x() * v.x()
+            + b * v.y() * v.y()
+            + c * v.z() * v.z()
+            + d * v.x() * v.y()
+            + e * v.y() * v.z()
+            + f * v.x() * v.z();
+        let qb = 2.0 * (a * o.x() * v.x() + b * o.y() * v.y() + c * o.z() * v.z())
+            + d * (o.x() * v.y() + o.y() * v.x())
+            + e * (o.y() * v.z() + o.z() * v.y())
+            + f * (o.x() * v.z() + o.z() * v.x())
+            + g * v.x()
+            + h * v.y()
+            + i * v.z();
+        let qc = self.eval(o);
+
+        let (inf, neg_inf) = (f32::INFINITY, f32::NEG_INFINITY);
+        if qa.abs() < 1e-12 {
+            return match qb {
+                _ if qb == 0.0 && qc <= 0.0 => vec![(neg_inf, inf)],
+                _ if qb == 0.0 => Vec::new(),
+                _ if qb > 0.0 => vec![(neg_inf, -qc / qb)],
+                _ => vec![(-qc / qb, inf)],
+            };
+        }
+        match super::solve_quadratic(qa, qb, qc) {
+            // No sign change: inside everywhere or nowhere.
+            None if qa < 0.0 => vec![(neg_inf, inf)],
+            None => Vec::new(),
+            Some((t0, t1)) if qa > 0.0 => vec![(t0, t1)],
+            Some((t0, t1)) => vec![(neg_inf, t0), (t1, inf)],
+        }
+    }
+}
+
+impl Hittable for Quadric {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (enter, exit) = super::cuboid::slabs(self.bounds.min, self.bounds.max, ray)?;
+
+        // Each interval inside both the solid and the box starts and ends on the surface:
+        // on a box face where the box clipped it, and on the quadric otherwise.
+        let crossings = self.inside(ray).into_iter().flat_map(|(t0, t1)| {
+            let start = if t0 <= enter.0 {
+                enter
+            } else {
+                (t0, self.gradient(ray.at(t0)))
+            };
+            let end = if t1 >= exit.0 {
+                exit
+            } else {
+                (t1, self.gradient(ray.at(t1)))
+            };
+            (start.0 <= end.0).then_some([start, end])
+        });
+        let (t, normal) = super::nearest(crossings.flatten(), t_min, t_max)?;
+        Some(HitRecord::new(ray, t, normal.normalized(), self.material))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounds)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ellipsoid() {
+        // x^2 + y^2 / 4 + z^2 <= 1, clipped to a box that cuts off its top.
+        let quadric = Quadric::new(
+            [1.0, 0.25, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -1.0],
+            Aabb::new(-Vector3::ones() * 3.0, Vector3::new(3.0, 1.0, 3.0)),
+            MaterialId(0),
+        );
+        assert!(quadric.eval(Vector3::zeros()) < 0.0);
+
+        let ray = Ray::new(Vector3::new(-5.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        let hit = quadric.hit(&ray, 0.0, f32::INFINITY).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-5, "Expected t = 4, got {}.", hit.t);
+        assert!((hit.normal - Vector3::new(-1.0, 0.0, 0.0)).norm() < 1e-5);
+        assert!(hit.front_face);
+
+        // Down from above, the ray enters through the flat clipped top.
+        let down = Ray::new(Vector3::new(0.0, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let hit = quadric.hit(&down, 0.0, f32::INFINITY).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-5, "Expected t = 4, got {}.", hit.t);
+        assert_eq!(Vector3::new(0.0, 1.0, 0.0), hit.normal);
+        // And leaves through the curved bottom.
+        let hit = quadric.hit(&down, 4.5, f32::INFINITY).unwrap();
+        assert!((hit.t - 7.0).abs() < 1e-5, "Expected t = 7, got {}.", hit.t);
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn test_hyperboloid() {
+        // x^2 + z^2 - y^2 >= 1 is outside a hyperboloid of one sheet; the solid is its
+        // negation, the region outside the waist, clipped to a box.
+        let quadric = Quadric::new(
+            [-1.0, 1.0, -1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0],
+            Aabb::new(-Vector3::ones() * 2.0, Vector3::ones() * 2.0),
+            MaterialId(0),
+        );
+        // Along the x axis the ray is inside from the box face to the waist, then again from
+        // the waist to the far face.
+        let ray = Ray::new(Vector3::new(-5.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        let mut t = 0.0;
+        for (expected, front_face) in [(3.0, true), (4.0, false), (6.0, true), (7.0, false)] {
+            let hit = quadric.hit(&ray, t, f32::INFINITY).unwrap();
+            assert!(
+                (hit.t - expected).abs() < 1e-5,
+                "Expected t = {}, got {}.",
+                expected,
+                hit.t
+            );
+            assert_eq!(front_face, hit.front_face);
+            t = hit.t + 1e-3;
+        }
+    }
+}
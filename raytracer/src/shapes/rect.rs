@@ -0,0 +1,129 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, MaterialId};
+use crate::ray::Ray;
+use crate::rng::Pcg32;
+use crate::vector::Vector3;
+
+/// A parallelogram with one vertex at `corner` and sides `edge_u` and `edge_v`. Its front face
+/// is the one `edge_u.cross(edge_v)` points out of.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub corner: Vector3,
+    pub edge_u: Vector3,
+    pub edge_v: Vector3,
+    pub material: MaterialId,
+}
+
+impl Rect {
+    /// Create a new `Rect` spanning `corner + a * edge_u + b * edge_v` for `a`, `b` in `[0, 1]`.
+    pub fn new(corner: Vector3, edge_u: Vector3, edge_v: Vector3, material: MaterialId) -> Self {
+        Rect {
+            corner,
+            edge_u,
+            edge_v,
+            material,
+        }
+    }
+
+    /// Return the unit normal of the front face.
+    pub fn normal(&self) -> Vector3 {
+        self.edge_u.cross(self.edge_v).normalized()
+    }
+
+    /// Return the area of this rectangle.
+    pub fn area(&self) -> f32 {
+        self.edge_u.cross(self.edge_v).norm()
+    }
+}
+
+impl Hittable for Rect {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let n = self.edge_u.cross(self.edge_v);
+        let denom = n.dot(ray.direction);
+        if denom.abs() < 1e-12 {
+            // The ray is parallel to the rectangle.
+            return None;
+        }
+
+        let t = (self.corner - ray.origin).dot(n) / denom;
+        if t <= t_min || t >= t_max {
+            return None;
+        }
+
+        // Express the hit point in the edge basis using the dual vectors v x n / |n|^2 and
+        // n x u / |n|^2.
+        let offset = ray.at(t) - self.corner;
+        let inv = 1.0 / n.squared_norm();
+        let a = self.edge_v.cross(n).dot(offset) * inv;
+        let b = n.cross(self.edge_u).dot(offset) * inv;
+        if !(0.0..=1.0).contains(&a) || !(0.0..=1.0).contains(&b) {
+            return None;
+        }
+
+        Some(HitRecord::new(ray, t, n.normalized(), self.material).with_uv(a, b))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::from_points([
+            self.corner,
+            self.corner + self.edge_u,
+            self.corner + self.edge_v,
+            self.corner + self.edge_u + self.edge_v,
+        ]))
+    }
+
+    fn sample_direction(&self, origin: Vector3, rng: &mut Pcg32) -> Option<Vector3> {
+        let [a, b] = rng.next_2d();
+        let direction = self.corner + a * self.edge_u + b * self.edge_v - origin;
+        if self.normal().dot(direction).abs() < 1e-12 {
+            // Seen edge-on.
+            return None;
+        }
+        Some(direction)
+    }
+
+    fn pdf_direction(&self, origin: Vector3, direction: Vector3) -> f32 {
+        let hit = match self.hit(&Ray::new(origin, direction), 0.0, f32::INFINITY) {
+            Some(hit) => hit,
+            None => return 0.0,
+        };
+        // Convert the uniform density over the area to solid angle.
+        let distance_squared = (hit.t * direction).squared_norm();
+        let cosine = self.normal().dot(direction.normalized()).abs();
+        distance_squared / (cosine * self.area())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hit_and_sample() {
+        let rect = Rect::new(
+            Vector3::new(-1.0, 2.0, -1.0),
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 2.0),
+            MaterialId(0),
+        );
+        assert_eq!(Vector3::new(0.0, -1.0, 0.0), rect.normal());
+        assert_eq!(4.0, rect.area());
+
+        let up = Ray::new(Vector3::new(0.5, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let hit = rect.hit(&up, 0.0, f32::INFINITY).unwrap();
+        assert_eq!(2.0, hit.t);
+        assert!(hit.front_face, "Expected to hit the downward face.");
+        assert_eq!((0.75, 0.5), (hit.u, hit.v));
+
+        let outside = Ray::new(Vector3::new(1.5, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        assert!(rect.hit(&outside, 0.0, f32::INFINITY).is_none());
+
+        // Directly below the center, the solid-angle density is d^2 / area.
+        assert!((rect.pdf_direction(Vector3::zeros(), up.direction) - 1.0).abs() < 1e-6);
+        let mut rng = Pcg32::new(0, 0);
+        for _ in 0..100 {
+            let direction = rect.sample_direction(Vector3::zeros(), &mut rng).unwrap();
+            assert!(rect.pdf_direction(Vector3::zeros(), direction) > 0.0);
+        }
+    }
+}
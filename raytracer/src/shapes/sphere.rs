@@ -0,0 +1,166 @@
+use std::f32::consts::PI;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, MaterialId};
+use crate::ray::Ray;
+use crate::rng::Pcg32;
+use crate::sampling::{uniform_cone, Onb};
+use crate::vector::Vector3;
+
+/// A sphere defined by its `center` and `radius`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sphere {
+    pub center: Vector3,
+    pub radius: f32,
+    pub material: MaterialId,
+}
+
+impl Sphere {
+    /// Create a new `Sphere` at `center` with the given `radius` and `material`.
+    pub fn new(center: Vector3, radius: f32, material: MaterialId) -> Self {
+        Sphere {
+            center,
+            radius,
+            material,
+        }
+    }
+
+    /// Return the cosine of the half-angle of the cone the sphere subtends from a point offset
+    /// by `-to_center` from its center, or `None` if that point is inside the sphere.
+    fn cos_max(&self, to_center: Vector3) -> Option<f32> {
+        let ratio = self.radius * self.radius / to_center.squared_norm();
+        if ratio >= 1.0 {
+            return None;
+        }
+        // Clamp away from 1 so tiny, distant spheres keep a finite density.
+        Some((1.0 - ratio).sqrt().min(1.0 - 1e-7))
+    }
+}
+
+impl Hittable for Sphere {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        // Solve |o + td - c|^2 = r^2 for t, using the half-b form of the quadratic formula.
+        let oc = ray.origin - self.center;
+        let a = ray.direction.squared_norm();
+        let half_b = oc.dot(ray.direction);
+        let c = oc.squared_norm() - self.radius * self.radius;
+
+        let discriminant = half_b * half_b - a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let sqrt_d = discriminant.sqrt();
+
+        // Try the nearer root first.
+        let mut root = (-half_b - sqrt_d) / a;
+        if root <= t_min || root >= t_max {
+            root = (-half_b + sqrt_d) / a;
+            if root <= t_min || root >= t_max {
+                return None;
+            }
+        }
+
+        let outward_normal = (ray.at(root) - self.center) * (1.0 / self.radius);
+        let (u, v) = sphere_uv(outward_normal);
+        Some(HitRecord::new(ray, root, outward_normal, self.material).with_uv(u, v))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = Vector3::new(self.radius, self.radius, self.radius);
+        Some(Aabb::new(self.center - r, self.center + r))
+    }
+
+    fn sample_direction(&self, origin: Vector3, rng: &mut Pcg32) -> Option<Vector3> {
+        // Sample the cone of directions subtended by the sphere, which wastes no samples on
+        // the far side.
+        let to_center = self.center - origin;
+        let cos_max = self.cos_max(to_center)?;
+        let onb = Onb::from_w(to_center.normalized());
+        Some(onb.to_world(uniform_cone(cos_max, rng.next_2d())))
+    }
+
+    fn pdf_direction(&self, origin: Vector3, direction: Vector3) -> f32 {
+        let cos_max = match self.cos_max(self.center - origin) {
+            Some(cos_max) => cos_max,
+            None => return 0.0,
+        };
+        if self
+            .hit(&Ray::new(origin, direction), 0.0, f32::INFINITY)
+            .is_none()
+        {
+            return 0.0;
+        }
+        1.0 / (2.0 * PI * (1.0 - cos_max))
+    }
+}
+
+/// Return the texture coordinates of the point with unit normal `n` on a sphere: `u` is the
+/// longitude, starting at -x and increasing towards +z, and `v` the latitude from the -y pole.
+fn sphere_uv(n: Vector3) -> (f32, f32) {
+    let theta = (-n.y()).clamp(-1.0, 1.0).acos();
+    let phi = (-n.z()).atan2(n.x()) + PI;
+    (phi / (2.0 * PI), theta / PI)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hit_outside() {
+        let sphere = Sphere::new(Vector3::new(0.0, 0.0, -3.0), 1.0, MaterialId(0));
+        let ray = Ray::new(Vector3::zeros(), Vector3::new(0.0, 0.0, -1.0));
+
+        let hit = sphere.hit(&ray, 0.0, f32::INFINITY).unwrap();
+        let expected = Vector3::new(0.0, 0.0, -2.0);
+        assert_eq!(
+            expected, hit.point,
+            "Sphere::hit() failed. Expected point {}, got {}.",
+            expected, hit.point
+        );
+        assert!(hit.front_face, "Expected a front-face hit, got {:?}.", hit);
+        assert_eq!(Vector3::new(0.0, 0.0, 1.0), hit.normal);
+    }
+
+    #[test]
+    fn test_hit_inside() {
+        let sphere = Sphere::new(Vector3::zeros(), 2.0, MaterialId(0));
+        let ray = Ray::new(Vector3::zeros(), Vector3::new(1.0, 0.0, 0.0));
+
+        let hit = sphere.hit(&ray, 0.0, f32::INFINITY).unwrap();
+        assert_eq!(2.0, hit.t, "Expected t = 2, got {}.", hit.t);
+        assert!(!hit.front_face, "Expected a back-face hit, got {:?}.", hit);
+        assert_eq!(Vector3::new(-1.0, 0.0, 0.0), hit.normal);
+    }
+
+    #[test]
+    fn test_miss() {
+        let sphere = Sphere::new(Vector3::new(0.0, 3.0, -3.0), 1.0, MaterialId(0));
+        let ray = Ray::new(Vector3::zeros(), Vector3::new(0.0, 0.0, -1.0));
+        assert!(sphere.hit(&ray, 0.0, f32::INFINITY).is_none());
+
+        // Behind the ray origin.
+        let sphere = Sphere::new(Vector3::new(0.0, 0.0, 3.0), 1.0, MaterialId(0));
+        assert!(sphere.hit(&ray, 0.0, f32::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_uv() {
+        let cases = [
+            (Vector3::new(1.0, 0.0, 0.0), (0.5, 0.5)),
+            (Vector3::new(0.0, 1.0, 0.0), (0.5, 1.0)),
+            (Vector3::new(0.0, 0.0, 1.0), (0.25, 0.5)),
+            (Vector3::new(0.0, 0.0, -1.0), (0.75, 0.5)),
+        ];
+        for (n, (u, v)) in cases {
+            let uv = sphere_uv(n);
+            assert!(
+                (uv.0 - u).abs() < 1e-6 && (uv.1 - v).abs() < 1e-6,
+                "sphere_uv({}) failed. Expected {:?}, got {:?}.",
+                n,
+                (u, v),
+                uv
+            );
+        }
+    }
+}
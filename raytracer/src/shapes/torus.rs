@@ -0,0 +1,226 @@
+use std::f32::consts::PI;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, MaterialId};
+use crate::ray::Ray;
+use crate::sampling::Onb;
+use crate::vector::Vector3;
+
+/// A solid ring around `center`: the points within `minor_radius` of the circle of
+/// `major_radius` perpendicular to the unit `axis`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Torus {
+    pub center: Vector3,
+    pub axis: Vector3,
+    pub major_radius: f32,
+    pub minor_radius: f32,
+    pub material: MaterialId,
+}
+
+impl Torus {
+    /// Create a new `Torus` around `center`, with its hole along `axis`. The axis is normalized.
+    pub fn new(
+        center: Vector3,
+        axis: Vector3,
+        major_radius: f32,
+        minor_radius: f32,
+        material: MaterialId,
+    ) -> Self {
+        Torus {
+            center,
+            axis: axis.normalized(),
+            major_radius,
+            minor_radius,
+            material,
+        }
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        // Work in a frame with the axis along +z from the center, in double precision since
+        // the quartic is badly conditioned.
+        let onb = Onb::from_w(self.axis);
+        let to_f64 = |v: Vector3| [v.x() as f64, v.y() as f64, v.z() as f64];
+        let o = to_f64(onb.to_local(ray.origin - self.center));
+        let d = to_f64(onb.to_local(ray.direction));
+        let dot = |a: [f64; 3], b: [f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+        let big_r2 = (self.major_radius as f64).powi(2);
+        let small_r2 = (self.minor_radius as f64).powi(2);
+
+        // Clip the ray to a slightly enlarged bounding sphere, which the torus touches at its
+        // equator, and solve relative to the ray's closest approach to the center to keep the
+        // coefficients small.
+        let dd = dot(d, d);
+        let shift = -dot(o, d) / dd;
+        let o = [
+            o[0] + shift * d[0],
+            o[1] + shift * d[1],
+            o[2] + shift * d[2],
+        ];
+        let bound = 1.001 * (self.major_radius + self.minor_radius) as f64;
+        let half_chord2 = (bound * bound - dot(o, o)) / dd;
+        if half_chord2 < 0.0 {
+            return None;
+        }
+        let half_chord = half_chord2.sqrt();
+        let lo = (-half_chord).max(t_min as f64 - shift);
+        let hi = half_chord.min(t_max as f64 - shift);
+        if lo >= hi {
+            return None;
+        }
+
+        // Substitute o + s d into (|p|^2 + R^2 - r^2)^2 = 4 R^2 (x^2 + y^2).
+        let od = dot(o, d);
+        let k = dot(o, o) + big_r2 - small_r2;
+        let coefficients = [
+            k * k - 4.0 * big_r2 * (o[0] * o[0] + o[1] * o[1]),
+            4.0 * od * k - 8.0 * big_r2 * (o[0] * d[0] + o[1] * d[1]),
+            4.0 * od * od + 2.0 * dd * k - 4.0 * big_r2 * (d[0] * d[0] + d[1] * d[1]),
+            4.0 * dd * od,
+            dd * dd,
+        ];
+        let s = real_roots(&coefficients, lo, hi)
+            .into_iter()
+            .find(|&s| s + shift > t_min as f64)?;
+        let t = (s + shift) as f32;
+        if t <= t_min || t >= t_max {
+            return None;
+        }
+
+        // The normal points away from the nearest point on the ring. Texture coordinates are
+        // the angles around the axis and around the tube.
+        let p = onb.to_local(ray.at(t) - self.center);
+        let ring = (p.x() * p.x() + p.y() * p.y()).sqrt();
+        let to_ring = Vector3::new(p.x(), p.y(), 0.0) * (self.major_radius / ring.max(1e-12));
+        let normal = (p - to_ring).normalized();
+        let u = (p.y().atan2(p.x()) + PI) / (2.0 * PI);
+        let v = (p.z().atan2(ring - self.major_radius) + PI) / (2.0 * PI);
+        Some(HitRecord::new(ray, t, onb.to_world(normal), self.material).with_uv(u, v))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // The ring's extent along each axis, widened by the tube.
+        let n = self.axis;
+        let extent = Vector3::new(
+            (1.0 - n.x() * n.x()).max(0.0).sqrt(),
+            (1.0 - n.y() * n.y()).max(0.0).sqrt(),
+            (1.0 - n.z() * n.z()).max(0.0).sqrt(),
+        ) * self.major_radius
+            + Vector3::ones() * self.minor_radius;
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+}
+
+/// Return the real roots in `(lo, hi)` of the polynomial with `coefficients`, lowest degree
+/// first, in ascending order.
+///
+/// The roots of the derivative split the interval into pieces on which the polynomial is
+/// monotonic, and each piece whose ends differ in sign holds exactly one root, which is found by
+/// bisection. Roots of even multiplicity, where the polynomial only touches zero, are missed;
+/// for ray intersection they are grazing hits.
+fn real_roots(coefficients: &[f64], lo: f64, hi: f64) -> Vec<f64> {
+    let degree = match coefficients.iter().rposition(|&c| c != 0.0) {
+        Some(degree) => degree,
+        None => return Vec::new(),
+    };
+    let coefficients = &coefficients[..=degree];
+    let eval = |x: f64| coefficients.iter().rev().fold(0.0, |sum, &c| sum * x + c);
+    if degree == 0 {
+        return Vec::new();
+    }
+
+    let derivative: Vec<f64> = coefficients[1..]
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| (i + 1) as f64 * c)
+        .collect();
+    let mut bounds = vec![lo];
+    bounds.extend(real_roots(&derivative, lo, hi));
+    bounds.push(hi);
+
+    let mut roots = Vec::new();
+    for pair in bounds.windows(2) {
+        let (mut a, mut b) = (pair[0], pair[1]);
+        let (fa, fb) = (eval(a), eval(b));
+        if fa == 0.0 && a > lo {
+            roots.push(a);
+            continue;
+        }
+        // A root exactly at `b` is picked up by the next piece.
+        if fb == 0.0 || fa.signum() == fb.signum() {
+            continue;
+        }
+        let rising = fb > fa;
+        for _ in 0..64 {
+            let mid = 0.5 * (a + b);
+            if mid <= a || mid >= b {
+                break;
+            }
+            if (eval(mid) < 0.0) == rising {
+                a = mid;
+            } else {
+                b = mid;
+            }
+        }
+        roots.push(0.5 * (a + b));
+    }
+    roots
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_real_roots() {
+        // (x - 1)(x - 2)(x + 3)(x - 4) = x^4 - 4x^3 - 7x^2 + 34x - 24.
+        let roots = real_roots(&[-24.0, 34.0, -7.0, -4.0, 1.0], -10.0, 10.0);
+        let expected = [-3.0, 1.0, 2.0, 4.0];
+        assert_eq!(expected.len(), roots.len(), "Got {:?}.", roots);
+        for (root, expected) in roots.iter().zip(expected) {
+            assert!((root - expected).abs() < 1e-9, "Got {:?}.", roots);
+        }
+        assert_eq!(vec![2.0], real_roots(&[-2.0, 1.0], 0.0, 5.0));
+        assert!(real_roots(&[1.0, 0.0, 1.0], -10.0, 10.0).is_empty());
+    }
+
+    #[test]
+    fn test_hit() {
+        let torus = Torus::new(
+            Vector3::zeros(),
+            Vector3::new(0.0, 1.0, 0.0),
+            2.0,
+            0.5,
+            MaterialId(0),
+        );
+        assert_eq!(
+            Aabb::new(Vector3::new(-2.5, -0.5, -2.5), Vector3::new(2.5, 0.5, 2.5)),
+            torus.bounding_box().unwrap()
+        );
+
+        // Along a diameter, the ray crosses the tube twice on each side.
+        let ray = Ray::new(Vector3::new(-10.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        let mut t = 0.0;
+        for (expected, front_face) in [(7.5, true), (8.5, false), (11.5, true), (12.5, false)] {
+            let hit = torus.hit(&ray, t, f32::INFINITY).unwrap();
+            assert!(
+                (hit.t - expected).abs() < 1e-4,
+                "Expected t = {}, got {}.",
+                expected,
+                hit.t
+            );
+            assert_eq!(front_face, hit.front_face);
+            t = hit.t + 1e-3;
+        }
+        assert!(torus.hit(&ray, t, f32::INFINITY).is_none());
+
+        // Straight down through the hole, and down onto the top of the tube.
+        let hole = Ray::new(Vector3::new(0.0, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        assert!(torus.hit(&hole, 0.0, f32::INFINITY).is_none());
+        let top = Ray::new(Vector3::new(0.0, 5.0, 2.0), Vector3::new(0.0, -1.0, 0.0));
+        let hit = torus.hit(&top, 0.0, f32::INFINITY).unwrap();
+        assert!((hit.t - 4.5).abs() < 1e-4);
+        assert!((hit.normal - Vector3::new(0.0, 1.0, 0.0)).norm() < 1e-4);
+    }
+}
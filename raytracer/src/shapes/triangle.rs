@@ -0,0 +1,194 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, MaterialId};
+use crate::ray::Ray;
+use crate::rng::Pcg32;
+use crate::sampling::uniform_triangle;
+use crate::vector::Vector3;
+
+/// A triangle with optional per-vertex shading normals and texture coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Triangle {
+    pub vertices: [Vector3; 3],
+    /// Per-vertex normals, interpolated across the face for smooth shading.
+    pub normals: Option<[Vector3; 3]>,
+    /// Per-vertex texture coordinates.
+    pub uvs: Option<[[f32; 2]; 3]>,
+    pub material: MaterialId,
+}
+
+impl Triangle {
+    /// Create a new flat-shaded `Triangle` with vertices `a`, `b`, `c` in counter-clockwise order
+    /// when viewed from the front.
+    pub fn new(a: Vector3, b: Vector3, c: Vector3, material: MaterialId) -> Self {
+        Triangle {
+            vertices: [a, b, c],
+            normals: None,
+            uvs: None,
+            material,
+        }
+    }
+
+    /// Set the per-vertex shading normals of this triangle. They are normalized.
+    pub fn with_normals(mut self, normals: [Vector3; 3]) -> Self {
+        self.normals = Some(normals.map(Vector3::normalized));
+        self
+    }
+
+    /// Set the per-vertex texture coordinates of this triangle.
+    pub fn with_uvs(mut self, uvs: [[f32; 2]; 3]) -> Self {
+        self.uvs = Some(uvs);
+        self
+    }
+
+    /// Return the unit geometric normal of this triangle, facing the side from which the vertices
+    /// appear counter-clockwise.
+    pub fn geometric_normal(&self) -> Vector3 {
+        let [a, b, c] = self.vertices;
+        (b - a).cross(c - a).normalized()
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        // Möller–Trumbore: solve o + td = (1 - u - v)a + ub + vc by Cramer's rule.
+        let [a, b, c] = self.vertices;
+        let edge1 = b - a;
+        let edge2 = c - a;
+
+        let p = ray.direction.cross(edge2);
+        let det = edge1.dot(p);
+        if det.abs() < 1e-12 {
+            // The ray is parallel to the triangle.
+            return None;
+        }
+        let inv_det = 1.0 / det;
+
+        let s = ray.origin - a;
+        let u = s.dot(p) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = s.cross(edge1);
+        let v = ray.direction.dot(q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t = edge2.dot(q) * inv_det;
+        if t <= t_min || t >= t_max {
+            return None;
+        }
+
+        let w = 1.0 - u - v;
+        let mut hit = HitRecord::new(ray, t, edge1.cross(edge2).normalized(), self.material);
+        if let Some([na, nb, nc]) = self.normals {
+            // Keep the shading normal on the same side as the geometric one.
+            let shading = (w * na + u * nb + v * nc).normalized();
+            hit.normal = if hit.front_face { shading } else { -shading };
+        }
+
+        let (tex_u, tex_v) = match self.uvs {
+            Some([ta, tb, tc]) => (
+                w * ta[0] + u * tb[0] + v * tc[0],
+                w * ta[1] + u * tb[1] + v * tc[1],
+            ),
+            None => (u, v),
+        };
+        Some(hit.with_uv(tex_u, tex_v))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::from_points(self.vertices))
+    }
+
+    fn sample_direction(&self, origin: Vector3, rng: &mut Pcg32) -> Option<Vector3> {
+        let [a, b, c] = self.vertices;
+        let [u, v] = uniform_triangle(rng.next_2d());
+        let direction = (1.0 - u - v) * a + u * b + v * c - origin;
+        if self.geometric_normal().dot(direction).abs() < 1e-12 {
+            // Seen edge-on.
+            return None;
+        }
+        Some(direction)
+    }
+
+    fn pdf_direction(&self, origin: Vector3, direction: Vector3) -> f32 {
+        let hit = match self.hit(&Ray::new(origin, direction), 0.0, f32::INFINITY) {
+            Some(hit) => hit,
+            None => return 0.0,
+        };
+        // Convert the uniform density over the area to solid angle.
+        let [a, b, c] = self.vertices;
+        let area = 0.5 * (b - a).cross(c - a).norm();
+        let distance_squared = (hit.t * direction).squared_norm();
+        let cosine = self.geometric_normal().dot(direction.normalized()).abs();
+        distance_squared / (cosine * area)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn unit_triangle() -> Triangle {
+        Triangle::new(
+            Vector3::zeros(),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            MaterialId(0),
+        )
+    }
+
+    #[test]
+    fn test_hit() {
+        let triangle = unit_triangle();
+        let ray = Ray::new(Vector3::new(0.25, 0.25, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit = triangle.hit(&ray, 0.0, f32::INFINITY).unwrap();
+
+        assert_eq!(1.0, hit.t, "Expected t = 1, got {}.", hit.t);
+        assert_eq!(Vector3::new(0.25, 0.25, 0.0), hit.point);
+        assert!(hit.front_face, "Expected a front-face hit, got {:?}.", hit);
+        assert_eq!(Vector3::new(0.0, 0.0, 1.0), hit.normal);
+        assert_eq!((0.25, 0.25), (hit.u, hit.v));
+
+        let from_behind = Ray::new(Vector3::new(0.25, 0.25, -1.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = triangle.hit(&from_behind, 0.0, f32::INFINITY).unwrap();
+        assert!(!hit.front_face, "Expected a back-face hit, got {:?}.", hit);
+        assert_eq!(Vector3::new(0.0, 0.0, -1.0), hit.normal);
+    }
+
+    #[test]
+    fn test_miss() {
+        let triangle = unit_triangle();
+        let outside = Ray::new(Vector3::new(0.75, 0.75, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!(triangle.hit(&outside, 0.0, f32::INFINITY).is_none());
+
+        let parallel = Ray::new(Vector3::new(-1.0, 0.25, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert!(triangle.hit(&parallel, 0.0, f32::INFINITY).is_none());
+
+        let too_far = Ray::new(Vector3::new(0.25, 0.25, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!(triangle.hit(&too_far, 0.0, 0.5).is_none());
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let tilted = Vector3::new(1.0, 0.0, 1.0);
+        let triangle = unit_triangle()
+            .with_normals([Vector3::new(0.0, 0.0, 1.0), tilted, tilted])
+            .with_uvs([[0.0, 0.0], [1.0, 0.0], [0.0, 2.0]]);
+
+        let ray = Ray::new(Vector3::new(0.5, 0.5, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit = triangle.hit(&ray, 0.0, f32::INFINITY).unwrap();
+        assert!(
+            (hit.normal - Vector3::unit(1.0, 0.0, 1.0)).norm() < 1e-6,
+            "Expected the interpolated normal, got {}.",
+            hit.normal
+        );
+        assert_eq!((0.5, 1.0), (hit.u, hit.v));
+
+        let bounds = triangle.bounding_box().unwrap();
+        assert_eq!(Vector3::zeros(), bounds.min);
+        assert_eq!(Vector3::new(1.0, 1.0, 0.0), bounds.max);
+    }
+}
@@ -0,0 +1,365 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::framebuffer::{extension, Framebuffer, ImageError};
+use crate::rng::Pcg32;
+use crate::vector::{Color, Vector3};
+
+/// A color that varies over a surface.
+pub trait Texture: Send + Sync {
+    /// Return the color at texture coordinates `u`, `v` and world-space `point`.
+    fn value(&self, u: f32, v: f32, point: Vector3) -> Color;
+}
+
+/// A texture of a single color.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SolidColor {
+    pub color: Color,
+}
+
+impl SolidColor {
+    /// Create a new `SolidColor` texture of `color`.
+    pub fn new(color: Color) -> Self {
+        SolidColor { color }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f32, _v: f32, _point: Vector3) -> Color {
+        self.color
+    }
+}
+
+/// A texture multiplied by a constant color.
+pub struct Scaled {
+    pub texture: Arc<dyn Texture>,
+    pub factor: Color,
+}
+
+impl Scaled {
+    /// Create a new `Scaled` texture multiplying `texture` by `factor` component-wise.
+    pub fn new(texture: Arc<dyn Texture>, factor: Color) -> Self {
+        Scaled { texture, factor }
+    }
+}
+
+impl Texture for Scaled {
+    fn value(&self, u: f32, v: f32, point: Vector3) -> Color {
+        self.texture.value(u, v, point).cwise_mul(self.factor)
+    }
+}
+
+/// A three-dimensional checkerboard alternating between two textures in cubes of side
+/// `1 / scale`.
+pub struct Checker {
+    pub even: Arc<dyn Texture>,
+    pub odd: Arc<dyn Texture>,
+    pub scale: f32,
+}
+
+impl Checker {
+    /// Create a new `Checker` alternating between `even` and `odd`.
+    pub fn new(even: Arc<dyn Texture>, odd: Arc<dyn Texture>, scale: f32) -> Self {
+        Checker { even, odd, scale }
+    }
+
+    /// Create a new `Checker` alternating between two colors.
+    pub fn from_colors(even: Color, odd: Color, scale: f32) -> Self {
+        Checker::new(
+            Arc::new(SolidColor::new(even)),
+            Arc::new(SolidColor::new(odd)),
+            scale,
+        )
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, u: f32, v: f32, point: Vector3) -> Color {
+        let p = point * self.scale;
+        let cell = p.x().floor() as i64 + p.y().floor() as i64 + p.z().floor() as i64;
+        if cell.rem_euclid(2) == 0 {
+            self.even.value(u, v, point)
+        } else {
+            self.odd.value(u, v, point)
+        }
+    }
+}
+
+const PERLIN_POINTS: usize = 256;
+
+/// Ken Perlin's gradient noise, with random unit gradients on the integer lattice.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Perlin {
+    gradients: Vec<Vector3>,
+    perm: [Vec<usize>; 3],
+}
+
+impl Perlin {
+    /// Create a new `Perlin` noise generator whose lattice is drawn from `seed`.
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Pcg32::new(seed, 0);
+        let gradients = (0..PERLIN_POINTS)
+            .map(|_| loop {
+                let v = Vector3::new(
+                    2.0 * rng.next_f32() - 1.0,
+                    2.0 * rng.next_f32() - 1.0,
+                    2.0 * rng.next_f32() - 1.0,
+                );
+                let n = v.squared_norm();
+                if n > 1e-6 && n <= 1.0 {
+                    break v.normalized();
+                }
+            })
+            .collect();
+        let mut permutation = || {
+            let mut p: Vec<usize> = (0..PERLIN_POINTS).collect();
+            // Fisher-Yates shuffle.
+            for i in (1..p.len()).rev() {
+                let j = (rng.next_u32() as usize) % (i + 1);
+                p.swap(i, j);
+            }
+            p
+        };
+        let perm = [permutation(), permutation(), permutation()];
+        Perlin { gradients, perm }
+    }
+
+    /// Return the noise at `p`, in roughly `[-1, 1]`.
+    pub fn noise(&self, p: Vector3) -> f32 {
+        let floor = Vector3::new(p.x().floor(), p.y().floor(), p.z().floor());
+        let f = p - floor;
+        let cell = [floor.x() as i64, floor.y() as i64, floor.z() as i64];
+        let mask = PERLIN_POINTS as i64 - 1;
+
+        // Hermite smoothing hides the lattice.
+        let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+        let (su, sv, sw) = (smooth(f.x()), smooth(f.y()), smooth(f.z()));
+
+        let mut sum = 0.0;
+        for corner in 0..8 {
+            let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let index = (0..3)
+                .map(|axis| self.perm[axis][((cell[axis] + offset[axis] as i64) & mask) as usize])
+                .fold(0, |acc, i| acc ^ i);
+            let weight = Vector3::new(
+                f.x() - offset[0] as f32,
+                f.y() - offset[1] as f32,
+                f.z() - offset[2] as f32,
+            );
+            let blend = |s: f32, bit: usize| if bit == 1 { s } else { 1.0 - s };
+            sum += blend(su, offset[0])
+                * blend(sv, offset[1])
+                * blend(sw, offset[2])
+                * self.gradients[index].dot(weight);
+        }
+        sum
+    }
+
+    /// Return the sum of `depth` octaves of noise at `p`, each at twice the frequency and half
+    /// the amplitude of the last, folded to be non-negative.
+    pub fn turbulence(&self, p: Vector3, depth: u32) -> f32 {
+        let mut sum = 0.0;
+        let mut p = p;
+        let mut weight = 1.0;
+        for _ in 0..depth {
+            sum += weight * self.noise(p);
+            weight *= 0.5;
+            p *= 2.0;
+        }
+        sum.abs()
+    }
+}
+
+/// Which pattern a `NoiseTexture` derives from Perlin noise.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoisePattern {
+    /// Plain noise, mapped to `[0, 1]`.
+    #[default]
+    Perlin,
+    /// Several octaves of noise, giving a cloudy look.
+    Turbulence,
+    /// Sine stripes along `z` perturbed by turbulence, like veined marble.
+    Marble,
+}
+
+/// Number of octaves summed by turbulent patterns.
+const TURBULENCE_DEPTH: u32 = 7;
+
+/// A procedural texture modulating `color` by Perlin noise.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoiseTexture {
+    pub perlin: Perlin,
+    pub pattern: NoisePattern,
+    /// Spatial frequency of the noise.
+    pub scale: f32,
+    pub color: Color,
+}
+
+impl NoiseTexture {
+    /// Create a new `NoiseTexture` whose noise is drawn from `seed`.
+    pub fn new(pattern: NoisePattern, scale: f32, color: Color, seed: u64) -> Self {
+        NoiseTexture {
+            perlin: Perlin::new(seed),
+            pattern,
+            scale,
+            color,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f32, _v: f32, point: Vector3) -> Color {
+        let p = point * self.scale;
+        let intensity = match self.pattern {
+            NoisePattern::Perlin => 0.5 * (1.0 + self.perlin.noise(p)),
+            NoisePattern::Turbulence => self.perlin.turbulence(p, TURBULENCE_DEPTH),
+            NoisePattern::Marble => {
+                let turbulence = self.perlin.turbulence(p, TURBULENCE_DEPTH);
+                0.5 * (1.0 + (p.z() + 10.0 * turbulence).sin())
+            }
+        };
+        self.color * intensity.clamp(0.0, 1.0)
+    }
+}
+
+/// A texture looked up from an image by texture coordinates, with `(0, 0)` at the bottom-left
+/// and `(1, 1)` at the top-right. Coordinates outside `[0, 1]` are clamped.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageTexture {
+    image: Framebuffer,
+}
+
+impl ImageTexture {
+    /// Create a new `ImageTexture` from `image`, whose pixels must be linear colors.
+    pub fn new(image: Framebuffer) -> Self {
+        ImageTexture { image }
+    }
+
+    /// Load an image from `path`. PNG and PPM images are gamma-encoded and are decoded with
+    /// `gamma` into linear colors, while HDR and PFM images are already linear and are used as
+    /// stored.
+    pub fn load(path: impl AsRef<Path>, gamma: f32) -> Result<Self, ImageError> {
+        let path = path.as_ref();
+        let mut image = Framebuffer::load(path)?;
+        if matches!(extension(path).as_str(), "hdr" | "pfm") {
+            return Ok(ImageTexture::new(image));
+        }
+        for pixel in image.pixels_mut() {
+            let c = *pixel;
+            *pixel = Color::new(c.x().powf(gamma), c.y().powf(gamma), c.z().powf(gamma));
+        }
+        Ok(ImageTexture::new(image))
+    }
+
+    /// Return the image this texture samples.
+    pub fn image(&self) -> &Framebuffer {
+        &self.image
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f32, v: f32, _point: Vector3) -> Color {
+        let (width, height) = (self.image.width(), self.image.height());
+        if width == 0 || height == 0 {
+            return Color::zeros();
+        }
+        // Images are stored top row first.
+        let x = ((u.clamp(0.0, 1.0) * width as f32) as usize).min(width - 1);
+        let y = (((1.0 - v.clamp(0.0, 1.0)) * height as f32) as usize).min(height - 1);
+        self.image.get(x, y)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_solid_and_checker() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        assert_eq!(red, SolidColor::new(red).value(0.3, 0.7, Vector3::ones()));
+        let scaled = Scaled::new(Arc::new(SolidColor::new(red)), Color::new(0.5, 1.0, 1.0));
+        assert_eq!(
+            Color::new(0.5, 0.0, 0.0),
+            scaled.value(0.0, 0.0, Vector3::zeros())
+        );
+
+        let checker = Checker::from_colors(Color::ones(), Color::zeros(), 2.0);
+        let at = |x, y, z| checker.value(0.0, 0.0, Vector3::new(x, y, z));
+        assert_eq!(Color::ones(), at(0.1, 0.1, 0.1));
+        assert_eq!(Color::zeros(), at(0.6, 0.1, 0.1));
+        assert_eq!(Color::ones(), at(0.6, 0.6, 0.1));
+        assert_eq!(
+            Color::zeros(),
+            at(-0.1, 0.1, 0.1),
+            "Cells must continue across zero."
+        );
+    }
+
+    #[test]
+    fn test_perlin() {
+        let perlin = Perlin::new(7);
+        assert_eq!(perlin, Perlin::new(7), "Noise must be deterministic.");
+        // Noise vanishes on the lattice and stays bounded and continuous between points.
+        assert_eq!(0.0, perlin.noise(Vector3::new(3.0, -2.0, 5.0)));
+        let mut rng = Pcg32::new(0, 0);
+        for _ in 0..1000 {
+            let p = 10.0 * Vector3::new(rng.next_f32(), rng.next_f32(), rng.next_f32());
+            let n = perlin.noise(p);
+            assert!((-1.05..=1.05).contains(&n), "Noise {} out of range.", n);
+            let nearby = perlin.noise(p + Vector3::new(1e-3, 0.0, 0.0));
+            assert!((n - nearby).abs() < 0.01);
+            assert!(perlin.turbulence(p, TURBULENCE_DEPTH) >= 0.0);
+        }
+
+        for pattern in [
+            NoisePattern::Perlin,
+            NoisePattern::Turbulence,
+            NoisePattern::Marble,
+        ] {
+            let texture = NoiseTexture::new(pattern, 4.0, Color::ones(), 1);
+            let c = texture.value(0.0, 0.0, Vector3::new(0.3, 0.2, 0.1));
+            assert!(c.min_component() >= 0.0 && c.max_component() <= 1.0);
+        }
+    }
+
+    #[test]
+    fn test_image() {
+        let image = Framebuffer::from_fn(2, 2, |x, y| Color::new(x as f32, y as f32, 0.0));
+        let texture = ImageTexture::new(image);
+        let at = |u, v| texture.value(u, v, Vector3::zeros());
+        assert_eq!(Color::new(0.0, 1.0, 0.0), at(0.25, 0.25), "Bottom-left.");
+        assert_eq!(Color::new(1.0, 0.0, 0.0), at(0.75, 0.75), "Top-right.");
+        assert_eq!(Color::new(1.0, 0.0, 0.0), at(1.0, 1.0));
+        assert_eq!(Color::new(0.0, 1.0, 0.0), at(-3.0, -1.0), "Clamped.");
+    }
+
+    #[test]
+    fn test_load_image() {
+        let dir = std::env::temp_dir().join(format!("raytracer-texture-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let image = Framebuffer::from_fn(2, 1, |x, _| Color::new(0.25, 0.5, 4.0) * (x + 1) as f32);
+
+        // PFM stores linear values, which are not decoded with the gamma.
+        let path = dir.join("linear.pfm");
+        image
+            .write_pfm(std::fs::File::create(&path).unwrap())
+            .unwrap();
+        let texture = ImageTexture::load(&path, 2.2).unwrap();
+        assert_eq!(&image, texture.image());
+
+        // PPM stores gamma-encoded values.
+        let path = dir.join("encoded.ppm");
+        std::fs::write(&path, "P3\n1 1\n255\n255 0 0\n").unwrap();
+        let texture = ImageTexture::load(&path, 2.2).unwrap();
+        assert_eq!(Color::new(1.0, 0.0, 0.0), texture.image().get(0, 0));
+        std::fs::write(&path, "P3\n1 1\n4\n2 2 2\n").unwrap();
+        let texture = ImageTexture::load(&path, 2.0).unwrap();
+        assert_eq!(Color::ones() * 0.25, texture.image().get(0, 0));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
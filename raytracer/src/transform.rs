@@ -0,0 +1,270 @@
+use std::ops::Mul;
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, MaterialId, TraversalStats};
+use crate::matrix::Matrix4;
+use crate::quaternion::Quaternion;
+use crate::ray::Ray;
+use crate::vector::{binop_ref_impl, Vector3};
+
+/// An invertible affine transformation, stored together with its inverse.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    matrix: Matrix4,
+    inverse: Matrix4,
+}
+
+impl Transform {
+    /// Create a new `Transform` applying `matrix`, or return `None` if it is singular.
+    pub fn new(matrix: Matrix4) -> Option<Self> {
+        let inverse = matrix.inverse()?;
+        Some(Transform { matrix, inverse })
+    }
+
+    /// Create a new identity `Transform`.
+    pub fn identity() -> Self {
+        Transform {
+            matrix: Matrix4::identity(),
+            inverse: Matrix4::identity(),
+        }
+    }
+
+    /// Create a new `Transform` translating by `offset`.
+    pub fn translation(offset: Vector3) -> Self {
+        Transform {
+            matrix: Matrix4::translation(offset),
+            inverse: Matrix4::translation(-offset),
+        }
+    }
+
+    /// Create a new `Transform` scaling each axis by the matching component of `factors`, which
+    /// must all be nonzero.
+    pub fn scaling(factors: Vector3) -> Self {
+        Transform {
+            matrix: Matrix4::scaling(factors),
+            inverse: Matrix4::scaling(Vector3::ones().cwise_div(factors)),
+        }
+    }
+
+    /// Create a new `Transform` applying the rotation represented by the unit quaternion `q`.
+    pub fn rotation(q: Quaternion) -> Self {
+        let matrix = Matrix4::rotation(q);
+        Transform {
+            matrix,
+            inverse: matrix.transpose(),
+        }
+    }
+
+    /// Return the matrix of this transform.
+    pub fn matrix(&self) -> Matrix4 {
+        self.matrix
+    }
+
+    /// Return the inverse of this transform.
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    /// Return the transform that applies this one and then `next`.
+    pub fn then(self, next: Transform) -> Transform {
+        next * self
+    }
+
+    /// Transform the point `p`.
+    pub fn point(&self, p: Vector3) -> Vector3 {
+        self.matrix.transform_point(p)
+    }
+
+    /// Transform the direction `v`.
+    pub fn vector(&self, v: Vector3) -> Vector3 {
+        self.matrix.transform_vector(v)
+    }
+
+    /// Transform the surface normal `n`. Normals transform by the inverse transpose so that they
+    /// stay perpendicular to transformed surfaces; the result is not normalized.
+    pub fn normal(&self, n: Vector3) -> Vector3 {
+        self.inverse.transpose().transform_vector(n)
+    }
+
+    /// Transform `ray`. The ray parameter of any point, and the ray's time, are unchanged.
+    pub fn ray(&self, ray: &Ray) -> Ray {
+        Ray::with_time(self.point(ray.origin), self.vector(ray.direction), ray.time)
+    }
+
+    /// Return the smallest `Aabb` containing the transformed corners of `aabb`.
+    pub fn bounding_box(&self, aabb: Aabb) -> Aabb {
+        let corners = (0..8).map(|i: usize| {
+            let pick = |bit| if i & bit == 0 { aabb.min } else { aabb.max };
+            Vector3::new(pick(1).x(), pick(2).y(), pick(4).z())
+        });
+        Aabb::from_points(corners.map(|p| self.point(p)))
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+impl Mul<Transform> for Transform {
+    type Output = Transform;
+
+    /// Compose two transforms; `a * b` applies `b` and then `a`.
+    fn mul(self, rhs: Transform) -> Self::Output {
+        Transform {
+            matrix: self.matrix * rhs.matrix,
+            inverse: rhs.inverse * self.inverse,
+        }
+    }
+}
+
+binop_ref_impl! { impl Mul<Transform> for Transform, mul -> Transform }
+
+/// A hittable placed in the world by a transform. The underlying object is shared, so one mesh
+/// can be instanced many times without copying it.
+pub struct Instance {
+    object: Arc<dyn Hittable>,
+    transform: Transform,
+    bounds: Option<Aabb>,
+    /// Material replacing those of the object's surfaces, if any.
+    material: Option<MaterialId>,
+}
+
+impl Instance {
+    /// Create a new `Instance` of `object`, mapping its local space into the world by
+    /// `transform`.
+    pub fn new(object: Arc<dyn Hittable>, transform: Transform) -> Self {
+        let bounds = object.bounding_box().map(|b| transform.bounding_box(b));
+        Instance {
+            object,
+            transform,
+            bounds,
+            material: None,
+        }
+    }
+
+    /// Shade every surface of this instance with `material`, in place of the object's own.
+    pub fn with_material(mut self, material: MaterialId) -> Self {
+        self.material = Some(material);
+        self
+    }
+
+    /// Return the transform from the object's local space into the world.
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.hit_counted(ray, t_min, t_max, &mut TraversalStats::default())
+    }
+
+    fn hit_counted(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord> {
+        // Intersect in object space. The direction is not renormalized, so t carries over.
+        let local = self.transform.inverse().ray(ray);
+        let mut hit = self.object.hit_counted(&local, t_min, t_max, stats)?;
+        hit.point = ray.at(hit.t);
+        // The normal already faces against the local ray, and transforming both by the same
+        // map preserves the sign of their dot product.
+        hit.normal = self.transform.normal(hit.normal).normalized();
+        if let Some(material) = self.material {
+            hit.material = material;
+        }
+        Some(hit)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bounds
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::shapes::Sphere;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn assert_close(expected: Vector3, actual: Vector3) {
+        assert!(
+            (expected - actual).norm() < 1e-5,
+            "Expected {:.4}, got {:.4}.",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_points_vectors_normals() {
+        let transform = Transform::translation(Vector3::new(0.0, 0.0, 5.0))
+            * Transform::rotation(Quaternion::from_axis_angle(
+                Vector3::new(0.0, 0.0, 1.0),
+                FRAC_PI_2,
+            ))
+            * Transform::scaling(Vector3::new(2.0, 1.0, 1.0));
+
+        let p = Vector3::new(1.0, 0.0, 0.0);
+        assert_close(Vector3::new(0.0, 2.0, 5.0), transform.point(p));
+        assert_close(Vector3::new(0.0, 2.0, 0.0), transform.vector(p));
+        assert_close(p, transform.inverse().point(transform.point(p)));
+
+        // The plane x + y = 0 has normal (1, 1, 0). Scaling x by 2 maps it to x + 2y = 0, with
+        // normal (1, 2, 0), which the rotation then turns to (-2, 1, 0).
+        let normal = transform.normal(Vector3::new(1.0, 1.0, 0.0)).normalized();
+        assert_close(Vector3::unit(-2.0, 1.0, 0.0), normal);
+        let tangent = transform.vector(Vector3::new(1.0, -1.0, 0.0));
+        assert!(normal.dot(tangent).abs() < 1e-5);
+
+        let composed = Transform::scaling(Vector3::new(2.0, 2.0, 2.0))
+            .then(Transform::translation(Vector3::new(1.0, 0.0, 0.0)));
+        assert_close(Vector3::new(3.0, 2.0, 2.0), composed.point(Vector3::ones()));
+        assert!(Transform::new(Matrix4::scaling(Vector3::zeros())).is_none());
+    }
+
+    #[test]
+    fn test_instance() {
+        let sphere: Arc<dyn Hittable> = Arc::new(Sphere::new(Vector3::zeros(), 1.0, MaterialId(3)));
+        let transform = Transform::translation(Vector3::new(0.0, 0.0, -5.0))
+            * Transform::scaling(Vector3::new(1.0, 1.0, 2.0));
+        let instance = Instance::new(sphere.clone(), transform);
+
+        let ray = Ray::new(Vector3::zeros(), Vector3::new(0.0, 0.0, -1.0));
+        let hit = instance.hit(&ray, 0.0, f32::INFINITY).unwrap();
+        assert!((hit.t - 3.0).abs() < 1e-5, "Expected t = 3, got {}.", hit.t);
+        assert_close(Vector3::new(0.0, 0.0, -3.0), hit.point);
+        assert_close(Vector3::new(0.0, 0.0, 1.0), hit.normal);
+        assert!(hit.front_face);
+        assert_eq!(MaterialId(3), hit.material);
+
+        // Off the axis, the normal follows the stretched ellipsoid rather than the sphere.
+        let side = Ray::new(
+            Vector3::new(5.0, 0.0, -5.0 + 2f32.sqrt()),
+            Vector3::new(-1.0, 0.0, 0.0),
+        );
+        let hit = instance.hit(&side, 0.0, f32::INFINITY).unwrap();
+        assert_close(Vector3::unit(2.0, 0.0, 1.0), hit.normal);
+
+        let bounds = instance.bounding_box().unwrap();
+        assert_close(Vector3::new(-1.0, -1.0, -7.0), bounds.min);
+        assert_close(Vector3::new(1.0, 1.0, -3.0), bounds.max);
+
+        let miss = Ray::new(Vector3::new(1.5, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!(instance.hit(&miss, 0.0, f32::INFINITY).is_none());
+
+        // Another instance of the same sphere can be shaded differently.
+        let painted = Instance::new(sphere, Transform::identity()).with_material(MaterialId(5));
+        let hit = painted.hit(&ray, 0.0, f32::INFINITY).unwrap();
+        assert_eq!(MaterialId(5), hit.material);
+    }
+}
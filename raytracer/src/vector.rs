@@ -1,9 +1,23 @@
 use std::fmt;
-use std::ops::{Add, Mul, Neg, Sub};
+use std::iter::Sum;
+use std::ops::{
+    Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign,
+};
 
 use serde::{Deserialize, Serialize};
 
+use crate::float::Float;
+
 macro_rules! unop_ref_impl {
+    (impl<$g:ident: $bound:path> $trait:ident for $self:ty, $method:ident -> $out:ty) => {
+        impl<$g: $bound> $trait for &$self {
+            type Output = $out;
+
+            fn $method(self) -> $out {
+                $trait::$method(*self)
+            }
+        }
+    };
     (impl $trait:ident for $self:ty, $method:ident -> $out:ty) => {
         impl $trait for &$self {
             type Output = $out;
@@ -16,6 +30,36 @@ macro_rules! unop_ref_impl {
 }
 
 macro_rules! binop_ref_impl {
+    (impl<$g:ident: $bound:path> $trait:ident<$other:ty> for $self:ty, $method:ident -> $out:ty) => {
+        impl<$g: $bound> $trait<$other> for &$self {
+            type Output = $out;
+
+            fn $method(self, rhs: $other) -> Self::Output {
+                (*self).$method(rhs)
+            }
+        }
+        impl<$g: $bound> $trait<&$other> for $self {
+            type Output = $out;
+
+            fn $method(self, rhs: &$other) -> Self::Output {
+                self.$method(*rhs)
+            }
+        }
+        impl<$g: $bound> $trait<&$other> for &$self {
+            type Output = $out;
+
+            fn $method(self, rhs: &$other) -> Self::Output {
+                (*self).$method(*rhs)
+            }
+        }
+    };
+    (impl<$g:ident: $bound:path> $trait:ident<$other:ty> for $self:ty, $method:ident) => {
+        impl<$g: $bound> $trait<&$other> for $self {
+            fn $method(&mut self, rhs: &$other) {
+                self.$method(*rhs)
+            }
+        }
+    };
     (impl $trait:ident<$other:ty> for $self:ty, $method:ident -> $out:ty) => {
         impl $trait<$other> for &$self {
             type Output = $out;
@@ -39,60 +83,82 @@ macro_rules! binop_ref_impl {
             }
         }
     };
+    (impl $trait:ident<$other:ty> for $self:ty, $method:ident) => {
+        impl $trait<&$other> for $self {
+            fn $method(&mut self, rhs: &$other) {
+                self.$method(*rhs)
+            }
+        }
+    };
 }
 
+pub(crate) use binop_ref_impl;
+pub(crate) use unop_ref_impl;
+
+/// An RGB color, stored as linear channel values in the `x`, `y`, and `z` components.
+pub type Color = Vector3;
+
+/// A three-component vector of `f32` (by default) or `f64`.
 #[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
-pub struct Vector3 {
-    x: f32,
-    y: f32,
-    z: f32,
+pub struct Vector3<T = f32> {
+    x: T,
+    y: T,
+    z: T,
 }
 
-impl Vector3 {
+impl<T: Float> Vector3<T> {
     /// Create a new `Vector3` with specified `x`, `y`, and `z` components.
-    pub fn new(x: f32, y: f32, z: f32) -> Self {
+    pub fn new(x: T, y: T, z: T) -> Self {
         Vector3 { x, y, z }
     }
 
     /// Create a new `Vector3` of zeros.
     pub fn zeros() -> Self {
-        Vector3::new(0f32, 0f32, 0f32)
+        Vector3::new(T::ZERO, T::ZERO, T::ZERO)
     }
 
     /// Create a new `Vector3` of ones.
     pub fn ones() -> Self {
-        Vector3::new(1f32, 1f32, 1f32)
+        Vector3::new(T::ONE, T::ONE, T::ONE)
     }
 
     /// Create a new unit `Vector3` in the direction of the vector with `x`, `y`, `z`.
-    pub fn unit(x: f32, y: f32, z: f32) -> Self {
-        let norm: f32 = (x.powi(2) + y.powi(2) + z.powi(2)).sqrt();
-        Vector3::new(x / norm, y / norm, z / norm)
+    pub fn unit(x: T, y: T, z: T) -> Self {
+        Vector3::new(x, y, z).normalized()
+    }
+
+    /// Convert each component of this vector to another float type.
+    pub fn cast<U: Float>(self) -> Vector3<U> {
+        Vector3::new(
+            U::from_f64(self.x.to_f64()),
+            U::from_f64(self.y.to_f64()),
+            U::from_f64(self.z.to_f64()),
+        )
     }
 
     /// Compute the square of the Euclidean norm of this vector.
-    pub fn squared_norm(self) -> f32 {
-        self.x.powi(2) + self.y.powi(2) + self.z.powi(2)
+    pub fn squared_norm(self) -> T {
+        self.dot(self)
     }
 
     /// Compute the Euclidean norm of this vector.
-    pub fn norm(self) -> f32 {
+    pub fn norm(self) -> T {
         self.squared_norm().sqrt()
     }
 
     /// Return a normalized copy of this vector.
-    pub fn normalized(mut self) -> Vector3 {
+    pub fn normalized(self) -> Vector3<T> {
         let norm = self.norm();
         Vector3::new(self.x / norm, self.y / norm, self.z / norm)
     }
 
     /// Compute the dot product of this vector and `other`.
-    pub fn dot(self, other: Vector3) -> f32 {
-        self.x * other.x + self.y * other.y + self.z * other.z
+    pub fn dot(self, other: Vector3<T>) -> T {
+        T::dot3(self.into(), other.into())
     }
 
     /// Compute the cross product of this vector and `other`.
-    pub fn cross(self, other: Vector3) -> Vector3 {
+    pub fn cross(self, other: Vector3<T>) -> Vector3<T> {
         Vector3::new(
             self.y * other.z - self.z * other.y,
             self.z * other.x - self.x * other.z,
@@ -102,87 +168,253 @@ impl Vector3 {
 
     /// Apply a component-wise reduction operation `f` to the paired `x`, `y`, and `z`, returning
     /// the result as a new vector.
-    pub fn cwise(self, other: Vector3, f: fn(f32, f32) -> f32) -> Vector3 {
+    pub fn cwise(self, other: Vector3<T>, f: fn(T, T) -> T) -> Vector3<T> {
         Vector3::new(f(self.x, other.x), f(self.y, other.y), f(self.z, other.z))
     }
 
     /// Component-wise multiplication of this vector and `other`.
-    pub fn cwise_mul(self, other: Vector3) -> Vector3 {
-        self.cwise(other, |a, b| a * b)
+    pub fn cwise_mul(self, other: Vector3<T>) -> Vector3<T> {
+        Vector3::from(T::mul3(self.into(), other.into()))
     }
 
     /// Component-wise division of this vector and `other`.
-    pub fn cwise_div(self, other: Vector3) -> Vector3 {
+    pub fn cwise_div(self, other: Vector3<T>) -> Vector3<T> {
         self.cwise(other, |a, b| a / b)
     }
 
+    /// Component-wise minimum of this vector and `other`.
+    pub fn min(self, other: Vector3<T>) -> Vector3<T> {
+        self.cwise(other, T::min)
+    }
+
+    /// Component-wise maximum of this vector and `other`.
+    pub fn max(self, other: Vector3<T>) -> Vector3<T> {
+        self.cwise(other, T::max)
+    }
+
+    /// Return the smallest component of this vector.
+    pub fn min_component(self) -> T {
+        self.x.min(self.y).min(self.z)
+    }
+
+    /// Return the largest component of this vector.
+    pub fn max_component(self) -> T {
+        self.x.max(self.y).max(self.z)
+    }
+
+    /// Return the component-wise absolute value of this vector.
+    pub fn abs(self) -> Vector3<T> {
+        Vector3::new(self.x.abs(), self.y.abs(), self.z.abs())
+    }
+
+    /// Linearly interpolate from this vector (`t = 0`) to `other` (`t = 1`).
+    pub fn lerp(self, other: Vector3<T>, t: T) -> Vector3<T> {
+        self + (other - self) * t
+    }
+
+    /// Reflect this vector about the unit normal `n`.
+    pub fn reflect(self, n: Vector3<T>) -> Vector3<T> {
+        self - n * (T::from_f64(2.0) * self.dot(n))
+    }
+
+    /// Refract this unit vector through a surface with unit normal `n` (facing against it) and
+    /// relative index of refraction `eta`, or return `None` on total internal reflection.
+    pub fn refract(self, n: Vector3<T>, eta: T) -> Option<Vector3<T>> {
+        let cos_theta = (-self).dot(n).min(T::ONE);
+        let sin2_theta = T::ONE - cos_theta * cos_theta;
+        if eta * eta * sin2_theta > T::ONE {
+            return None;
+        }
+        let perpendicular = (self + n * cos_theta) * eta;
+        let parallel = n * -(T::ONE - perpendicular.squared_norm()).abs().sqrt();
+        Some(perpendicular + parallel)
+    }
+
+    /// Return whether every component of this vector is within `tolerance` of the matching
+    /// component of `other`.
+    pub fn approx_eq(self, other: Vector3<T>, tolerance: T) -> bool {
+        (self - other).abs().max_component() <= tolerance
+    }
+
     /// Return the `x` component of this vector.
-    pub fn x(self) -> f32 {
+    pub fn x(self) -> T {
         self.x
     }
 
     /// Return the `y` component of this vector.
-    pub fn y(self) -> f32 {
+    pub fn y(self) -> T {
         self.y
     }
 
     /// Return the `z` component of this vector.
-    pub fn z(self) -> f32 {
+    pub fn z(self) -> T {
         self.z
     }
 }
 
-impl Add<Vector3> for Vector3 {
-    type Output = Vector3;
+impl<T: Float> Add<Vector3<T>> for Vector3<T> {
+    type Output = Vector3<T>;
 
-    fn add(self, rhs: Vector3) -> Self::Output {
-        self.cwise(rhs, |a, b| a + b)
+    fn add(self, rhs: Vector3<T>) -> Self::Output {
+        Vector3::from(T::add3(self.into(), rhs.into()))
     }
 }
 
-binop_ref_impl! { impl Add<Vector3> for Vector3, add -> Vector3 }
+binop_ref_impl! { impl<T: Float> Add<Vector3<T>> for Vector3<T>, add -> Vector3<T> }
 
-impl Sub<Vector3> for Vector3 {
-    type Output = Vector3;
+impl<T: Float> Sub<Vector3<T>> for Vector3<T> {
+    type Output = Vector3<T>;
 
-    fn sub(self, rhs: Vector3) -> Self::Output {
-        self.cwise(rhs, |a, b| a - b)
+    fn sub(self, rhs: Vector3<T>) -> Self::Output {
+        Vector3::from(T::sub3(self.into(), rhs.into()))
     }
 }
 
-binop_ref_impl! { impl Sub<Vector3> for Vector3, sub -> Vector3 }
+binop_ref_impl! { impl<T: Float> Sub<Vector3<T>> for Vector3<T>, sub -> Vector3<T> }
 
-impl Mul<f32> for Vector3 {
-    type Output = Vector3;
+impl<T: Float> Mul<T> for Vector3<T> {
+    type Output = Vector3<T>;
 
-    fn mul(self, rhs: f32) -> Self::Output {
-        Vector3::new(self.x * rhs, self.y * rhs, self.z * rhs)
+    fn mul(self, rhs: T) -> Self::Output {
+        Vector3::from(T::scale3(self.into(), rhs))
     }
 }
 
-binop_ref_impl! { impl Mul<f32> for Vector3, mul -> Vector3 }
+binop_ref_impl! { impl<T: Float> Mul<T> for Vector3<T>, mul -> Vector3<T> }
 
-impl Mul<Vector3> for f32 {
-    type Output = Vector3;
+// Scalars on the left must be implemented per type, since `impl<T> Mul<Vector3<T>> for T` would
+// implement a foreign trait for an uncovered type parameter.
+macro_rules! scalar_mul_impl {
+    ($t:ty) => {
+        impl Mul<Vector3<$t>> for $t {
+            type Output = Vector3<$t>;
 
-    fn mul(self, rhs: Vector3) -> Self::Output {
-        Vector3::new(self * rhs.x, self * rhs.y, self * rhs.z)
-    }
+            fn mul(self, rhs: Vector3<$t>) -> Self::Output {
+                rhs * self
+            }
+        }
+
+        binop_ref_impl! { impl Mul<Vector3<$t>> for $t, mul -> Vector3<$t> }
+    };
 }
 
-binop_ref_impl! { impl Mul<Vector3> for f32, mul -> Vector3 }
+scalar_mul_impl!(f32);
+scalar_mul_impl!(f64);
 
-impl Neg for Vector3 {
-    type Output = Vector3;
+impl<T: Float> Neg for Vector3<T> {
+    type Output = Vector3<T>;
 
     fn neg(self) -> Self::Output {
-        -1f32 * self
+        Vector3::new(-self.x, -self.y, -self.z)
+    }
+}
+
+unop_ref_impl! { impl<T: Float> Neg for Vector3<T>, neg -> Vector3<T> }
+
+impl<T: Float> Div<T> for Vector3<T> {
+    type Output = Vector3<T>;
+
+    fn div(self, rhs: T) -> Self::Output {
+        self * (T::ONE / rhs)
+    }
+}
+
+binop_ref_impl! { impl<T: Float> Div<T> for Vector3<T>, div -> Vector3<T> }
+
+impl<T: Float> AddAssign<Vector3<T>> for Vector3<T> {
+    fn add_assign(&mut self, rhs: Vector3<T>) {
+        *self = *self + rhs;
+    }
+}
+
+binop_ref_impl! { impl<T: Float> AddAssign<Vector3<T>> for Vector3<T>, add_assign }
+
+impl<T: Float> SubAssign<Vector3<T>> for Vector3<T> {
+    fn sub_assign(&mut self, rhs: Vector3<T>) {
+        *self = *self - rhs;
+    }
+}
+
+binop_ref_impl! { impl<T: Float> SubAssign<Vector3<T>> for Vector3<T>, sub_assign }
+
+impl<T: Float> MulAssign<T> for Vector3<T> {
+    fn mul_assign(&mut self, rhs: T) {
+        *self = *self * rhs;
+    }
+}
+
+binop_ref_impl! { impl<T: Float> MulAssign<T> for Vector3<T>, mul_assign }
+
+impl<T: Float> DivAssign<T> for Vector3<T> {
+    fn div_assign(&mut self, rhs: T) {
+        *self = *self / rhs;
+    }
+}
+
+binop_ref_impl! { impl<T: Float> DivAssign<T> for Vector3<T>, div_assign }
+
+impl<T> Index<usize> for Vector3<T> {
+    type Output = T;
+
+    /// Return the `index`-th component (0 = x, 1 = y, 2 = z) of this vector.
+    fn index(&self, index: usize) -> &T {
+        match index {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vector3 index out of range: {}", index),
+        }
+    }
+}
+
+impl<T> IndexMut<usize> for Vector3<T> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        match index {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.z,
+            _ => panic!("Vector3 index out of range: {}", index),
+        }
+    }
+}
+
+impl<T: Float> Sum for Vector3<T> {
+    fn sum<I: Iterator<Item = Vector3<T>>>(iter: I) -> Self {
+        iter.fold(Vector3::zeros(), |acc, v| acc + v)
+    }
+}
+
+impl<'a, T: Float> Sum<&'a Vector3<T>> for Vector3<T> {
+    fn sum<I: Iterator<Item = &'a Vector3<T>>>(iter: I) -> Self {
+        iter.fold(Vector3::zeros(), |acc, v| acc + v)
     }
 }
 
-unop_ref_impl! { impl Neg for Vector3, neg -> Vector3 }
+impl<T> From<[T; 3]> for Vector3<T> {
+    fn from([x, y, z]: [T; 3]) -> Self {
+        Vector3 { x, y, z }
+    }
+}
 
-impl fmt::Display for Vector3 {
+impl<T> From<(T, T, T)> for Vector3<T> {
+    fn from((x, y, z): (T, T, T)) -> Self {
+        Vector3 { x, y, z }
+    }
+}
+
+impl<T> From<Vector3<T>> for [T; 3] {
+    fn from(v: Vector3<T>) -> Self {
+        [v.x, v.y, v.z]
+    }
+}
+
+impl<T> From<Vector3<T>> for (T, T, T) {
+    fn from(v: Vector3<T>) -> Self {
+        (v.x, v.y, v.z)
+    }
+}
+
+impl<T: Float> fmt::Display for Vector3<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let p = f.precision().unwrap_or(2);
         write!(f, "[{:.*}, {:.*}, {:.*}]", p, self.x, p, self.y, p, self.z)
@@ -341,6 +573,7 @@ mod test {
     }
 
     #[test]
+    #[allow(clippy::op_ref)]
     fn test_ops() {
         let test1 = Vector3::new(1.0, 1.0, 1.0);
         let test2 = Vector3::new(1.0, 2.0, 4.0);
@@ -397,4 +630,113 @@ mod test {
 
         let _ = -&test1;
     }
+
+    #[test]
+    #[allow(clippy::op_ref)]
+    fn test_generic() {
+        let single: Vector3 = Vector3::new(1.0, 2.0, 2.0);
+        let double: Vector3<f64> = single.cast();
+        assert_eq!(Vector3::<f64>::new(1.0, 2.0, 2.0), double);
+        assert_eq!(3.0f64, double.norm());
+        assert_eq!(single, double.cast::<f32>());
+
+        // f64 keeps precision that f32 loses far from the origin.
+        let far = Vector3::<f64>::new(1e8, 0.0, 0.0);
+        let step = Vector3::<f64>::new(1.0, 0.0, 0.0);
+        assert_eq!(1.0, ((far + step) - far).x());
+        assert_eq!(0.0, ((far.cast::<f32>() + step.cast()) - far.cast()).x());
+
+        let sum = &double + &double;
+        let _ = 2.0f64 * &double;
+        let _ = -&double;
+        assert_eq!(2.0 * double, sum);
+    }
+
+    #[test]
+    #[allow(clippy::op_ref)]
+    fn test_assign_ops() {
+        let mut v = Vector3::new(1.0, 2.0, 4.0);
+        v += Vector3::ones();
+        assert_eq!(Vector3::new(2.0, 3.0, 5.0), v);
+        v -= &Vector3::new(2.0, 2.0, 2.0);
+        assert_eq!(Vector3::new(0.0, 1.0, 3.0), v);
+        v *= 2.0;
+        assert_eq!(Vector3::new(0.0, 2.0, 6.0), v);
+        v /= &2.0;
+        assert_eq!(Vector3::new(0.0, 1.0, 3.0), v);
+        assert_eq!(Vector3::new(0.0, 0.5, 1.5), v / 2.0);
+
+        // Make sure implementations exist
+        v += &v.clone();
+        v -= &v.clone();
+        v *= &1.0;
+        v /= 1.0;
+        let _ = &v / 2.0;
+        let _ = v / &2.0;
+        let _ = &v / &2.0;
+    }
+
+    #[test]
+    fn test_index_sum_conversions() {
+        let mut v: Vector3 = [1.0, 2.0, 3.0].into();
+        assert_eq!((1.0, 2.0, 3.0), (v[0], v[1], v[2]));
+        v[1] = 5.0;
+        assert_eq!(Vector3::new(1.0, 5.0, 3.0), v);
+
+        let tuple: (f32, f32, f32) = v.into();
+        assert_eq!((1.0, 5.0, 3.0), tuple);
+        let array: [f32; 3] = v.into();
+        assert_eq!([1.0, 5.0, 3.0], array);
+        assert_eq!(v, Vector3::from(tuple));
+
+        let vectors = [Vector3::ones(), v, Vector3::new(-1.0, 0.0, 0.0)];
+        let sum: Vector3 = vectors.iter().sum();
+        assert_eq!(Vector3::new(1.0, 6.0, 4.0), sum);
+        assert_eq!(sum, vectors.into_iter().sum());
+        assert_eq!(Vector3::zeros(), std::iter::empty::<Vector3>().sum());
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of range")]
+    fn test_index_out_of_range() {
+        let _ = Vector3::<f32>::zeros()[3];
+    }
+
+    #[test]
+    fn test_componentwise() {
+        let a = Vector3::new(1.0, -4.0, 2.0);
+        let b = Vector3::new(-1.0, 3.0, 2.5);
+        assert_eq!(Vector3::new(-1.0, -4.0, 2.0), a.min(b));
+        assert_eq!(Vector3::new(1.0, 3.0, 2.5), a.max(b));
+        assert_eq!(Vector3::new(1.0, 4.0, 2.0), a.abs());
+        assert_eq!(-4.0, a.min_component());
+        assert_eq!(2.0, a.max_component());
+
+        assert_eq!(a, a.lerp(b, 0.0));
+        assert_eq!(b, a.lerp(b, 1.0));
+        assert_eq!(Vector3::new(0.0, -0.5, 2.25), a.lerp(b, 0.5));
+
+        assert!(a.approx_eq(a + Vector3::new(1e-4, -1e-4, 0.0), 1e-3));
+        assert!(!a.approx_eq(a + Vector3::new(0.0, 0.0, 1e-2), 1e-3));
+    }
+
+    #[test]
+    fn test_reflect_refract() {
+        let v = Vector3::new(1.0f32, -1.0, 0.0);
+        let n = Vector3::new(0.0, 1.0, 0.0);
+        assert_eq!(Vector3::new(1.0, 1.0, 0.0), v.reflect(n));
+
+        // Matching indices pass straight through.
+        let unit = v.normalized();
+        let refracted = unit.refract(n, 1.0).unwrap();
+        assert!(refracted.approx_eq(unit, 1e-6), "Got {}.", refracted);
+
+        // Entering a denser medium bends toward the normal, preserving the tangential ratio.
+        let refracted = unit.refract(n, 1.0 / 1.5).unwrap();
+        assert!((refracted.norm() - 1.0).abs() < 1e-6);
+        assert!((refracted.x() - unit.x() / 1.5).abs() < 1e-6);
+
+        // Grazing light leaving a dense medium is totally internally reflected.
+        assert!(unit.refract(n, 1.5).is_none());
+    }
 }
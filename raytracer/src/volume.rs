@@ -0,0 +1,376 @@
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, MaterialId};
+use crate::ray::Ray;
+use crate::rng::Pcg32;
+use crate::sampling::Onb;
+use crate::vector::Vector3;
+
+/// How a participating medium redistributes the light it scatters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum PhaseFunction {
+    /// Scatters equally in every direction.
+    #[default]
+    Isotropic,
+    /// The Henyey-Greenstein lobe, whose mean cosine `g` in `(-1, 1)` ranges from back
+    /// scattering through isotropic at zero to forward scattering, as in smoke and haze.
+    HenyeyGreenstein { g: f32 },
+}
+
+impl PhaseFunction {
+    /// Return the density, in solid angle, with which light travelling along a direction is
+    /// scattered into another at an angle with cosine `cos_theta` to it.
+    pub fn eval(&self, cos_theta: f32) -> f32 {
+        match *self {
+            PhaseFunction::Isotropic => 1.0 / (4.0 * PI),
+            PhaseFunction::HenyeyGreenstein { g } => {
+                let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+                (1.0 - g * g) / (4.0 * PI * denominator * denominator.sqrt())
+            }
+        }
+    }
+
+    /// Map a uniform sample in `[0, 1)^2` to a direction scattered from light travelling along
+    /// the unit `direction`, distributed as `eval`.
+    pub fn sample(&self, direction: Vector3, [a, b]: [f32; 2]) -> Vector3 {
+        let cos_theta = match *self {
+            PhaseFunction::HenyeyGreenstein { g } if g.abs() >= 1e-3 => {
+                let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * a);
+                ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+            }
+            _ => 1.0 - 2.0 * a,
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * b;
+        Onb::from_w(direction).to_world(Vector3::new(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        ))
+    }
+}
+
+/// A grid of density values spread evenly over a box, from one corner to the other, and
+/// interpolated trilinearly in between.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DensityGrid {
+    /// Number of values along each axis.
+    pub resolution: [usize; 3],
+    /// The values, with `x` varying fastest and then `y`.
+    pub values: Vec<f32>,
+}
+
+impl DensityGrid {
+    /// Create a new `DensityGrid` with `resolution` values along each axis. `values` must hold
+    /// one value per grid point.
+    pub fn new(resolution: [usize; 3], values: Vec<f32>) -> Self {
+        DensityGrid { resolution, values }
+    }
+
+    /// Create a new `DensityGrid` by evaluating `f` at each grid point, given as a position in
+    /// `[0, 1]^3`.
+    pub fn from_fn(resolution: [usize; 3], f: impl Fn(Vector3) -> f32) -> Self {
+        let [nx, ny, nz] = resolution;
+        let coordinate = |i: usize, n: usize| i as f32 / (n.max(2) - 1) as f32;
+        let mut values = Vec::with_capacity(nx * ny * nz);
+        for z in 0..nz {
+            for y in 0..ny {
+                for x in 0..nx {
+                    let p = Vector3::new(coordinate(x, nx), coordinate(y, ny), coordinate(z, nz));
+                    values.push(f(p));
+                }
+            }
+        }
+        DensityGrid { resolution, values }
+    }
+
+    /// Return the largest value in the grid.
+    pub fn max(&self) -> f32 {
+        self.values.iter().copied().fold(0.0, f32::max)
+    }
+
+    /// Return the interpolated value at `p`, a position in `[0, 1]^3` across the grid. Positions
+    /// outside the grid take the value at its nearest edge.
+    pub fn value(&self, p: Vector3) -> f32 {
+        // Split each coordinate into the index of the lower grid point and the fraction of the
+        // way to the next.
+        let mut lower = [0; 3];
+        let mut fraction = [0.0; 3];
+        for axis in 0..3 {
+            let n = self.resolution[axis];
+            if n < 2 {
+                continue;
+            }
+            let x = (p[axis].clamp(0.0, 1.0) * (n - 1) as f32).min((n - 1) as f32);
+            let i = (x as usize).min(n - 2);
+            lower[axis] = i;
+            fraction[axis] = x - i as f32;
+        }
+
+        let [nx, ny, _] = self.resolution;
+        let mut value = 0.0;
+        for corner in 0..8 {
+            let mut weight = 1.0;
+            let mut index = [0; 3];
+            for axis in 0..3 {
+                let upper = corner >> axis & 1 == 1;
+                index[axis] = (lower[axis] + upper as usize).min(self.resolution[axis] - 1);
+                weight *= if upper {
+                    fraction[axis]
+                } else {
+                    1.0 - fraction[axis]
+                };
+            }
+            if weight > 0.0 {
+                value += weight * self.values[index[0] + nx * (index[1] + ny * index[2])];
+            }
+        }
+        value
+    }
+}
+
+/// A participating medium, such as fog or smoke, filling a closed `boundary`.
+///
+/// Rays passing through the medium scatter at a random distance, with a probability per unit
+/// length of `density` (times the grid value there, for a heterogeneous medium), and are then
+/// shaded with `material`, which should be a `Medium`. Distances are sampled by delta tracking,
+/// with random numbers drawn from a generator seeded by the ray itself so that intersection stays
+/// deterministic. Shadow rays are blocked with the probability of scattering along them, which
+/// makes their visibility an unbiased estimate of the transmittance.
+pub struct Volume {
+    boundary: Box<dyn Hittable>,
+    density: f32,
+    grid: Option<(DensityGrid, Aabb)>,
+    material: MaterialId,
+}
+
+impl Volume {
+    /// Create a new homogeneous `Volume` of constant `density` filling `boundary`.
+    pub fn new(boundary: Box<dyn Hittable>, density: f32, material: MaterialId) -> Self {
+        Volume {
+            boundary,
+            density,
+            grid: None,
+            material,
+        }
+    }
+
+    /// Scale the density by `grid`, stretched over the bounding box of the boundary. Returns
+    /// `None` if the boundary is unbounded.
+    pub fn with_grid(mut self, grid: DensityGrid) -> Option<Self> {
+        let bounds = self.boundary.bounding_box()?;
+        self.grid = Some((grid, bounds));
+        Some(self)
+    }
+
+    /// Return the density of the medium at `p`, which must be inside the boundary.
+    pub fn density(&self, p: Vector3) -> f32 {
+        match &self.grid {
+            None => self.density,
+            Some((grid, bounds)) => {
+                let local =
+                    (p - bounds.min).cwise_div(bounds.extent().max(Vector3::ones() * 1e-12));
+                self.density * grid.value(local)
+            }
+        }
+    }
+
+    /// Return the density that bounds `density` everywhere.
+    fn majorant(&self) -> f32 {
+        match &self.grid {
+            None => self.density,
+            Some((grid, _)) => self.density * grid.max(),
+        }
+    }
+}
+
+/// Seed a generator from the bits of `ray`, so that the same ray always draws the same numbers.
+fn ray_rng(ray: &Ray) -> Pcg32 {
+    let bits = [
+        ray.origin.x(),
+        ray.origin.y(),
+        ray.origin.z(),
+        ray.direction.x(),
+        ray.direction.y(),
+        ray.direction.z(),
+        ray.time,
+    ];
+    // FNV-1a over the words, finished by PCG's own scrambling.
+    let hash = bits.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, x| {
+        (hash ^ x.to_bits() as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    Pcg32::new(hash, hash >> 32)
+}
+
+impl Hittable for Volume {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let majorant = self.majorant();
+        if majorant <= 0.0 {
+            return None;
+        }
+        let speed = ray.direction.norm();
+        let mut rng = ray_rng(ray);
+
+        // Walk the stretches of the ray inside the boundary. Free-flight distances are
+        // memoryless, so each stretch can start afresh.
+        let mut from = t_min;
+        loop {
+            let first = self.boundary.hit(ray, from, f32::INFINITY)?;
+            let (start, end) = if first.front_face {
+                let exit = self.boundary.hit(ray, first.t, f32::INFINITY)?;
+                (first.t, exit.t)
+            } else {
+                (from, first.t)
+            };
+            if start >= t_max {
+                return None;
+            }
+
+            // Delta tracking: step by distances sampled against the majorant, and scatter at
+            // each with the probability that the real density there accounts for.
+            let mut t = start;
+            loop {
+                t -= (1.0 - rng.next_f32()).ln() / (majorant * speed);
+                if t >= end || t >= t_max {
+                    break;
+                }
+                if rng.next_f32() * majorant < self.density(ray.at(t)) {
+                    // Facing back along the ray, the hit counts as being on a front face.
+                    let normal = -ray.direction / speed;
+                    return Some(HitRecord::new(ray, t, normal, self.material));
+                }
+            }
+            if end >= t_max {
+                return None;
+            }
+            from = end;
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.boundary.bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::shapes::{Cuboid, Sphere};
+
+    #[test]
+    fn test_phase_functions() {
+        let direction = Vector3::new(0.0, 0.0, 1.0);
+        let mut rng = Pcg32::new(3, 4);
+        for phase in [
+            PhaseFunction::Isotropic,
+            PhaseFunction::HenyeyGreenstein { g: 0.7 },
+            PhaseFunction::HenyeyGreenstein { g: -0.4 },
+        ] {
+            // Each integrates to one over the sphere, by the midpoint rule in cos(theta).
+            let n = 10000;
+            let integral: f32 = (0..n)
+                .map(|i| phase.eval(-1.0 + (2 * i + 1) as f32 / n as f32))
+                .sum::<f32>()
+                * 2.0
+                / n as f32
+                * 2.0
+                * PI;
+            assert!(
+                (integral - 1.0).abs() < 1e-3,
+                "{:?} integrates to {}.",
+                phase,
+                integral
+            );
+
+            // Samples follow the lobe, whose mean cosine is g.
+            let g = match phase {
+                PhaseFunction::Isotropic => 0.0,
+                PhaseFunction::HenyeyGreenstein { g } => g,
+            };
+            let samples = 20000;
+            let mut mean = 0.0;
+            for _ in 0..samples {
+                let sample = phase.sample(direction, rng.next_2d());
+                assert!((sample.norm() - 1.0).abs() < 1e-4);
+                mean += sample.dot(direction) / samples as f32;
+            }
+            assert!(
+                (mean - g).abs() < 0.02,
+                "{:?} has mean cosine {}.",
+                phase,
+                mean
+            );
+        }
+    }
+
+    #[test]
+    fn test_grid() {
+        let grid = DensityGrid::from_fn([2, 3, 1], |p| p.x() + 2.0 * p.y());
+        assert_eq!(vec![0.0, 1.0, 1.0, 2.0, 2.0, 3.0], grid.values);
+        assert_eq!(3.0, grid.max());
+        assert_eq!(0.75, grid.value(Vector3::new(0.25, 0.25, 0.5)));
+        assert_eq!(3.0, grid.value(Vector3::new(2.0, 1.5, -1.0)));
+    }
+
+    #[test]
+    fn test_transmittance() {
+        // The fraction of rays crossing a unit-density slab of thickness 2 without scattering
+        // is exp(-2), whether the density is constant or comes from a grid.
+        let slab = || {
+            Box::new(Cuboid::new(
+                Vector3::new(-1.0, -5.0, -5.0),
+                Vector3::new(1.0, 5.0, 5.0),
+                MaterialId(0),
+            ))
+        };
+        let constant = Volume::new(slab(), 1.0, MaterialId(1));
+        let grid = Volume::new(slab(), 2.0, MaterialId(1))
+            .with_grid(DensityGrid::new([2, 1, 1], vec![0.25, 0.75]))
+            .unwrap();
+        for volume in [constant, grid] {
+            let n = 20000;
+            let mut through = 0;
+            let mut rng = Pcg32::new(1, 1);
+            for _ in 0..n {
+                let origin = Vector3::new(-3.0, rng.next_f32() - 0.5, rng.next_f32() - 0.5);
+                let ray = Ray::new(origin, Vector3::new(2.0, 0.0, 0.0));
+                match volume.hit(&ray, 0.0, f32::INFINITY) {
+                    Some(hit) => {
+                        assert!((-1.0..=1.0).contains(&hit.point.x()));
+                        assert_eq!(MaterialId(1), hit.material);
+                        assert!(hit.front_face);
+                    }
+                    None => through += 1,
+                }
+            }
+            let fraction = through as f32 / n as f32;
+            assert!(
+                (fraction - (-2f32).exp()).abs() < 0.01,
+                "Expected {} of rays through, got {}.",
+                (-2f32).exp(),
+                fraction
+            );
+        }
+    }
+
+    #[test]
+    fn test_inside_and_deterministic() {
+        // From inside a dense sphere, rays scatter almost at once, the same way every time.
+        let sphere = || Box::new(Sphere::new(Vector3::zeros(), 1.0, MaterialId(0)));
+        let volume = Volume::new(sphere(), 100.0, MaterialId(1));
+        let ray = Ray::new(Vector3::zeros(), Vector3::new(0.0, 1.0, 0.0));
+        let hit = volume.hit(&ray, 0.0, f32::INFINITY).unwrap();
+        assert!(hit.t < 0.1);
+        assert_eq!(Some(hit), volume.hit(&ray, 0.0, f32::INFINITY));
+        assert!(volume.hit(&ray, 0.0, hit.t).is_none());
+
+        // Empty media are invisible.
+        let empty = Volume::new(sphere(), 0.0, MaterialId(1));
+        assert!(empty.hit(&ray, 0.0, f32::INFINITY).is_none());
+    }
+}
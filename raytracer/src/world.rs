@@ -0,0 +1,184 @@
+use crate::environment::Environment;
+use crate::hittable::{HitRecord, Hittable, MaterialId, TraversalStats};
+use crate::light::{Light, LightSample};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::rng::Pcg32;
+use crate::vector::{Color, Vector3};
+
+/// Rays start this far along their direction to avoid re-hitting the surface they left.
+const T_MIN: f32 = 1e-3;
+
+/// Renderable geometry together with the materials it refers to.
+pub struct World {
+    objects: Box<dyn Hittable>,
+    materials: Vec<Box<dyn Material>>,
+    lights: Vec<Box<dyn Light>>,
+    environment: Option<Environment>,
+}
+
+impl World {
+    /// Create a new `World`. Every `MaterialId` used by `objects` must index into `materials`.
+    pub fn new(objects: impl Hittable + 'static, materials: Vec<Box<dyn Material>>) -> Self {
+        World {
+            objects: Box::new(objects),
+            materials,
+            lights: Vec::new(),
+            environment: None,
+        }
+    }
+
+    /// Set the lights that next-event estimation samples directly. Area lights should also be
+    /// part of the scene geometry, with a matching emissive material.
+    pub fn with_lights(mut self, lights: Vec<Box<dyn Light>>) -> Self {
+        self.lights = lights;
+        self
+    }
+
+    /// Set the light arriving along rays that leave the scene, which is otherwise black. It is
+    /// sampled directly alongside the other lights.
+    pub fn with_environment(mut self, environment: Environment) -> Self {
+        self.environment = Some(environment);
+        self
+    }
+
+    /// Return the radiance arriving along `direction` from beyond the scene geometry.
+    pub fn background(&self, direction: Vector3) -> Color {
+        match &self.environment {
+            Some(environment) => environment.radiance(direction),
+            None => Color::zeros(),
+        }
+    }
+
+    /// Return the number of lights that `sample_light` chooses among.
+    fn light_count(&self) -> usize {
+        self.lights.len() + self.environment.is_some() as usize
+    }
+
+    /// Return the material referred to by `id`.
+    pub fn material(&self, id: MaterialId) -> &dyn Material {
+        self.materials[id.0].as_ref()
+    }
+
+    /// Return the closest intersection of `ray` with the scene geometry.
+    pub fn hit(&self, ray: &Ray) -> Option<HitRecord> {
+        self.objects.hit(ray, T_MIN, f32::INFINITY)
+    }
+
+    /// Return the closest intersection of `ray` with the scene geometry, adding the work done to
+    /// find it to `stats`.
+    pub fn hit_counted(&self, ray: &Ray, stats: &mut TraversalStats) -> Option<HitRecord> {
+        self.objects.hit_counted(ray, T_MIN, f32::INFINITY, stats)
+    }
+
+    /// Return whether nothing blocks the segment leaving `origin` along the unit `direction` for
+    /// `distance` at `time`.
+    pub fn visible(&self, origin: Vector3, direction: Vector3, distance: f32, time: f32) -> bool {
+        let shadow = Ray::with_time(origin, direction, time);
+        self.objects
+            .hit(&shadow, T_MIN, distance * (1.0 - T_MIN))
+            .is_none()
+    }
+
+    /// Sample a uniformly chosen light from `origin`, or return `None` if the world has no lights
+    /// or the chosen one contributes nothing there. The sample's density accounts for the choice
+    /// of light.
+    pub fn sample_light(&self, origin: Vector3, rng: &mut Pcg32) -> Option<LightSample> {
+        let count = self.light_count();
+        if count == 0 {
+            return None;
+        }
+        let index = ((rng.next_f32() * count as f32) as usize).min(count - 1);
+        let light: &dyn Light = match self.lights.get(index) {
+            Some(light) => light.as_ref(),
+            None => self.environment.as_ref()?,
+        };
+        let mut sample = light.sample(origin, rng)?;
+        sample.pdf /= count as f32;
+        Some(sample)
+    }
+
+    /// Return the density, in solid angle, with which `sample_light` picks `direction` from
+    /// `origin` toward a light in the scene, leaving out the environment behind it.
+    pub fn light_pdf(&self, origin: Vector3, direction: Vector3) -> f32 {
+        if self.lights.is_empty() {
+            return 0.0;
+        }
+        let sum: f32 = self
+            .lights
+            .iter()
+            .map(|light| light.pdf(origin, direction))
+            .sum();
+        sum / self.light_count() as f32
+    }
+
+    /// Return the density, in solid angle, with which `sample_light` picks `direction` toward
+    /// the environment.
+    pub fn environment_pdf(&self, direction: Vector3) -> f32 {
+        match &self.environment {
+            Some(environment) => {
+                environment.pdf(Vector3::zeros(), direction) / self.light_count() as f32
+            }
+            None => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::light::{AreaLight, PointLight};
+    use crate::material::DiffuseLight;
+    use crate::shapes::Sphere;
+    use crate::texture::SolidColor;
+    use crate::vector::Color;
+
+    #[test]
+    fn test_light_sampling() {
+        let light = || Sphere::new(Vector3::new(0.0, 0.0, -4.0), 1.0, MaterialId(0));
+        let materials: Vec<Box<dyn Material>> =
+            vec![Box::new(DiffuseLight::new(Color::new(1.0, 2.0, 3.0)))];
+        let world = World::new(light(), materials);
+        let mut rng = Pcg32::new(0, 0);
+        assert!(world.sample_light(Vector3::zeros(), &mut rng).is_none());
+        assert_eq!(
+            0.0,
+            world.light_pdf(Vector3::zeros(), Vector3::new(0.0, 0.0, -1.0))
+        );
+
+        let emit = Arc::new(SolidColor::new(Color::new(1.0, 2.0, 3.0)));
+        let world = world.with_lights(vec![
+            Box::new(AreaLight::new(Box::new(light()), emit)),
+            Box::new(PointLight::new(Vector3::new(0.0, 0.0, 4.0), Color::ones())),
+        ]);
+        let mut area_samples = 0;
+        for _ in 0..100 {
+            let sample = world.sample_light(Vector3::zeros(), &mut rng).unwrap();
+            if sample.delta {
+                assert_eq!(0.5, sample.pdf, "Expected the choice of light in the pdf.");
+                assert!(world.visible(Vector3::zeros(), sample.direction, sample.distance, 0.0));
+                continue;
+            }
+            area_samples += 1;
+            let hit = world
+                .hit(&Ray::new(Vector3::zeros(), sample.direction))
+                .unwrap();
+            assert_eq!(MaterialId(0), hit.material);
+            assert!(
+                (world.light_pdf(Vector3::zeros(), sample.direction) - sample.pdf).abs() < 1e-3
+            );
+            // The light's own surface does not occlude it.
+            assert!(world.visible(Vector3::zeros(), sample.direction, sample.distance, 0.0));
+        }
+        assert!(area_samples > 0);
+        assert_eq!(
+            0.0,
+            world.light_pdf(Vector3::zeros(), Vector3::new(0.0, 0.0, 1.0))
+        );
+        // Seen from behind the sphere, the point light is hidden.
+        let behind = Vector3::new(0.0, 0.0, -6.0);
+        assert!(!world.visible(behind, Vector3::new(0.0, 0.0, 1.0), 10.0, 0.0));
+    }
+}
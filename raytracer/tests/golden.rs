@@ -0,0 +1,116 @@
+//! Golden-image regression tests. Each scene `tests/golden/<name>.json` is rendered and compared
+//! with its reference image `tests/golden/<name>.png`, as displayed with the default settings.
+//!
+//! Run them with `cargo test --test golden`. When a render strays from its reference beyond the
+//! tolerance, it is written under `target/tmp/golden` together with an amplified image of the
+//! differences, `<name>.diff.png`.
+//!
+//! After a change that is meant to alter the renders, look over the new images and bless them as
+//! the references with `UPDATE_GOLDEN=1 cargo test --test golden`.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use raytracer::framebuffer::{DisplaySettings, Framebuffer};
+use raytracer::golden::{display_encoded, ImageDiff, Tolerance};
+use raytracer::renderer::Renderer;
+use raytracer::scene::Scene;
+
+/// Render the golden scene `name` and compare it with its reference, or replace the reference if
+/// `UPDATE_GOLDEN` is set.
+fn check(name: &str) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let scene = Scene::load(dir.join(format!("{}.json", name)))
+        .unwrap_or_else(|err| panic!("{}: {}", name, err));
+    let world = scene
+        .build_world()
+        .unwrap_or_else(|err| panic!("{}: {}", name, err));
+    let image = Renderer::new(scene.render.clone()).render(&world, &scene.camera());
+
+    let settings = DisplaySettings::default();
+    let reference_path = dir.join(format!("{}.png", name));
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        image.save(&reference_path, &settings).unwrap();
+        eprintln!("Wrote {}", reference_path.display());
+        return;
+    }
+    let reference = Framebuffer::load(&reference_path).unwrap_or_else(|err| {
+        panic!(
+            "{}: {}. Run with UPDATE_GOLDEN=1 to create it.",
+            reference_path.display(),
+            err
+        )
+    });
+
+    let diff = ImageDiff::new(&reference, &display_encoded(&image, &settings));
+    let tolerance = Tolerance::default();
+    if diff.as_ref().is_some_and(|diff| diff.within(&tolerance)) {
+        return;
+    }
+
+    let out = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    fs::create_dir_all(&out).unwrap();
+    let render_path = out.join(format!("{}.png", name));
+    image.save(&render_path, &settings).unwrap();
+    let diff = match diff {
+        Some(diff) => diff,
+        None => panic!(
+            "{} is {}x{}, but its reference is {}x{}. Wrote the render to {}.",
+            name,
+            image.width(),
+            image.height(),
+            reference.width(),
+            reference.height(),
+            render_path.display()
+        ),
+    };
+    // The differences are data rather than a picture, so they are written without gamma.
+    let linear = DisplaySettings {
+        gamma: 1.0,
+        ..DisplaySettings::default()
+    };
+    let diff_path = out.join(format!("{}.diff.png", name));
+    diff.image().save(&diff_path, &linear).unwrap();
+    panic!(
+        "{} differs from its reference: RMSE {:.4} (at most {}), RMSE over blocks {:.4} (at \
+         most {}), {:.2}% of pixels off by more than {} (at most {:.2}%), largest difference \
+         {:.3}. Wrote the render to {} and the differences to {}.",
+        name,
+        diff.rmse,
+        tolerance.rmse,
+        diff.block_rmse,
+        tolerance.block_rmse,
+        100.0 * diff.outliers(tolerance.pixel),
+        tolerance.pixel,
+        100.0 * tolerance.outliers,
+        diff.max_error,
+        render_path.display(),
+        diff_path.display()
+    );
+}
+
+#[test]
+fn test_materials() {
+    check("materials");
+}
+
+#[test]
+fn test_shapes() {
+    check("shapes");
+}
+
+#[test]
+fn test_media() {
+    check("media");
+}
+
+#[test]
+fn test_sky() {
+    check("sky");
+}
+
+#[test]
+fn test_motion() {
+    check("motion");
+}